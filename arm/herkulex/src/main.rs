@@ -5,9 +5,7 @@
 use cortex_m_rt::entry;
 use cortex_m_semihosting::hprintln;
 use drs_0x01::Rotation::Clockwise;
-use drs_0x01::Servo;
-use nb::block; // The runtime
-               //use embedded_hal::digital::v2::OutputPin; // the `set_high/low`function
+//use embedded_hal::digital::v2::OutputPin; // the `set_high/low`function
 
 #[allow(unused_imports)]
 use panic_halt;
@@ -25,7 +23,7 @@ use stm32f1xx_hal::{
 };
 
 use crate::motors::communication::Communication;
-use crate::motors::Motors;
+use herkulex_driver::Motors;
 
 pub mod motors;
 
@@ -75,44 +73,54 @@ fn main() -> ! {
     let (mut tx, rx) = serial.split();
     let mut delay = cp.SYST.delay(&clocks_serial);
 
-    hprintln!("Debut");
-    let communication = Communication::new(&mut tx, rx);
-    hprintln!("Communication créée");
+    // The semihosting output is only there with a debugger attached, it can fail
+    hprintln!("Debut").ok();
+    let timer = dp.TIM2.counter_us(&clocks_serial);
+    let communication = Communication::new(&mut tx, rx, timer);
+    hprintln!("Communication créée").ok();
 
     let motors = Motors::new(communication);
-    hprintln!("Motors créé");
+    hprintln!("Motors créé").ok();
 
     let motor0 = motors.new_motor(0x00);
     // id = 0
-    hprintln!("Motor0 créé");
+    hprintln!("Motor0 créé").ok();
 
     let motor2 = motors.new_motor(0x02);
     // id = 2
-    hprintln!("Motor2 créé");
+    hprintln!("Motor2 créé").ok();
 
     motor0.reboot();
     motor2.reboot();
-    hprintln!("servos redémarrés");
+    hprintln!("servos redémarrés").ok();
     delay.delay_ms(400_u16);
 
     motor0.enable_torque();
     motor2.enable_torque();
-    hprintln!("servos torque activé");
+    hprintln!("servos torque activé").ok();
     delay.delay_ms(100_u16);
 
-    hprintln!("Init fini");
+    hprintln!("Init fini").ok();
 
-    hprintln!("Demande id à motor0");
-    let motor0_stat = motor0.stat();
-    hprintln!("{:?}", motor0_stat);
-    let motor2_stat = motor2.stat();
-    hprintln!("{:?}", motor2_stat);
+    hprintln!("Demande id à motor0").ok();
+    match motor0.stat() {
+        Ok(status) => hprintln!("{:?}", status).ok(),
+        Err(e) => hprintln!("stat servo0 : {:?}", e).ok(),
+    };
+    match motor2.stat() {
+        Ok(status) => hprintln!("{:?}", status).ok(),
+        Err(e) => hprintln!("stat servo2 : {:?}", e).ok(),
+    };
 
     loop {
-        let t0 = motor0.get_temperature();
-        hprintln!("temp servo0 : {:?}", t0);
-        let t2 = motor2.get_temperature();
-        hprintln!("temp servo2 : {:?}", t2);
+        match motor0.get_temperature() {
+            Ok(t0) => hprintln!("temp servo0 : {}", t0).ok(),
+            Err(e) => hprintln!("temp servo0 : {:?}", e).ok(),
+        };
+        match motor2.get_temperature() {
+            Ok(t2) => hprintln!("temp servo2 : {}", t2).ok(),
+            Err(e) => hprintln!("temp servo2 : {:?}", e).ok(),
+        };
 
         motor0.set_speed(512, Clockwise);
         motor2.set_speed(0, Clockwise);
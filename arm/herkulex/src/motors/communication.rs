@@ -1,100 +1,111 @@
+use core::cell::RefCell;
+use cortex_m::interrupt::Mutex;
 use drs_0x01::builder::HerkulexMessage;
 use embedded_hal::serial::{Read, Write};
+use heapless::spsc::{Consumer, Producer, Queue};
+use herkulex_driver::communication::HerkulexCommunication;
+use herkulex_driver::error::HerkulexError;
+use herkulex_driver::receiver::{Frame, FrameReceiver};
 use nb::block;
 use stm32f1xx_hal::pac;
 use stm32f1xx_hal::serial::{Rx, Tx};
-use stm32f1xx_hal::{pac::interrupt, pac::USART1};
+use stm32f1xx_hal::time::{us, MicroSeconds};
+use stm32f1xx_hal::timer::CounterUs;
+use stm32f1xx_hal::{pac::interrupt, pac::TIM2, pac::USART1};
 
-const BUFF_SIZE: usize = 20;
-static mut BUFF_INDEX: usize = 0;
-static mut BUFFER: &mut [u8; BUFF_SIZE] = &mut [0; BUFF_SIZE];
+/// Frames received and not read yet, one less than the size of the queue
+const QUEUE_SIZE: usize = 4;
 
-static mut RX: Option<Rx<USART1>> = None;
+/// A reply takes about 1ms at 115200 bauds, plus the processing time of the servo
+const REPLY_TIMEOUT: MicroSeconds = us(5_000);
 
-pub struct Communication<'a> {
-    tx: &'a mut Tx<USART1, u8>,
+/// What the USART interrupt needs to receive the frames
+struct Reception {
+    rx: Rx<USART1>,
+    receiver: FrameReceiver,
+    frames: Producer<'static, Frame, QUEUE_SIZE>,
 }
 
-pub trait HerkulexCommunication {
-    fn send_message(&mut self, msg: HerkulexMessage);
+static RECEPTION: Mutex<RefCell<Option<Reception>>> = Mutex::new(RefCell::new(None));
 
-    fn read_message(&self) -> [u8; BUFF_SIZE];
+pub struct Communication<'a> {
+    tx: &'a mut Tx<USART1, u8>,
+    timer: CounterUs<TIM2>,
+    frames: Consumer<'static, Frame, QUEUE_SIZE>,
 }
 
 impl<'a> Communication<'a> {
-    pub fn new(tx: &'a mut Tx<USART1, u8>, mut rx: Rx<USART1, u8>) -> Communication<'a> {
-        let c = Communication { tx };
+    /// `timer` measures the reply timeout. Only one `Communication` can be created.
+    pub fn new(
+        tx: &'a mut Tx<USART1, u8>,
+        mut rx: Rx<USART1, u8>,
+        timer: CounterUs<TIM2>,
+    ) -> Communication<'a> {
+        let queue = cortex_m::singleton!(: Queue<Frame, QUEUE_SIZE> = Queue::new()).unwrap();
+        let (producer, consumer) = queue.split();
+
+        rx.listen();
+        cortex_m::interrupt::free(|cs| {
+            RECEPTION.borrow(cs).replace(Some(Reception {
+                rx,
+                receiver: FrameReceiver::new(),
+                frames: producer,
+            }));
+        });
 
         unsafe {
             pac::NVIC::unmask(pac::Interrupt::USART1);
         }
 
-        rx.listen();
-        rx.listen_idle();
-        cortex_m::interrupt::free(|_| unsafe {
-            RX.replace(rx);
-        });
-        c
+        Communication {
+            tx,
+            timer,
+            frames: consumer,
+        }
     }
 }
 
 impl<'a> HerkulexCommunication for Communication<'a> {
     fn send_message(&mut self, msg: HerkulexMessage) {
+        while self.frames.dequeue().is_some() {}
         for b in &msg {
             block!(self.tx.write(*b)).unwrap();
         }
     }
 
-    fn read_message(&self) -> [u8; BUFF_SIZE] {
-        cortex_m::asm::wfi();
-
-        let mut received_message: [u8; BUFF_SIZE] = [0; BUFF_SIZE];
-        for i in 0..BUFF_SIZE {
-            unsafe {
-                received_message[i] = BUFFER[i];
+    fn read_message(&mut self) -> Result<Frame, HerkulexError> {
+        // Can't fail, the timeout fits in the timer
+        self.timer.start(REPLY_TIMEOUT).unwrap();
+        loop {
+            if let Some(frame) = self.frames.dequeue() {
+                let _ = self.timer.cancel();
+                return Ok(frame);
+            }
+            if self.timer.wait().is_ok() {
+                return Err(HerkulexError::Timeout);
             }
         }
-        received_message
     }
 }
 
 #[interrupt]
 fn USART1() {
-    // When a packet is received, there is at least 3 bytes : header, header, packet size
-    let mut packet_size = 3;
-
-    unsafe {
-        cortex_m::interrupt::free(|_| {
-            if let Some(rx) = RX.as_mut() {
-                // If it received a packet and we have not read it entirely yet
-                while rx.is_rx_not_empty() || BUFF_INDEX < packet_size {
-                    // Read the byte
-                    if let Ok(w) = rx.read() {
-                        BUFFER[BUFF_INDEX] = w; // Fill the buffer
-
-                        // If we read the packet size in the received packet
-                        // it updates our packet_size to read all bytes from the received packet
-                        if BUFF_INDEX == 2 {
-                            packet_size = w as usize;
+    cortex_m::interrupt::free(|cs| {
+        if let Some(reception) = RECEPTION.borrow(cs).borrow_mut().as_mut() {
+            loop {
+                match reception.rx.read() {
+                    Ok(byte) => {
+                        // The invalid frames are dropped, the request is sent again on timeout
+                        if let Ok(Some(frame)) = reception.receiver.push(byte) {
+                            // Full when nobody reads the replies, the new ones are lost
+                            let _ = reception.frames.enqueue(frame);
                         }
-
-                        // If the buffer is full, it rewrites at the beginning
-                        if BUFF_INDEX >= BUFF_SIZE - 1 {
-                            BUFF_INDEX = 0;
-                        }
-
-                        BUFF_INDEX += 1;
                     }
-
-                    rx.listen_idle();
-                }
-
-                // Reset the buffer index position for the next packet
-                if rx.is_idle() {
-                    rx.unlisten_idle();
-                    BUFF_INDEX = 0;
+                    Err(nb::Error::WouldBlock) => break,
+                    // Overrun, noise... the frame being received is incomplete
+                    Err(nb::Error::Other(_)) => reception.receiver.reset(),
                 }
             }
-        })
-    }
+        }
+    });
 }
@@ -0,0 +1,180 @@
+//! USART1 transport using DMA1, so the CPU isn't busy during the servo traffic.
+//!
+//! A request is copied in a static buffer and sent by the TX channel (4), `send_message`
+//! returns as soon as the transfer starts. The RX channel (5) writes the received bytes in a
+//! circular buffer, which is given to the `FrameReceiver` when the USART sees the line idle
+//! after a packet. No interrupt is used.
+
+use core::sync::atomic::{self, Ordering};
+use drs_0x01::builder::HerkulexMessage;
+use embedded_dma::ReadBuffer;
+use herkulex_driver::communication::HerkulexCommunication;
+use herkulex_driver::error::HerkulexError;
+use herkulex_driver::receiver::{Frame, FrameReceiver};
+use stm32f1xx_hal::dma::{dma1, Transfer, WriteDma, R};
+use stm32f1xx_hal::pac::{TIM2, USART1};
+use stm32f1xx_hal::serial::{Rx, RxDma1, Tx, TxDma1};
+use stm32f1xx_hal::time::{us, MicroSeconds};
+use stm32f1xx_hal::timer::CounterUs;
+
+/// Size of `HerkulexMessage`
+const TX_BUFFER_SIZE: usize = 128;
+/// Larger than a packet, the buffer is read after each one
+const RX_BUFFER_SIZE: usize = 256;
+
+/// A reply takes about 1ms at 115200 bauds, plus the processing time of the servo
+const REPLY_TIMEOUT: MicroSeconds = us(5_000);
+
+/// A request in the static buffer read by the TX channel
+pub struct TxPacket {
+    buffer: &'static mut [u8; TX_BUFFER_SIZE],
+    len: usize,
+}
+
+unsafe impl ReadBuffer for TxPacket {
+    type Word = u8;
+
+    unsafe fn read_buffer(&self) -> (*const u8, usize) {
+        (self.buffer.as_ptr(), self.len)
+    }
+}
+
+enum Transmitter {
+    Idle(TxDma1, TxPacket),
+    Sending(Transfer<R, TxPacket, TxDma1>),
+}
+
+pub struct DmaCommunication {
+    /// Always `Some` between two calls
+    transmitter: Option<Transmitter>,
+    rx: RxDma1,
+    rx_buffer: &'static mut [u8; RX_BUFFER_SIZE],
+    /// Next byte of `rx_buffer` to give to `receiver`
+    read_index: usize,
+    receiver: FrameReceiver,
+    timer: CounterUs<TIM2>,
+}
+
+impl DmaCommunication {
+    /// `timer` measures the reply timeout. Only one `DmaCommunication` can be created.
+    pub fn new(
+        tx: Tx<USART1>,
+        rx: Rx<USART1>,
+        tx_channel: dma1::C4,
+        rx_channel: dma1::C5,
+        timer: CounterUs<TIM2>,
+    ) -> DmaCommunication {
+        let tx_buffer = cortex_m::singleton!(: [u8; TX_BUFFER_SIZE] = [0; TX_BUFFER_SIZE]).unwrap();
+        let rx_buffer = cortex_m::singleton!(: [u8; RX_BUFFER_SIZE] = [0; RX_BUFFER_SIZE]).unwrap();
+
+        let mut rx = rx.with_dma(rx_channel);
+        // Same configuration as `CircReadDma::circ_read`, which only gives access to the
+        // halves of the buffer
+        // NOTE(unsafe) the address of the data register of USART1
+        let data_register = unsafe { &(*USART1::ptr()).dr as *const _ as u32 };
+        rx.channel.set_peripheral_address(data_register, false);
+        rx.channel
+            .set_memory_address(rx_buffer.as_ptr() as u32, true);
+        rx.channel.set_transfer_length(RX_BUFFER_SIZE);
+        atomic::compiler_fence(Ordering::Release);
+        rx.channel.ch().cr.modify(|_, w| {
+            w.mem2mem()
+                .clear_bit()
+                .pl()
+                .medium()
+                .msize()
+                .bits8()
+                .psize()
+                .bits8()
+                .circ()
+                .set_bit()
+                .dir()
+                .clear_bit()
+        });
+        rx.channel.start();
+
+        DmaCommunication {
+            transmitter: Some(Transmitter::Idle(
+                tx.with_dma(tx_channel),
+                TxPacket {
+                    buffer: tx_buffer,
+                    len: 0,
+                },
+            )),
+            rx,
+            rx_buffer,
+            read_index: 0,
+            receiver: FrameReceiver::new(),
+            timer,
+        }
+    }
+
+    /// Index of `rx_buffer` the RX channel writes next
+    fn write_index(&self) -> usize {
+        // The channel counts down to 1 and reloads
+        (RX_BUFFER_SIZE - self.rx.channel.get_ndtr() as usize) % RX_BUFFER_SIZE
+    }
+
+    /// True if the line became idle since the last call, after the end of a packet
+    fn line_idle(&self) -> bool {
+        // NOTE(unsafe) atomic reads of the registers of USART1, like `Rx::is_idle`
+        let usart = unsafe { &*USART1::ptr() };
+        if usart.sr.read().idle().bit_is_clear() {
+            return false;
+        }
+        // Reading SR then DR clears the flag, DR has been read by the DMA
+        let _ = usart.dr.read();
+        true
+    }
+
+    /// Gives the received bytes to the receiver, up to the first frame
+    fn receive(&mut self) -> Option<Frame> {
+        let write_index = self.write_index();
+        atomic::compiler_fence(Ordering::Acquire);
+        while self.read_index != write_index {
+            // NOTE(unsafe) the DMA writes the buffer, the byte at `read_index` is already
+            // written and won't be until the buffer wraps
+            let byte = unsafe { core::ptr::read_volatile(&self.rx_buffer[self.read_index]) };
+            self.read_index = (self.read_index + 1) % RX_BUFFER_SIZE;
+            // The invalid frames are dropped, the request is sent again on timeout
+            if let Ok(Some(frame)) = self.receiver.push(byte) {
+                return Some(frame);
+            }
+        }
+        None
+    }
+}
+
+impl HerkulexCommunication for DmaCommunication {
+    fn send_message(&mut self, msg: HerkulexMessage) {
+        self.read_index = self.write_index();
+        self.receiver.reset();
+
+        let (tx, mut packet) = match self.transmitter.take().unwrap() {
+            Transmitter::Idle(tx, packet) => (tx, packet),
+            Transmitter::Sending(transfer) => {
+                let (packet, tx) = transfer.wait();
+                (tx, packet)
+            }
+        };
+        packet.buffer[..msg.len()].copy_from_slice(&msg);
+        packet.len = msg.len();
+        self.transmitter = Some(Transmitter::Sending(tx.write(packet)));
+    }
+
+    fn read_message(&mut self) -> Result<Frame, HerkulexError> {
+        // Can't fail, the timeout fits in the timer
+        self.timer.start(REPLY_TIMEOUT).unwrap();
+        loop {
+            if self.line_idle() {
+                if let Some(frame) = self.receive() {
+                    let _ = self.timer.cancel();
+                    return Ok(frame);
+                }
+            }
+            if self.timer.wait().is_ok() {
+                return Err(HerkulexError::Timeout);
+            }
+        }
+    }
+}
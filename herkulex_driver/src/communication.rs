@@ -0,0 +1,14 @@
+use crate::error::HerkulexError;
+use crate::receiver::Frame;
+use drs_0x01::builder::HerkulexMessage;
+
+/// Serial link to the servos. A request gets at most one reply, the driver sends it again
+/// when the reply is missing or invalid.
+pub trait HerkulexCommunication {
+    /// Sends a request, the reply of the previous one is discarded
+    fn send_message(&mut self, msg: HerkulexMessage);
+
+    /// Waits for the reply to the last request, `HerkulexError::Timeout` if the servo doesn't
+    /// answer
+    fn read_message(&mut self) -> Result<Frame, HerkulexError>;
+}
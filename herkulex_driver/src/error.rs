@@ -0,0 +1,31 @@
+use crate::response::Status;
+
+/// Why a request can't be built, its reply was refused or a move failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HerkulexError {
+    /// No reply in time, the servo is absent or its ACK policy disables the reply
+    Timeout,
+    /// The reply is shorter than announced by its size byte, or than its content needs
+    Truncated,
+    /// The reply doesn't start with 0xFF 0xFF
+    InvalidHeader,
+    /// The size byte is impossible
+    InvalidSize(u8),
+    /// One of the two checksums doesn't match the content
+    Checksum,
+    /// The reply comes from another servo
+    WrongId { expected: u8, received: u8 },
+    /// The reply answers another command
+    WrongCommand { expected: u8, received: u8 },
+    /// The reply holds another register than the one requested
+    WrongRegister { expected: u8, received: u8 },
+    /// The value of a register doesn't fit the type it is read as, or a value to send is out
+    /// of range
+    InvalidValue(i32),
+    /// No room left for another servo in a jog packet
+    PacketFull,
+    /// The servo reported an overload or a position limit error during a move
+    MoveFailed(Status),
+    /// The move wasn't over after the last poll
+    MoveTimeout,
+}
@@ -0,0 +1,240 @@
+//! S_JOG and I_JOG requests, which command several servos with one packet so they start
+//! moving together.
+//!
+//! S_JOG data: the play time shared by the servos, then LSB, MSB, SET and id of each servo.
+//! I_JOG data: LSB, MSB, SET, id and play time of each servo. The value is a position, or a
+//! speed in continuous rotation. `drs_0x01` only fits 3 servos in a packet, so the packets are
+//! built here.
+
+use crate::error::HerkulexError;
+use crate::response::{checksums, BROADCAST_ID, HEADER, I_JOG, MIN_PACKET_SIZE, S_JOG};
+use drs_0x01::builder::HerkulexMessage;
+use drs_0x01::Rotation;
+use heapless::Vec;
+
+/// Bits of the SET byte
+pub const JOG_STOP: u8 = 0x01;
+pub const JOG_CONTINUOUS: u8 = 0x02;
+pub const JOG_INVALID: u8 = 0x20;
+/// Set in the value of a speed for the clockwise rotation
+pub const SPEED_CLOCKWISE: u16 = 0x4000;
+/// Largest position and speed
+pub const MAX_JOG_VALUE: u16 = 0x03FF;
+
+/// Servos in a packet, limited by the size of `HerkulexMessage`
+pub const MAX_S_JOG_SERVOS: usize = 30;
+pub const MAX_I_JOG_SERVOS: usize = 24;
+
+/// Colours of the LED, mixed when several are on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Led {
+    pub green: bool,
+    pub blue: bool,
+    pub red: bool,
+}
+
+impl Led {
+    pub const OFF: Led = Led {
+        green: false,
+        blue: false,
+        red: false,
+    };
+    pub const GREEN: Led = Led {
+        green: true,
+        ..Led::OFF
+    };
+    pub const BLUE: Led = Led {
+        blue: true,
+        ..Led::OFF
+    };
+    pub const RED: Led = Led {
+        red: true,
+        ..Led::OFF
+    };
+
+    /// Bits of the LED in the SET byte
+    fn bits(self) -> u8 {
+        u8::from(self.green) << 2 | u8::from(self.blue) << 3 | u8::from(self.red) << 4
+    }
+}
+
+/// Command of one servo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Jog {
+    id: u8,
+    value: u16,
+    set: u8,
+}
+
+impl Jog {
+    fn position(id: u8, position: u16, led: Led) -> Result<Jog, HerkulexError> {
+        if position > MAX_JOG_VALUE {
+            return Err(HerkulexError::InvalidValue(position.into()));
+        }
+        Ok(Jog {
+            id,
+            value: position,
+            set: led.bits(),
+        })
+    }
+
+    fn speed(id: u8, speed: u16, rotation: Rotation, led: Led) -> Result<Jog, HerkulexError> {
+        if speed > MAX_JOG_VALUE {
+            return Err(HerkulexError::InvalidValue(speed.into()));
+        }
+        let direction = match rotation {
+            Rotation::Clockwise => SPEED_CLOCKWISE,
+            Rotation::CounterClockwise => 0,
+        };
+        Ok(Jog {
+            id,
+            value: speed | direction,
+            set: JOG_CONTINUOUS | led.bits(),
+        })
+    }
+
+    fn stop(id: u8, led: Led) -> Jog {
+        Jog {
+            id,
+            value: 0,
+            set: JOG_STOP | led.bits(),
+        }
+    }
+
+    fn bytes(&self) -> [u8; 4] {
+        let [lsb, msb] = self.value.to_le_bytes();
+        [lsb, msb, self.set, self.id]
+    }
+}
+
+/// Sent to the broadcast id, so no servo replies
+fn build(command: u8, data: &[u8]) -> HerkulexMessage {
+    let size = (MIN_PACKET_SIZE + data.len()) as u8;
+    let (checksum1, checksum2) = checksums(size, BROADCAST_ID, command, data);
+    let mut message = HerkulexMessage::new();
+    message.extend([
+        HEADER,
+        HEADER,
+        size,
+        BROADCAST_ID,
+        command,
+        checksum1,
+        checksum2,
+    ]);
+    message.extend(data.iter().copied());
+    message
+}
+
+/// Servos reaching their goal at the same time, after `playtime` ticks of 11.2ms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SJog {
+    playtime: u8,
+    jogs: Vec<Jog, MAX_S_JOG_SERVOS>,
+}
+
+impl SJog {
+    pub fn new(playtime: u8) -> Self {
+        SJog {
+            playtime,
+            jogs: Vec::new(),
+        }
+    }
+
+    /// Moves the servo `id` to `position`, between 0 and 1023
+    pub fn position(
+        &mut self,
+        id: u8,
+        position: u16,
+        led: Led,
+    ) -> Result<&mut Self, HerkulexError> {
+        self.push(Jog::position(id, position, led)?)
+    }
+
+    /// Turns the servo `id` continuously, `speed` is the PWM between 0 and 1023
+    pub fn speed(
+        &mut self,
+        id: u8,
+        speed: u16,
+        rotation: Rotation,
+        led: Led,
+    ) -> Result<&mut Self, HerkulexError> {
+        self.push(Jog::speed(id, speed, rotation, led)?)
+    }
+
+    /// Stops the servo `id` where it is
+    pub fn stop(&mut self, id: u8, led: Led) -> Result<&mut Self, HerkulexError> {
+        self.push(Jog::stop(id, led))
+    }
+
+    fn push(&mut self, jog: Jog) -> Result<&mut Self, HerkulexError> {
+        self.jogs.push(jog).map_err(|_| HerkulexError::PacketFull)?;
+        Ok(self)
+    }
+
+    pub fn build(&self) -> HerkulexMessage {
+        let mut data: Vec<u8, { 1 + 4 * MAX_S_JOG_SERVOS }> = Vec::new();
+        // Can't fail, the data is sized for the servos
+        data.push(self.playtime).unwrap();
+        for jog in &self.jogs {
+            data.extend_from_slice(&jog.bytes()).unwrap();
+        }
+        build(S_JOG, &data)
+    }
+}
+
+/// Servos with their own play time, in ticks of 11.2ms
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IJog {
+    jogs: Vec<(Jog, u8), MAX_I_JOG_SERVOS>,
+}
+
+impl IJog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the servo `id` to `position`, between 0 and 1023
+    pub fn position(
+        &mut self,
+        id: u8,
+        position: u16,
+        led: Led,
+        playtime: u8,
+    ) -> Result<&mut Self, HerkulexError> {
+        self.push(Jog::position(id, position, led)?, playtime)
+    }
+
+    /// Turns the servo `id` continuously, `speed` is the PWM between 0 and 1023
+    pub fn speed(
+        &mut self,
+        id: u8,
+        speed: u16,
+        rotation: Rotation,
+        led: Led,
+        playtime: u8,
+    ) -> Result<&mut Self, HerkulexError> {
+        self.push(Jog::speed(id, speed, rotation, led)?, playtime)
+    }
+
+    /// Stops the servo `id` where it is
+    pub fn stop(&mut self, id: u8, led: Led) -> Result<&mut Self, HerkulexError> {
+        self.push(Jog::stop(id, led), 0)
+    }
+
+    fn push(&mut self, jog: Jog, playtime: u8) -> Result<&mut Self, HerkulexError> {
+        self.jogs
+            .push((jog, playtime))
+            .map_err(|_| HerkulexError::PacketFull)?;
+        Ok(self)
+    }
+
+    pub fn build(&self) -> HerkulexMessage {
+        let mut data: Vec<u8, { 5 * MAX_I_JOG_SERVOS }> = Vec::new();
+        for (jog, playtime) in &self.jogs {
+            // Can't fail, the data is sized for the servos
+            data.extend_from_slice(&jog.bytes()).unwrap();
+            data.push(*playtime).unwrap();
+        }
+        build(I_JOG, &data)
+    }
+}
@@ -0,0 +1,66 @@
+//! Driver of the Herkulex DRS-0101 servos, independent of the hardware. The serial link is
+//! given through `HerkulexCommunication`, the firmwares implement it over their USART.
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+use crate::communication::HerkulexCommunication;
+use crate::jog::{IJog, SJog};
+use crate::motor::Motor;
+use core::cell::RefCell;
+use heapless::Vec;
+
+pub mod communication;
+pub mod error;
+pub mod jog;
+pub mod motor;
+pub mod receiver;
+pub mod registers;
+pub mod response;
+#[cfg(any(test, feature = "std"))]
+pub mod sim;
+pub mod units;
+
+#[cfg(test)]
+mod tests;
+
+pub struct Motors<Comm: HerkulexCommunication> {
+    communication: RefCell<Comm>,
+}
+
+impl<Comm: HerkulexCommunication> Motors<Comm> {
+    pub fn new(comm: Comm) -> Motors<Comm> {
+        Motors {
+            communication: RefCell::new(comm),
+        }
+    }
+
+    pub fn new_motor(&self, id: u8) -> Motor<'_, Comm> {
+        Motor::new(id, &self.communication)
+    }
+
+    /// Ids among `ids` of the servos which answer a STAT, in the order of `ids`: `0..BROADCAST_ID`
+    /// scans the whole line. An id nobody answers costs the timeout of every read attempt, and
+    /// the servos with an ACK policy of 0 are never found. Stops after `N` servos.
+    pub fn scan<const N: usize>(&self, ids: impl IntoIterator<Item = u8>) -> Vec<u8, N> {
+        let mut found = Vec::new();
+        for id in ids {
+            if found.is_full() {
+                break;
+            }
+            if self.new_motor(id).stat().is_ok() {
+                // Can't fail, there is room left
+                found.push(id).ok();
+            }
+        }
+        found
+    }
+
+    /// Moves several servos with one packet, they reach their goals together
+    pub fn s_jog(&self, jog: &SJog) {
+        self.communication.borrow_mut().send_message(jog.build());
+    }
+
+    /// Moves several servos with one packet, each with its play time
+    pub fn i_jog(&self, jog: &IJog) {
+        self.communication.borrow_mut().send_message(jog.build());
+    }
+}
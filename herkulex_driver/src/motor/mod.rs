@@ -1,13 +1,29 @@
-use crate::motors::communication::HerkulexCommunication;
-use core::cell::RefCell;
-use drs_0x01::{
-    ReadableEEPAddr, ReadableRamAddr, Rotation, Servo, WritableEEPAddr, WritableRamAddr,
-};
+use crate::communication::HerkulexCommunication;
+use crate::error::HerkulexError;
+use crate::jog::{Led, SJog};
+use crate::registers::{eep, ram, Register};
+use crate::response::status_detail::{INPOSITION, MOVING};
+use crate::response::status_error::{EXCEED_POSITION_LIMIT, OVERLOAD};
+use crate::response::{check_reply, Reply, Status};
+use core::cell::{Cell, RefCell};
+use drs_0x01::builder::HerkulexMessage;
+use drs_0x01::{Rotation, Servo, WritableEEPAddr, WritableRamAddr};
 // use drs_0x01::*;
 
+mod units;
+
+/// Attempts of a read before giving up
+const MAX_ATTEMPTS: usize = 3;
+/// Ticks of 11.2ms between two polls of `wait_until_in_position`
+const POLL_PERIOD: u32 = 1;
+
 pub struct Motor<'a, Comm: HerkulexCommunication> {
     communication: &'a RefCell<Comm>,
     id: u8,
+    /// Goal of the last move sent by this `Motor`, so `poll_move` doesn't read it
+    goal: Cell<Option<u16>>,
+    /// In Position Margin, read by the first `poll_move`
+    margin: Cell<Option<u8>>,
 }
 
 impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
@@ -16,7 +32,9 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     pub fn new(id: u8, communication: &'a RefCell<Comm>) -> Motor<'a, Comm> {
         Motor {
             id,
-            communication: communication,
+            communication,
+            goal: Cell::new(None),
+            margin: Cell::new(None),
         }
     }
 
@@ -56,6 +74,7 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
         self.communication
             .borrow_mut()
             .send_message(Servo::new(self.id).set_speed(speed, rot));
+        self.goal.set(None);
     }
 
     /// Set a position.
@@ -64,31 +83,108 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
         self.communication
             .borrow_mut()
             .send_message(Servo::new(self.id).set_position(position));
+        self.goal.set(Some(position));
+    }
+
+    /// Move to a position in `playtime` ticks of 11.2ms, with the LED in the colour `led`.
+    /// The position is between 0 and 1023, the limits of the servo (21 to 1002 by default) are
+    /// checked by the servo itself and reported by `poll_move`.
+    pub fn move_to(&self, position: u16, playtime: u8, led: Led) -> Result<(), HerkulexError> {
+        let mut jog = SJog::new(playtime);
+        jog.position(self.id, position, led)?;
+        self.communication.borrow_mut().send_message(jog.build());
+        self.goal.set(Some(position));
+        Ok(())
+    }
+
+    /// Check if the last move is over.
+    /// The servo must report it in position and not moving, and be within the In Position
+    /// Margin of its goal. An overload or a position limit error ends the move with
+    /// `HerkulexError::MoveFailed`, the errors stay in the status until `clear_errors`.
+    /// A poll reads the status and the position, the goal is the one of the last `move_to` or
+    /// `set_position` of this `Motor`. Poll a move sent with `Motors::s_jog` or `i_jog` with a
+    /// new `Motor`, it reads the goal from the servo.
+    pub fn poll_move(&self) -> Result<bool, HerkulexError> {
+        let status = self.stat()?;
+        if status.error & (OVERLOAD | EXCEED_POSITION_LIMIT) != 0 {
+            return Err(HerkulexError::MoveFailed(status));
+        }
+        if status.detail & MOVING != 0 || status.detail & INPOSITION == 0 {
+            return Ok(false);
+        }
+        let margin = match self.margin.get() {
+            Some(margin) => margin,
+            None => {
+                let margin = self.get_inposition_margin()?;
+                self.margin.set(Some(margin));
+                margin
+            }
+        };
+        let goal = match self.goal.get() {
+            Some(goal) => goal,
+            None => self.get_absolute_goal_position()?,
+        };
+        let position = self.get_absolute_position()?;
+        Ok(position.abs_diff(goal) <= margin.into())
+    }
+
+    /// Poll the move until it is over, for at most `timeout` ticks of 11.2ms.
+    /// `delay` is called between two polls with the ticks to wait.
+    pub fn wait_until_in_position(
+        &self,
+        timeout: u32,
+        mut delay: impl FnMut(u32),
+    ) -> Result<(), HerkulexError> {
+        let mut waited = 0;
+        loop {
+            if self.poll_move()? {
+                return Ok(());
+            }
+            if waited >= timeout {
+                return Err(HerkulexError::MoveTimeout);
+            }
+            let ticks = POLL_PERIOD.min(timeout - waited);
+            delay(ticks);
+            waited += ticks;
+        }
+    }
+
+    /// Send a request and check that the reply answers it.
+    /// The request is sent again when the reply is missing or invalid.
+    fn read(&self, request: HerkulexMessage) -> Result<Reply, HerkulexError> {
+        let mut communication = self.communication.borrow_mut();
+        let mut result = Err(HerkulexError::Timeout);
+        for _ in 0..MAX_ATTEMPTS {
+            communication.send_message(request.clone());
+            result = communication
+                .read_message()
+                .and_then(|reply| check_reply(&request, &reply));
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Read a register, decoded according to its declaration in the register map.
+    fn read_register<T: TryFrom<i32>>(&self, register: Register) -> Result<T, HerkulexError> {
+        let value = register.decode(&self.read(register.request(self.id))?.value)?;
+        T::try_from(value).map_err(|_| HerkulexError::InvalidValue(value))
     }
 
-    // Check that the return array is the size of the buffer
     /// Get the status of the servo.
-    pub fn stat(&self) -> [u8; 20] {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).stat());
-        self.communication.borrow_mut().read_message()
+    pub fn stat(&self) -> Result<Status, HerkulexError> {
+        Ok(self.read(Servo::new(self.id).stat())?.status)
     }
 
     /// Get the ID of the servo in its RAM register.
-    pub fn get_id(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::ID));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_id(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::ID)
     }
 
     /// Get the ID of the servo in its EEP register.
-    pub fn get_id_eep(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).eep_request(ReadableEEPAddr::ID));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_id_eep(&self) -> Result<u8, HerkulexError> {
+        self.read_register(eep::ID)
     }
 
     /// Set the ID of the servo in its RAM register.
@@ -113,11 +209,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// 0 : No reply to any Request Packet
     /// 1 : Only reply to Read CMD
     /// 2 : Reply to all Request Packet
-    pub fn get_ack_policy(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::AckPolicy));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_ack_policy(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::ACK_POLICY)
     }
 
     /// Set the ACK Policy of the servo set in its RAM register.
@@ -125,21 +218,17 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// 0 : No reply to any Request Packet
     /// 1 : Only reply to Read CMD
     /// 2 : Reply to all Request Packet
-    pub fn set_ack_policy(&self, policy: u8) -> u8 {
+    pub fn set_ack_policy(&self, policy: u8) {
         self.communication
             .borrow_mut()
             .send_message(Servo::new(self.id).ram_write(WritableRamAddr::AckPolicy(policy)));
-        self.communication.borrow_mut().read_message()[9]
     }
 
     /// Get the Alarm LED policy of the servo set in its RAM register.
     /// The Alarm LED policy is used when an error is detected.
     /// When LED Policy and Status Error are true, the Alarm LED starts to blink. The blink period can be changed.
-    pub fn get_alarm_led_policy(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::AlarmLEDPolicy));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_alarm_led_policy(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::ALARM_LED_POLICY)
     }
 
     /// Get the Torque Policy of the servo set in its RAM register.
@@ -147,39 +236,27 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// - 0x40 : Break On
     /// - 0x60 : Torque On
     /// - 0x00 : Torque Free
-    pub fn get_torque_policy(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::TorquePolicy));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_torque_policy(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::TORQUE_POLICY)
     }
 
     /// Get the maximum temperature allowed of the servo set in its RAM register.
     /// When the temperature of the servo is greater than the maximum temperature allowed, the 'Exceed Temperature Limit' is thrown.
     /// Default value is 0xDF ( approximately 85 degrees Celcius)
-    pub fn get_max_temp(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::MaxTemperature));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_max_temp(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::MAX_TEMPERATURE)
     }
     /// Get the minimum voltage allowed of the servo set in its RAM register.
     /// When servo input voltage is below the minimum voltage "Exceeded Voltage Limit" error is thrown
     /// Default value is 0x5B ( approximately 6.74V).
-    pub fn get_min_voltage(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::MinVoltage));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_min_voltage(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::MIN_VOLTAGE)
     }
     /// Get max minimum voltage allowed of the servo set in its RAM register.
     /// When servo input voltage exceeds the maximum voltage "Exceeded Voltage Limit" error is thrown.
     /// Default value is 0x89( approximately 10.14V).
-    pub fn get_max_voltage(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::MaxTemperature));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_max_voltage(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::MAX_VOLTAGE)
     }
 
     /// Get the current acceleration ratio of the servo set in its RAM register.
@@ -189,11 +266,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Example : operating time is 100ms and acceleration is 20 => Acceleration time = 100*0.2=20ms.
     /// When the acceleration ratio is 0, speed profile is rectangle
     /// When the acceleration ratio is below 50, velocity profile is triangle.
-    pub fn get_acceleration_ratio(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::AccelerationRatio));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_acceleration_ratio(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::ACCELERATION_RATIO)
     }
 
     /// Set the current acceleration ratio of the servo set in its RAM register.
@@ -212,11 +286,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the current acceleration ratio of the servo set in its RAM register.
     /// Acceleration interval is 11.2ms.
     /// When the value is 254, the maximum acceleration time is 2844ms.
-    pub fn get_max_acceleration(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::MaxAcceleration));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_max_acceleration(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::MAX_ACCELERATION)
     }
 
     /// Set the current acceleration ratio of the servo set in its RAM register.
@@ -230,11 +301,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
 
     /// Get the current dead zone of the servo set in its RAM register.
     /// The dead zone only works within position control (using set_position)
-    pub fn get_dead_zone(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::DeadZone));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_dead_zone(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::DEAD_ZONE)
     }
 
     /// Set the current dead zone of the servo set in its RAM register.
@@ -248,11 +316,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Set the offset of the saturator.
     /// The saturator effect on PWM is similar to having a spring installed near the goal position.
     /// Refer to the page 36 of the manual for further information.
-    pub fn get_saturator_offset(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::SaturatorOffset));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_saturator_offset(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::SATURATOR_OFFSET)
     }
 
     /// Get the offset of the saturator.
@@ -267,12 +332,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the slope of the saturator.
     /// The saturator effect on PWM is similar to having a spring installed near the goal position.
     /// Refer to the page 36 of the manual for further information.
-    pub fn get_saturator_slope(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::SaturatorSlope));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_saturator_slope(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::SATURATOR_SLOPE)
     }
 
     /// Set the slope of the saturator.
@@ -289,11 +350,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the PWM Offset value.
     /// PWM will increase the output by the amount of the offset.
     /// This output could be used to act as a compensator in a system where load is on one side.
-    pub fn get_pwm_offset(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::PWMOffset));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_pwm_offset(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::PWM_OFFSET)
     }
 
     /// Set the PWM Offset value.
@@ -308,11 +366,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the minimum PWM value.
     /// The minimum PWM corresponds to the minimum torque
     /// The values range is 0x00-0xFE
-    pub fn get_min_pwm(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::MinPWM));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_min_pwm(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::MIN_PWM)
     }
 
     /// Set the minimum PWM value.
@@ -327,12 +382,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the minimum PWM value.
     /// The minimum PWM corresponds to the minimum torque
     /// The values range should be 0-1024.
-    pub fn get_max_pwm(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::MaxPWM));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_max_pwm(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::MAX_PWM)
     }
 
     /// Set the minimum PWM value.
@@ -349,12 +400,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the overload PWM Threshold in the RAM register.
     /// Overload PWM Threshold sets overload activation point.
     /// Overload activates when external force is grater than this value.
-    pub fn get_overload_pwm_threshold(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::OverloadPWMThreshold));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_overload_pwm_threshold(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::OVERLOAD_PWM_THRESHOLD)
     }
 
     /// Set the overload PWM Threshold in the RAM register.
@@ -371,12 +418,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the minimum operational angle in the RAM register.
     /// When requested position angle is less than the minimum position value, "Exceed Allowed POT Limit" error is thrown.
     /// Default value is 0x15 ( -159.8 degrees).
-    pub fn get_min_position(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::MinPosition));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_min_position(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::MIN_POSITION)
     }
 
     /// Set the minimum operational angle in the RAM register.
@@ -393,12 +436,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the maximum operational angle in the RAM register.
     /// When requested position angle is less than the maximum position value, "Exceed Allowed POT Limit" error is thrown.
     /// Default value is 0x3EA ( 159.8 degrees).
-    pub fn get_max_position(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::MaxPosition));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_max_position(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::MAX_POSITION)
     }
 
     /// Set the maximum operational angle in the RAM register.
@@ -415,20 +454,16 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the Proportional Gain in the RAM register.
     /// Increasing the proportional gain increases the response time.
     /// If the increase is too large it will result with vibration and overshoot.
-    pub fn get_position_kp(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::PositionKp));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_position_kp(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::POSITION_KP)
     }
 
     /// Set the Proportional Gain in the RAM register.
     /// Increasing the proportional gain increases the response time.
     /// If the increase is too large it will result with vibration and overshoot.
-    pub fn set_position_kp(&self, Kp: u16) {
-        let var1: u8 = (Kp & 0xFF) as u8;
-        let var2: u8 = (Kp >> 8) as u8;
+    pub fn set_position_kp(&self, kp: u16) {
+        let var1: u8 = (kp & 0xFF) as u8;
+        let var2: u8 = (kp >> 8) as u8;
         self.communication
             .borrow_mut()
             .send_message(Servo::new(self.id).ram_write(WritableRamAddr::PositionKp(var1, var2)));
@@ -437,20 +472,16 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the Derivative Gain in the RAM register.
     /// Increasing the derivative gain will suppress the over response from the Proportional gain.
     /// Instability may result if the increase is too large.
-    pub fn get_position_kd(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::PositionKd));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_position_kd(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::POSITION_KD)
     }
 
     /// Set the Derivative Gain in the RAM register.
     /// Increasing the derivative gain will suppress the over response from the Proportional gain.
     /// Instability may result if the increase is too large.
-    pub fn set_position_kd(&self, Kd: u16) {
-        let var1: u8 = (Kd & 0xFF) as u8;
-        let var2: u8 = (Kd >> 8) as u8;
+    pub fn set_position_kd(&self, kd: u16) {
+        let var1: u8 = (kd & 0xFF) as u8;
+        let var2: u8 = (kd >> 8) as u8;
         self.communication
             .borrow_mut()
             .send_message(Servo::new(self.id).ram_write(WritableRamAddr::PositionKd(var1, var2)));
@@ -459,20 +490,16 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the Integral Gain in the RAM register.
     /// Increasing the integral gain will correct small offset in steady state.
     /// Response lag may result if the increase is too large.
-    pub fn get_position_ki(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::PositionKi));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_position_ki(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::POSITION_KI)
     }
 
     /// Set the Integral Gain in the RAM register.
     /// Increasing the integral gain will correct small offset in steady state.
     /// Response lag may result if the increase is too large.
-    pub fn set_position_ki(&self, Ki: u16) {
-        let var1: u8 = (Ki & 0xFF) as u8;
-        let var2: u8 = (Ki >> 8) as u8;
+    pub fn set_position_ki(&self, ki: u16) {
+        let var1: u8 = (ki & 0xFF) as u8;
+        let var2: u8 = (ki >> 8) as u8;
         self.communication
             .borrow_mut()
             .send_message(Servo::new(self.id).ram_write(WritableRamAddr::PositionKi(var1, var2)));
@@ -480,12 +507,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
 
     /// Get the position feedforward first gain in the RAM register.
     /// Position Feedforward first gain is applied to increase servo response time.
-    pub fn get_position_feedforward_1st_gain(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::PositionFFFirstGain));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_position_feedforward_1st_gain(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::POSITION_FF_1ST_GAIN)
     }
 
     /// Set the position feedforward first gain in the RAM register.
@@ -500,12 +523,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
 
     /// Get the position feedforward second gain in the RAM register.
     /// Position Feedforward second gain is applied to increase servo response time.
-    pub fn get_position_feedforward_2nd_gain(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::PositionFFSecondGain));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_position_feedforward_2nd_gain(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::POSITION_FF_2ND_GAIN)
     }
 
     /// Set the position feedforward second gain in the RAM register.
@@ -521,11 +540,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the LED blink period in the RAM register.
     /// Default value is 0x2D (504ms).
     /// 0x01 is equivalent to 11.2ms.
-    pub fn get_led_blink_period(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::LedBlinkPeriod));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_led_blink_period(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::LED_BLINK_PERIOD)
     }
 
     /// Set the LED blink period in the RAM register.
@@ -541,11 +557,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Temperature / Input voltage error check interval.
     /// Error is activated if the Temperature/Voltage error lasts longer than the check interval.
     /// Default value is 0x2D (504ms).
-    pub fn get_adc_fault_detection_period(&self) -> u8 {
-        self.communication.borrow_mut().send_message(
-            Servo::new(self.id).ram_request(ReadableRamAddr::ADCFaultDetectionPeriod),
-        );
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_adc_fault_detection_period(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::ADC_FAULT_DETECTION_PERIOD)
     }
 
     /// Set the ADC Fault Check Period in the RAM register.
@@ -561,11 +574,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the Packet Garbage Check Period in the RAM register.
     /// Incomplete Packet is deleted if it remains longer than the check interval.
     /// Default value is 0x21 (201ms).
-    pub fn get_packet_garbage_detection_period(&self) -> u8 {
-        self.communication.borrow_mut().send_message(
-            Servo::new(self.id).ram_request(ReadableRamAddr::PacketGarbageDetectionPeriod),
-        );
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_packet_garbage_detection_period(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::PACKET_GARBAGE_DETECTION_PERIOD)
     }
 
     /// Set the Packet Garbage Check Period in the RAM register.
@@ -579,11 +589,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
 
     /// Get the Stop Detection Period in the RAM register.
     /// This period corresponds to the time limit which the servo stoppage is measured to determine whether it has stopped.
-    pub fn get_stop_detection_period(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::StopDetectionPeriod));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_stop_detection_period(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::STOP_DETECTION_PERIOD)
     }
 
     /// Set the Stop Detection Period in the RAM register.
@@ -598,11 +605,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the Overload Detection Period in the RAM register.
     /// This period corresponds to the time limit which the servo overload is measure to determine whether an overload has occured.
     /// Default value is 0x96 (1.68s).
-    pub fn get_overload_detection_period(&self) -> u8 {
-        self.communication.borrow_mut().send_message(
-            Servo::new(self.id).ram_request(ReadableRamAddr::OverloadDetectionPeriod),
-        );
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_overload_detection_period(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::OVERLOAD_DETECTION_PERIOD)
     }
 
     /// Set the Overload Detection Period in the RAM register.
@@ -616,11 +620,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
 
     /// Get the Stop Threshold in the RAM register.
     /// The servo is seen as stopped when the position movement is less than the Stop Threshold value.
-    pub fn get_stop_threshold(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::StopThreshold));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_stop_threshold(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::STOP_THRESHOLD)
     }
 
     /// Set the Stop Threshold in the RAM register.
@@ -634,11 +635,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// Get the In Position Margin in the RAM register.
     /// Standard value to determine whether the goal position has been reached.
     /// Goal position is judged to have been reached if the deviation is less than the In Position Margin value.
-    pub fn get_inposition_margin(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::InpositionMargin));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_inposition_margin(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::INPOSITION_MARGIN)
     }
 
     /// Set the In Position Margin in the RAM register.
@@ -647,17 +645,15 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     pub fn set_inposition_margin(&self, margin: u8) {
         self.communication
             .borrow_mut()
-            .send_message(Servo::new(self.id).ram_write(WritableRamAddr::InpositionMargin(margin)))
+            .send_message(Servo::new(self.id).ram_write(WritableRamAddr::InpositionMargin(margin)));
+        self.margin.set(Some(margin));
     }
 
     /// Get the Calibration Difference in the RAM register.
     /// Used to calibrate neutral point.
     /// It is calculated by the formula : Calibrated Pos = Absolute Pos - Calibration Difference
-    pub fn get_calibration_difference(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::CalibrationDifference));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_calibration_difference(&self) -> Result<i8, HerkulexError> {
+        self.read_register(ram::CALIBRATION_DIFFERENCE)
     }
 
     /// Set the Calibration Difference in the RAM register.
@@ -669,18 +665,12 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
         );
     }
 
-    pub fn get_status_error(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::StatusError));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_status_error(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::STATUS_ERROR)
     }
 
-    pub fn get_status_detail(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::StatusDetail));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_status_detail(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::STATUS_DETAIL)
     }
 
     /// Get Torque Control in the RAM register.
@@ -688,11 +678,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// 0x40 Break On
     /// 0x60 Torque On
     /// 0x00 Torque Free
-    pub fn get_torque_control(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::TorqueControl));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_torque_control(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::TORQUE_CONTROL)
     }
 
     /// Set Torque Control in the RAM register.
@@ -712,11 +699,8 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
     /// 0x01 : Green
     /// 0x02 : Blue
     /// 0x04 : Red
-    pub fn get_led_control(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::LEDControl));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_led_control(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::LED_CONTROL)
     }
 
     /// Set LED Control in the RAM regsiter.
@@ -733,91 +717,51 @@ impl<'a, Comm: HerkulexCommunication> Motor<'_, Comm> {
 
     /// Get the Voltage raw data in the RAM register.
     /// Voltage = 0.074 * ADC
-    pub fn get_voltage(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::Voltage));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_voltage(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::VOLTAGE)
     }
 
     /// Get the temperature raw data in the RAM register.
-    pub fn get_temperature(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::Temperature));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_temperature(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::TEMPERATURE)
     }
 
-    ///
-    pub fn get_current_control_mode(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::CurrentControlMode));
-        self.communication.borrow_mut().read_message()[9]
+    /// Get the current control mode in the RAM register.
+    pub fn get_current_control_mode(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::CURRENT_CONTROL_MODE)
     }
 
-    pub fn get_tick(&self) -> u8 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::Tick));
-        self.communication.borrow_mut().read_message()[9]
+    pub fn get_tick(&self) -> Result<u8, HerkulexError> {
+        self.read_register(ram::TICK)
     }
 
-    pub fn get_calibrated_position(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::CalibratedPosition));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_calibrated_position(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::CALIBRATED_POSITION)
     }
 
-    pub fn get_absolute_position(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::AbsolutePosition));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_absolute_position(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::ABSOLUTE_POSITION)
     }
 
     /// Get the Differential Position in the RAM Register.
     /// Show velocity measurement, velocity is measure in 11.2ms intervals.
-    pub fn get_differential_position(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::DifferentialPosition));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_differential_position(&self) -> Result<i16, HerkulexError> {
+        self.read_register(ram::DIFFERENTIAL_POSITION)
     }
 
-    pub fn get_PWM(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::PWM));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_pwm(&self) -> Result<i16, HerkulexError> {
+        self.read_register(ram::PWM)
     }
 
-    pub fn get_absolute_goal_position(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::AbsoluteGoalPosition));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_absolute_goal_position(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::ABSOLUTE_GOAL_POSITION)
     }
 
-    pub fn get_absolute_desired_trajectory_position(&self) -> u16 {
-        self.communication.borrow_mut().send_message(
-            Servo::new(self.id).ram_request(ReadableRamAddr::AbsoluteDesiredTrajectoryPosition),
-        );
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_absolute_desired_trajectory_position(&self) -> Result<u16, HerkulexError> {
+        self.read_register(ram::ABSOLUTE_DESIRED_TRAJECTORY_POSITION)
     }
 
-    pub fn get_desired_velocity(&self) -> u16 {
-        self.communication
-            .borrow_mut()
-            .send_message(Servo::new(self.id).ram_request(ReadableRamAddr::DesiredVelocity));
-        let ans = self.communication.borrow_mut().read_message();
-        (ans[9] + ans[10] << 8) as u16
+    pub fn get_desired_velocity(&self) -> Result<i16, HerkulexError> {
+        self.read_register(ram::DESIRED_VELOCITY)
     }
 }
@@ -0,0 +1,59 @@
+//! Methods of `Motor` in physical units, see `crate::units`.
+
+use super::Motor;
+use crate::communication::HerkulexCommunication;
+use crate::error::HerkulexError;
+use crate::jog::Led;
+use crate::units::{millivolts, Angle, AngularVelocity, TEMPERATURE_TABLE};
+
+impl<Comm: HerkulexCommunication> Motor<'_, Comm> {
+    /// Get the angle of the servo from its centre.
+    pub fn get_angle(&self) -> Result<Angle, HerkulexError> {
+        Ok(Angle::from_position(self.get_absolute_position()?))
+    }
+
+    /// Get the angular velocity, measured over the last tick.
+    pub fn get_angular_velocity(&self) -> Result<AngularVelocity, HerkulexError> {
+        Ok(AngularVelocity::from_differential_position(
+            self.get_differential_position()?,
+        ))
+    }
+
+    /// Get the input voltage in millivolts.
+    pub fn get_voltage_millivolts(&self) -> Result<u16, HerkulexError> {
+        Ok(millivolts(self.get_voltage()?))
+    }
+
+    /// Get the temperature in °C, converted with `TEMPERATURE_TABLE`.
+    pub fn get_temperature_celsius(&self) -> Result<i16, HerkulexError> {
+        TEMPERATURE_TABLE.celsius(self.get_temperature()?)
+    }
+
+    /// Turn continuously at an angular velocity, counter clockwise when it is positive.
+    /// Nothing is sent if it is faster than the servo, see `AngularVelocity::to_speed`.
+    pub fn set_angular_velocity(&self, velocity: AngularVelocity) -> Result<(), HerkulexError> {
+        let (speed, rotation) = velocity.to_speed()?;
+        self.set_speed(speed, rotation);
+        Ok(())
+    }
+
+    /// Move to an angle in `playtime` ticks of 11.2ms, with the LED in the colour `led`.
+    /// Nothing is sent if the angle is out of the limits of the servo.
+    pub fn move_to_angle(&self, angle: Angle, playtime: u8, led: Led) -> Result<(), HerkulexError> {
+        self.move_to(angle.to_position()?, playtime, led)
+    }
+
+    /// Move to an angle at an average velocity, from the current position.
+    /// Nothing is sent if the angle is out of the limits of the servo or if the move is too
+    /// long for a play time (255 ticks, 2.86s).
+    pub fn move_to_angle_at(
+        &self,
+        angle: Angle,
+        velocity: AngularVelocity,
+        led: Led,
+    ) -> Result<(), HerkulexError> {
+        let position = angle.to_position()?;
+        let distance = Angle(Angle::from_position(position).0 - self.get_angle()?.0);
+        self.move_to(position, velocity.playtime(distance)?, led)
+    }
+}
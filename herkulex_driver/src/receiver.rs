@@ -0,0 +1,99 @@
+//! Cuts the bytes received from the servos into frames, without any hardware access so it
+//! can be fed from the USART interrupt as well as from a test.
+//!
+//! The receiver syncs on 0xFF 0xFF, reads the size and keeps the frame only if its checksums
+//! are right. After an invalid size or checksum it waits for the next header.
+
+use crate::error::HerkulexError;
+use crate::response::{checksums, HEADER, MIN_PACKET_SIZE};
+use heapless::Vec;
+
+/// Largest packet of the protocol
+pub const MAX_PACKET_SIZE: usize = 223;
+
+/// A whole packet, header included
+pub type Frame = Vec<u8, MAX_PACKET_SIZE>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header1,
+    Header2,
+    Size,
+    Body,
+}
+
+pub struct FrameReceiver {
+    state: State,
+    frame: Frame,
+}
+
+impl Default for FrameReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameReceiver {
+    pub const fn new() -> Self {
+        FrameReceiver {
+            state: State::Header1,
+            frame: Vec::new(),
+        }
+    }
+
+    /// Drops the frame being received, after a reception error of the UART for instance
+    pub fn reset(&mut self) {
+        self.state = State::Header1;
+        self.frame.clear();
+    }
+
+    /// Handles the next received byte, returns the frame it completes
+    pub fn push(&mut self, byte: u8) -> Result<Option<Frame>, HerkulexError> {
+        match self.state {
+            State::Header1 => {
+                if byte == HEADER {
+                    self.state = State::Header2;
+                }
+            }
+            State::Header2 => {
+                self.state = if byte == HEADER {
+                    State::Size
+                } else {
+                    State::Header1
+                };
+            }
+            State::Size => {
+                // More than two 0xFF, the header ends with the last one
+                if byte == HEADER {
+                    return Ok(None);
+                }
+                if (byte as usize) < MIN_PACKET_SIZE || byte as usize > MAX_PACKET_SIZE {
+                    self.state = State::Header1;
+                    return Err(HerkulexError::InvalidSize(byte));
+                }
+                self.frame.clear();
+                // Can't fail, the frame is empty
+                self.frame
+                    .extend_from_slice(&[HEADER, HEADER, byte])
+                    .unwrap();
+                self.state = State::Body;
+            }
+            State::Body => {
+                // Can't fail, the size is checked above
+                self.frame.push(byte).unwrap();
+                if self.frame.len() == self.frame[2] as usize {
+                    self.state = State::Header1;
+                    let frame = core::mem::take(&mut self.frame);
+                    let (size, id, command) = (frame[2], frame[3], frame[4]);
+                    if checksums(size, id, command, &frame[MIN_PACKET_SIZE..])
+                        != (frame[5], frame[6])
+                    {
+                        return Err(HerkulexError::Checksum);
+                    }
+                    return Ok(Some(frame));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,191 @@
+//! Register maps of the DRS-0101, pages 22 to 25 of the manual.
+//!
+//! Every readable register is declared with its address, its width and how its bits are
+//! read. The values are little endian. `drs_0x01` gets the width of some RAM registers
+//! wrong (voltage, temperature, tick, desired velocity...), so the read requests are built
+//! from this map.
+
+use crate::error::HerkulexError;
+use crate::response::{checksums, EEP_READ, HEADER, MIN_PACKET_SIZE, RAM_READ};
+use drs_0x01::builder::HerkulexMessage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Memory {
+    /// Volatile, loaded from the EEP at boot
+    Ram,
+    /// Persistent, applied at the next reboot
+    Eep,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Register {
+    pub memory: Memory,
+    pub address: u8,
+    /// 1 or 2 bytes
+    pub width: u8,
+    /// Two's complement over the bits of `mask`
+    pub signed: bool,
+    /// Meaningful bits of the value, the others are cleared
+    pub mask: u16,
+}
+
+impl Register {
+    const fn new(memory: Memory, address: u8, width: u8) -> Self {
+        Register {
+            memory,
+            address,
+            width,
+            signed: false,
+            mask: if width == 1 { 0x00FF } else { 0xFFFF },
+        }
+    }
+
+    const fn ram(address: u8, width: u8) -> Self {
+        Register::new(Memory::Ram, address, width)
+    }
+
+    const fn eep(address: u8, width: u8) -> Self {
+        Register::new(Memory::Eep, address, width)
+    }
+
+    const fn signed(self) -> Self {
+        Register {
+            signed: true,
+            ..self
+        }
+    }
+
+    const fn masked(self, mask: u16) -> Self {
+        Register { mask, ..self }
+    }
+
+    /// Read request of the register of the servo `id`
+    pub fn request(&self, id: u8) -> HerkulexMessage {
+        let command = match self.memory {
+            Memory::Ram => RAM_READ,
+            Memory::Eep => EEP_READ,
+        };
+        let data = [self.address, self.width];
+        let size = (MIN_PACKET_SIZE + data.len()) as u8;
+        let (checksum1, checksum2) = checksums(size, id, command, &data);
+        let mut message = HerkulexMessage::new();
+        message.extend([HEADER, HEADER, size, id, command, checksum1, checksum2]);
+        message.extend(data);
+        message
+    }
+
+    /// Value of the register from the data of a read reply
+    pub fn decode(&self, data: &[u8]) -> Result<i32, HerkulexError> {
+        if data.len() != self.width as usize {
+            return Err(HerkulexError::Truncated);
+        }
+        let raw = data
+            .iter()
+            .rev()
+            .fold(0u16, |acc, b| acc << 8 | u16::from(*b))
+            & self.mask;
+        if !self.signed {
+            return Ok(i32::from(raw));
+        }
+        let bits = 16 - self.mask.leading_zeros();
+        let shift = 16 - bits;
+        Ok(i32::from(((raw << shift) as i16) >> shift))
+    }
+}
+
+/// Positions are 10 bits on the DRS-0101, the upper bits aren't specified
+const POSITION_MASK: u16 = 0x03FF;
+
+pub mod ram {
+    use super::{Register, POSITION_MASK};
+
+    pub const ID: Register = Register::ram(0, 1);
+    pub const ACK_POLICY: Register = Register::ram(1, 1);
+    pub const ALARM_LED_POLICY: Register = Register::ram(2, 1);
+    pub const TORQUE_POLICY: Register = Register::ram(3, 1);
+    pub const MAX_TEMPERATURE: Register = Register::ram(5, 1);
+    pub const MIN_VOLTAGE: Register = Register::ram(6, 1);
+    pub const MAX_VOLTAGE: Register = Register::ram(7, 1);
+    pub const ACCELERATION_RATIO: Register = Register::ram(8, 1);
+    pub const MAX_ACCELERATION: Register = Register::ram(9, 1);
+    pub const DEAD_ZONE: Register = Register::ram(10, 1);
+    pub const SATURATOR_OFFSET: Register = Register::ram(11, 1);
+    pub const SATURATOR_SLOPE: Register = Register::ram(12, 2);
+    pub const PWM_OFFSET: Register = Register::ram(14, 1);
+    pub const MIN_PWM: Register = Register::ram(15, 1);
+    pub const MAX_PWM: Register = Register::ram(16, 2);
+    pub const OVERLOAD_PWM_THRESHOLD: Register = Register::ram(18, 2);
+    pub const MIN_POSITION: Register = Register::ram(20, 2).masked(POSITION_MASK);
+    pub const MAX_POSITION: Register = Register::ram(22, 2).masked(POSITION_MASK);
+    pub const POSITION_KP: Register = Register::ram(24, 2);
+    pub const POSITION_KD: Register = Register::ram(26, 2);
+    pub const POSITION_KI: Register = Register::ram(28, 2);
+    pub const POSITION_FF_1ST_GAIN: Register = Register::ram(30, 2);
+    pub const POSITION_FF_2ND_GAIN: Register = Register::ram(32, 2);
+    pub const LED_BLINK_PERIOD: Register = Register::ram(38, 1);
+    pub const ADC_FAULT_DETECTION_PERIOD: Register = Register::ram(39, 1);
+    pub const PACKET_GARBAGE_DETECTION_PERIOD: Register = Register::ram(40, 1);
+    pub const STOP_DETECTION_PERIOD: Register = Register::ram(41, 1);
+    pub const OVERLOAD_DETECTION_PERIOD: Register = Register::ram(42, 1);
+    pub const STOP_THRESHOLD: Register = Register::ram(43, 1);
+    pub const INPOSITION_MARGIN: Register = Register::ram(44, 1);
+    pub const CALIBRATION_DIFFERENCE: Register = Register::ram(47, 1).signed();
+    pub const STATUS_ERROR: Register = Register::ram(48, 1);
+    pub const STATUS_DETAIL: Register = Register::ram(49, 1);
+    pub const TORQUE_CONTROL: Register = Register::ram(52, 1);
+    pub const LED_CONTROL: Register = Register::ram(53, 1);
+    pub const VOLTAGE: Register = Register::ram(54, 1);
+    pub const TEMPERATURE: Register = Register::ram(55, 1);
+    pub const CURRENT_CONTROL_MODE: Register = Register::ram(56, 1);
+    pub const TICK: Register = Register::ram(57, 1);
+    pub const CALIBRATED_POSITION: Register = Register::ram(58, 2).masked(POSITION_MASK);
+    pub const ABSOLUTE_POSITION: Register = Register::ram(60, 2).masked(POSITION_MASK);
+    /// Speed, measured every 11.2ms
+    pub const DIFFERENTIAL_POSITION: Register = Register::ram(62, 2).signed();
+    pub const PWM: Register = Register::ram(64, 2).signed();
+    pub const ABSOLUTE_GOAL_POSITION: Register = Register::ram(68, 2).masked(POSITION_MASK);
+    pub const ABSOLUTE_DESIRED_TRAJECTORY_POSITION: Register =
+        Register::ram(70, 2).masked(POSITION_MASK);
+    pub const DESIRED_VELOCITY: Register = Register::ram(72, 2).signed();
+}
+
+pub mod eep {
+    use super::{Register, POSITION_MASK};
+
+    pub const MODEL_NO_1: Register = Register::eep(0, 1);
+    pub const MODEL_NO_2: Register = Register::eep(1, 1);
+    pub const VERSION_1: Register = Register::eep(2, 1);
+    pub const VERSION_2: Register = Register::eep(3, 1);
+    pub const BAUD_RATE: Register = Register::eep(4, 1);
+    pub const ID: Register = Register::eep(6, 1);
+    pub const ACK_POLICY: Register = Register::eep(7, 1);
+    pub const ALARM_LED_POLICY: Register = Register::eep(8, 1);
+    pub const TORQUE_POLICY: Register = Register::eep(9, 1);
+    pub const MAX_TEMPERATURE: Register = Register::eep(11, 1);
+    pub const MIN_VOLTAGE: Register = Register::eep(12, 1);
+    pub const MAX_VOLTAGE: Register = Register::eep(13, 1);
+    pub const ACCELERATION_RATIO: Register = Register::eep(14, 1);
+    pub const MAX_ACCELERATION: Register = Register::eep(15, 1);
+    pub const DEAD_ZONE: Register = Register::eep(16, 1);
+    pub const SATURATOR_OFFSET: Register = Register::eep(17, 1);
+    pub const SATURATOR_SLOPE: Register = Register::eep(18, 2);
+    pub const PWM_OFFSET: Register = Register::eep(20, 1);
+    pub const MIN_PWM: Register = Register::eep(21, 1);
+    pub const MAX_PWM: Register = Register::eep(22, 2);
+    pub const OVERLOAD_PWM_THRESHOLD: Register = Register::eep(24, 2);
+    pub const MIN_POSITION: Register = Register::eep(26, 2).masked(POSITION_MASK);
+    pub const MAX_POSITION: Register = Register::eep(28, 2).masked(POSITION_MASK);
+    pub const POSITION_KP: Register = Register::eep(30, 2);
+    pub const POSITION_KD: Register = Register::eep(32, 2);
+    pub const POSITION_KI: Register = Register::eep(34, 2);
+    pub const POSITION_FF_1ST_GAIN: Register = Register::eep(36, 2);
+    pub const POSITION_FF_2ND_GAIN: Register = Register::eep(38, 2);
+    pub const LED_BLINK_PERIOD: Register = Register::eep(44, 1);
+    pub const ADC_FAULT_DETECTION_PERIOD: Register = Register::eep(45, 1);
+    pub const PACKET_GARBAGE_DETECTION_PERIOD: Register = Register::eep(46, 1);
+    pub const STOP_DETECTION_PERIOD: Register = Register::eep(47, 1);
+    pub const OVERLOAD_DETECTION_PERIOD: Register = Register::eep(48, 1);
+    pub const STOP_THRESHOLD: Register = Register::eep(49, 1);
+    pub const INPOSITION_MARGIN: Register = Register::eep(50, 1);
+    pub const CALIBRATION_DIFFERENCE: Register = Register::eep(53, 1).signed();
+}
@@ -0,0 +1,142 @@
+//! Checks the replies of the servos against the requests they answer.
+//!
+//! Packet layout: 0xFF | 0xFF | size | id | command | checksum 1 | checksum 2 | data.
+//! The data of a reply ends with the status error and status detail bytes, a register read
+//! starts it with the address and the length of the register.
+
+use crate::error::HerkulexError;
+use heapless::Vec;
+
+pub const HEADER: u8 = 0xFF;
+/// Header, size, id, command and checksums
+pub const MIN_PACKET_SIZE: usize = 7;
+/// Status error and status detail, at the end of every reply
+const STATUS_SIZE: usize = 2;
+/// Every servo handles the packets sent to this id, none of them replies
+pub const BROADCAST_ID: u8 = 0xFE;
+/// Set in the command of a reply
+pub const ACK_FLAG: u8 = 0x40;
+/// The registers of the DRS-0101 are at most 2 bytes wide
+pub const MAX_REGISTER_LEN: usize = 2;
+
+pub const EEP_WRITE: u8 = 0x01;
+pub const EEP_READ: u8 = 0x02;
+pub const RAM_WRITE: u8 = 0x03;
+pub const RAM_READ: u8 = 0x04;
+pub const I_JOG: u8 = 0x05;
+pub const S_JOG: u8 = 0x06;
+pub const STAT: u8 = 0x07;
+pub const ROLLBACK: u8 = 0x08;
+pub const REBOOT: u8 = 0x09;
+
+/// Bits of `Status::error`
+pub mod status_error {
+    pub const EXCEED_INPUT_VOLTAGE: u8 = 0x01;
+    pub const EXCEED_POSITION_LIMIT: u8 = 0x02;
+    pub const EXCEED_TEMPERATURE: u8 = 0x04;
+    pub const INVALID_PACKET: u8 = 0x08;
+    pub const OVERLOAD: u8 = 0x10;
+    pub const DRIVER_FAULT: u8 = 0x20;
+    pub const EEP_DISTORTED: u8 = 0x40;
+}
+
+/// Bits of `Status::detail`
+pub mod status_detail {
+    pub const MOVING: u8 = 0x01;
+    pub const INPOSITION: u8 = 0x02;
+    pub const CHECKSUM_ERROR: u8 = 0x04;
+    pub const UNKNOWN_COMMAND: u8 = 0x08;
+    pub const EXCEED_REG_RANGE: u8 = 0x10;
+    pub const GARBAGE_DETECTED: u8 = 0x20;
+    pub const MOTOR_ON: u8 = 0x40;
+}
+
+/// Status sent at the end of every reply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    pub error: u8,
+    pub detail: u8,
+}
+
+/// A reply which passed every check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub id: u8,
+    pub command: u8,
+    pub status: Status,
+    /// Value of the register, for the replies to a read
+    pub value: Vec<u8, MAX_REGISTER_LEN>,
+}
+
+/// Checksum 1 is the XOR of size, id, command and data with the lowest bit cleared,
+/// checksum 2 its complement
+pub fn checksums(size: u8, id: u8, command: u8, data: &[u8]) -> (u8, u8) {
+    let checksum1 = data.iter().fold(size ^ id ^ command, |acc, b| acc ^ b) & 0xFE;
+    (checksum1, !checksum1 & 0xFE)
+}
+
+/// Validates `reply` as the answer to `request`, both are whole packets. The bytes after the
+/// size announced by the reply are ignored.
+pub fn check_reply(request: &[u8], reply: &[u8]) -> Result<Reply, HerkulexError> {
+    if reply.len() < MIN_PACKET_SIZE {
+        return Err(HerkulexError::Truncated);
+    }
+    if reply[0] != HEADER || reply[1] != HEADER {
+        return Err(HerkulexError::InvalidHeader);
+    }
+    let size = reply[2] as usize;
+    if size < MIN_PACKET_SIZE + STATUS_SIZE {
+        return Err(HerkulexError::InvalidSize(reply[2]));
+    }
+    if reply.len() < size {
+        return Err(HerkulexError::Truncated);
+    }
+    let (id, command, data) = (reply[3], reply[4], &reply[MIN_PACKET_SIZE..size]);
+    if checksums(reply[2], id, command, data) != (reply[5], reply[6]) {
+        return Err(HerkulexError::Checksum);
+    }
+
+    if id != request[3] {
+        return Err(HerkulexError::WrongId {
+            expected: request[3],
+            received: id,
+        });
+    }
+    let expected_command = request[4] | ACK_FLAG;
+    if command != expected_command {
+        return Err(HerkulexError::WrongCommand {
+            expected: expected_command,
+            received: command,
+        });
+    }
+
+    let (payload, status) = data.split_at(data.len() - STATUS_SIZE);
+    let mut value = Vec::new();
+    if request[4] == EEP_READ || request[4] == RAM_READ {
+        let (address, len) = (request[MIN_PACKET_SIZE], request[MIN_PACKET_SIZE + 1]);
+        if payload.len() < 2 {
+            return Err(HerkulexError::Truncated);
+        }
+        if payload[0] != address {
+            return Err(HerkulexError::WrongRegister {
+                expected: address,
+                received: payload[0],
+            });
+        }
+        if payload[1] != len || payload.len() != 2 + len as usize {
+            return Err(HerkulexError::Truncated);
+        }
+        value
+            .extend_from_slice(&payload[2..])
+            .map_err(|_| HerkulexError::InvalidSize(reply[2]))?;
+    }
+    Ok(Reply {
+        id,
+        command,
+        status: Status {
+            error: status[0],
+            detail: status[1],
+        },
+        value,
+    })
+}
@@ -0,0 +1,97 @@
+//! Simulated servos to test the driver and its users on the host.
+//!
+//! `VirtualLine` is the serial line, shared by several `VirtualServo`. It implements
+//! `HerkulexCommunication`, so a `Motors` can be built on it like on the USART.
+
+use crate::communication::HerkulexCommunication;
+use crate::error::HerkulexError;
+use crate::receiver::{Frame, FrameReceiver};
+use drs_0x01::builder::HerkulexMessage;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+mod servo;
+
+pub use servo::*;
+
+#[derive(Default)]
+struct Line {
+    servos: Vec<VirtualServo>,
+    /// Bytes sent back by the servos since the last request
+    received: Vec<u8>,
+}
+
+/// Serial line shared by the simulated servos. Clones share the line, the test keeps one to
+/// move the time forward and look at the servos while the driver owns another.
+#[derive(Clone, Default)]
+pub struct VirtualLine(Rc<RefCell<Line>>);
+
+impl VirtualLine {
+    /// Line with one servo with the factory settings for each id
+    pub fn new(ids: &[u8]) -> Self {
+        let line = VirtualLine::default();
+        for id in ids {
+            line.add(VirtualServo::new(*id));
+        }
+        line
+    }
+
+    pub fn add(&self, servo: VirtualServo) {
+        self.0.borrow_mut().servos.push(servo);
+    }
+
+    /// Gives the first servo with this id to `f`, panics if there is none
+    pub fn with_servo<R>(&self, id: u8, f: impl FnOnce(&mut VirtualServo) -> R) -> R {
+        let mut line = self.0.borrow_mut();
+        let servo = line
+            .servos
+            .iter_mut()
+            .find(|servo| servo.id() == id)
+            .unwrap_or_else(|| panic!("no servo {id:#04X} on the line"));
+        f(servo)
+    }
+
+    /// Moves the time forward by `ticks` ticks of 11.2ms
+    pub fn step(&self, ticks: u32) {
+        let mut line = self.0.borrow_mut();
+        for _ in 0..ticks {
+            line.servos.iter_mut().for_each(VirtualServo::step);
+        }
+    }
+
+    /// Sends raw bytes to every servo, to test invalid packets
+    pub fn send_bytes(&self, bytes: &[u8]) {
+        let mut line = self.0.borrow_mut();
+        let mut received = Vec::new();
+        for servo in line.servos.iter_mut() {
+            let reply = servo.receive(bytes);
+            collide(&mut received, &reply);
+        }
+        line.received = received;
+    }
+}
+
+/// Replies sent at the same time collide. The line is high when idle and the bits at 0 win.
+fn collide(line: &mut Vec<u8>, reply: &[u8]) {
+    for (i, byte) in reply.iter().enumerate() {
+        match line.get_mut(i) {
+            Some(received) => *received &= byte,
+            None => line.push(*byte),
+        }
+    }
+}
+
+impl HerkulexCommunication for VirtualLine {
+    fn send_message(&mut self, msg: HerkulexMessage) {
+        self.send_bytes(&msg);
+    }
+
+    fn read_message(&mut self) -> Result<Frame, HerkulexError> {
+        let received = core::mem::take(&mut self.0.borrow_mut().received);
+        let mut receiver = FrameReceiver::new();
+        received
+            .into_iter()
+            .find_map(|byte| receiver.push(byte).ok().flatten())
+            .ok_or(HerkulexError::Timeout)
+    }
+}
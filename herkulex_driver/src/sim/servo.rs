@@ -0,0 +1,559 @@
+//! Software DRS-0101, answering the packets byte for byte like the servo.
+//!
+//! The servo keeps its EEP and RAM maps, follows its ACK policy and sets the status flags.
+//! The motor is a simple model: in position mode it follows a trapezoidal profile over the
+//! play time, with the acceleration ratio and the max acceleration time of the RAM, in speed
+//! mode it turns at a speed proportional to the jog value. The time only moves forward with
+//! `step`, one tick of 11.2ms each.
+
+use crate::error::HerkulexError;
+use crate::jog::{JOG_CONTINUOUS, JOG_INVALID, JOG_STOP, MAX_JOG_VALUE, SPEED_CLOCKWISE};
+use crate::receiver::FrameReceiver;
+use crate::registers::{eep, ram, Memory, Register};
+use crate::response::status_detail::*;
+use crate::response::status_error::*;
+use crate::response::{
+    checksums, ACK_FLAG, BROADCAST_ID, EEP_READ, EEP_WRITE, HEADER, I_JOG, MIN_PACKET_SIZE,
+    RAM_READ, RAM_WRITE, REBOOT, ROLLBACK, STAT, S_JOG,
+};
+
+pub const EEP_SIZE: usize = 54;
+pub const RAM_SIZE: usize = 74;
+/// RAM registers 0 to 47 are loaded from the EEP registers 6 to 53 at boot
+const EEP_OFFSET: usize = 6;
+const LOADED_REGISTERS: usize = EEP_SIZE - EEP_OFFSET;
+/// The RAM registers from this address are measures, they can't be written
+const RAM_WRITABLE_END: usize = 54;
+/// The model and version numbers, the first EEP registers, can't be written
+const EEP_WRITABLE_START: usize = 4;
+
+/// Id of a servo out of the box
+pub const FACTORY_ID: u8 = 0xDB;
+
+pub const TORQUE_FREE: u8 = 0x00;
+pub const TORQUE_BRAKE: u8 = 0x40;
+pub const TORQUE_ON: u8 = 0x60;
+
+/// Speed of the model at full PWM, in position units per tick (60° in about 0.16s)
+const MAX_SPEED: f32 = 13.0;
+/// One turn, in position units of 0.325°
+const TURN: f32 = 1108.0;
+
+/// Factory values of the EEP registers which aren't 0
+const FACTORY_DEFAULTS: [(Register, u16); 26] = [
+    (eep::MODEL_NO_1, 0x01),
+    (eep::MODEL_NO_2, 0x01),
+    (eep::VERSION_1, 0x01),
+    (eep::BAUD_RATE, 0x10),
+    (eep::ID, FACTORY_ID as u16),
+    (eep::ACK_POLICY, 0x01),
+    (eep::ALARM_LED_POLICY, 0x7F),
+    (eep::TORQUE_POLICY, 0x35),
+    (eep::MAX_TEMPERATURE, 0xDF),
+    (eep::MIN_VOLTAGE, 0x5B),
+    (eep::MAX_VOLTAGE, 0x89),
+    (eep::ACCELERATION_RATIO, 0x19),
+    (eep::MAX_ACCELERATION, 0x2D),
+    (eep::MAX_PWM, 0x03FF),
+    (eep::OVERLOAD_PWM_THRESHOLD, 0x03FF),
+    (eep::MIN_POSITION, 0x0015),
+    (eep::MAX_POSITION, 0x03EA),
+    (eep::POSITION_KP, 0x01B8),
+    (eep::POSITION_KD, 0x1F40),
+    (eep::LED_BLINK_PERIOD, 0x2D),
+    (eep::ADC_FAULT_DETECTION_PERIOD, 0x2D),
+    (eep::PACKET_GARBAGE_DETECTION_PERIOD, 0x12),
+    (eep::STOP_DETECTION_PERIOD, 0x1B),
+    (eep::OVERLOAD_DETECTION_PERIOD, 0x96),
+    (eep::STOP_THRESHOLD, 0x03),
+    (eep::INPOSITION_MARGIN, 0x03),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Motion {
+    /// Holds `VirtualServo::goal`
+    Hold,
+    /// Trapezoidal profile from `start` to the goal in `duration` ticks, accelerating during
+    /// `acceleration` ticks
+    Trajectory {
+        start: f32,
+        duration: f32,
+        acceleration: f32,
+        elapsed: u32,
+    },
+    /// Continuous rotation, in position units per tick
+    Turn { velocity: f32 },
+}
+
+pub struct VirtualServo {
+    eep: [u8; EEP_SIZE],
+    ram: [u8; RAM_SIZE],
+    receiver: FrameReceiver,
+    motion: Motion,
+    /// In position units, fractional to integrate small speeds
+    position: f32,
+    goal: f32,
+    /// Position units per tick during the last tick
+    velocity: f32,
+    /// Bits of the status detail kept until the status error is cleared
+    detail_flags: u8,
+    /// Raw value of the voltage register, 0.074V per unit
+    pub voltage: u8,
+    /// Raw value of the temperature register
+    pub temperature: u8,
+}
+
+impl VirtualServo {
+    /// Servo with the factory settings except its id, just booted at the middle position
+    pub fn new(id: u8) -> Self {
+        let mut servo = VirtualServo {
+            eep: [0; EEP_SIZE],
+            ram: [0; RAM_SIZE],
+            receiver: FrameReceiver::new(),
+            motion: Motion::Hold,
+            position: 512.0,
+            goal: 512.0,
+            velocity: 0.0,
+            detail_flags: 0,
+            voltage: 0x64,
+            temperature: 0x40,
+        };
+        servo.restore_factory_defaults();
+        servo.set_register(eep::ID, id.into());
+        servo.reboot();
+        servo
+    }
+
+    pub fn id(&self) -> u8 {
+        self.ram[ram::ID.address as usize]
+    }
+
+    pub fn ram(&self) -> &[u8; RAM_SIZE] {
+        &self.ram
+    }
+
+    pub fn eep(&self) -> &[u8; EEP_SIZE] {
+        &self.eep
+    }
+
+    /// Current position, in units of 0.325°
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    /// Value of a register, read like the driver does
+    pub fn register(&self, register: Register) -> i32 {
+        let address = register.address as usize;
+        let bytes = &self.memory(register.memory)[address..address + register.width as usize];
+        // Can't fail, the width of the slice is the width of the register
+        register.decode(bytes).unwrap()
+    }
+
+    /// Writes a register directly, without a packet and without the checks of a write
+    pub fn set_register(&mut self, register: Register, value: u16) {
+        let address = register.address as usize;
+        let bytes = value.to_le_bytes();
+        let width = register.width as usize;
+        match register.memory {
+            Memory::Ram => {
+                self.ram[address..address + width].copy_from_slice(&bytes[..width]);
+                self.apply_ram_write(address, width);
+            }
+            Memory::Eep => self.eep[address..address + width].copy_from_slice(&bytes[..width]),
+        }
+    }
+
+    /// Handles the bytes sent on the line, returns the bytes the servo sends back
+    pub fn receive(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let mut replies = Vec::new();
+        for byte in bytes {
+            match self.receiver.push(*byte) {
+                Ok(Some(frame)) => {
+                    if let Some(reply) = self.handle(&frame) {
+                        replies.extend(reply);
+                    }
+                }
+                Ok(None) => {}
+                // The id of an invalid packet isn't known, every servo takes it for itself
+                Err(HerkulexError::Checksum) => self.raise(INVALID_PACKET, CHECKSUM_ERROR),
+                Err(_) => self.raise(INVALID_PACKET, GARBAGE_DETECTED),
+            }
+        }
+        replies
+    }
+
+    /// Moves the time forward by one tick of 11.2ms
+    pub fn step(&mut self) {
+        self.check_limits();
+        let torque_on = self.ram[ram::TORQUE_CONTROL.address as usize] == TORQUE_ON;
+        let previous = self.position;
+        self.velocity = 0.0;
+        if torque_on {
+            match &mut self.motion {
+                Motion::Hold => self.position = self.goal,
+                Motion::Trajectory {
+                    start,
+                    duration,
+                    acceleration,
+                    elapsed,
+                } => {
+                    *elapsed = elapsed.saturating_add(1);
+                    let distance = self.goal - *start;
+                    self.position =
+                        *start + profile(distance, *duration, *acceleration, *elapsed as f32);
+                    self.velocity = self.position - previous;
+                }
+                Motion::Turn { velocity } => {
+                    self.position = (self.position + *velocity).rem_euclid(TURN);
+                    self.velocity = *velocity;
+                }
+            }
+        } else {
+            self.goal = self.position;
+        }
+        let tick = ram::TICK.address as usize;
+        self.ram[tick] = self.ram[tick].wrapping_add(1);
+        self.update_registers();
+    }
+
+    /// RAM loaded from the EEP, errors cleared and torque free, the position is kept
+    pub fn reboot(&mut self) {
+        self.ram[..LOADED_REGISTERS].copy_from_slice(&self.eep[EEP_OFFSET..]);
+        self.ram[LOADED_REGISTERS..].fill(0);
+        self.receiver.reset();
+        self.motion = Motion::Hold;
+        self.goal = self.position;
+        self.velocity = 0.0;
+        self.detail_flags = 0;
+        self.update_registers();
+    }
+
+    fn restore_factory_defaults(&mut self) {
+        self.eep.fill(0);
+        for (register, value) in FACTORY_DEFAULTS {
+            self.set_register(register, value);
+        }
+    }
+
+    fn memory(&self, memory: Memory) -> &[u8] {
+        match memory {
+            Memory::Ram => &self.ram,
+            Memory::Eep => &self.eep,
+        }
+    }
+
+    fn ram_u8(&self, register: Register) -> u8 {
+        self.ram[register.address as usize]
+    }
+
+    fn ram_u16(&self, register: Register) -> u16 {
+        let address = register.address as usize;
+        u16::from_le_bytes([self.ram[address], self.ram[address + 1]]) & register.mask
+    }
+
+    fn set_ram(&mut self, register: Register, value: u16) {
+        let address = register.address as usize;
+        let bytes = value.to_le_bytes();
+        let width = register.width as usize;
+        self.ram[address..address + width].copy_from_slice(&bytes[..width]);
+    }
+
+    /// Sets status bits like a fault of the servo, the torque is released if the torque policy
+    /// asks for it
+    pub fn raise(&mut self, error: u8, detail: u8) {
+        self.ram[ram::STATUS_ERROR.address as usize] |= error;
+        self.detail_flags |= detail;
+        if error & self.ram_u8(ram::TORQUE_POLICY) != 0 {
+            self.set_ram(ram::TORQUE_CONTROL, TORQUE_FREE.into());
+            self.motion = Motion::Hold;
+        }
+        self.update_registers();
+    }
+
+    fn check_limits(&mut self) {
+        if self.voltage < self.ram_u8(ram::MIN_VOLTAGE)
+            || self.voltage > self.ram_u8(ram::MAX_VOLTAGE)
+        {
+            self.raise(EXCEED_INPUT_VOLTAGE, 0);
+        }
+        if self.temperature > self.ram_u8(ram::MAX_TEMPERATURE) {
+            self.raise(EXCEED_TEMPERATURE, 0);
+        }
+    }
+
+    /// Side effects of a write of `len` RAM registers from `address`
+    fn apply_ram_write(&mut self, address: usize, len: usize) {
+        let written = address..address + len;
+        if written.contains(&(ram::STATUS_ERROR.address as usize))
+            && self.ram_u8(ram::STATUS_ERROR) == 0
+        {
+            self.detail_flags = 0;
+        }
+        if written.contains(&(ram::STATUS_DETAIL.address as usize)) {
+            self.detail_flags &= self.ram_u8(ram::STATUS_DETAIL);
+        }
+        if written.contains(&(ram::TORQUE_CONTROL.address as usize))
+            && self.ram_u8(ram::TORQUE_CONTROL) != TORQUE_ON
+        {
+            self.motion = Motion::Hold;
+            self.goal = self.position;
+        }
+        self.update_registers();
+    }
+
+    /// Measures and status from the state of the model
+    fn update_registers(&mut self) {
+        let torque = self.ram_u8(ram::TORQUE_CONTROL);
+        let position = self.position.round() as i32;
+        let calibrated =
+            (position + self.register(ram::CALIBRATION_DIFFERENCE)).clamp(0, 0x03FF) as u16;
+        let max_pwm = f32::from(self.ram_u16(ram::MAX_PWM));
+
+        self.set_ram(ram::VOLTAGE, self.voltage.into());
+        self.set_ram(ram::TEMPERATURE, self.temperature.into());
+        self.set_ram(
+            ram::CURRENT_CONTROL_MODE,
+            matches!(self.motion, Motion::Turn { .. }).into(),
+        );
+        self.set_ram(ram::CALIBRATED_POSITION, calibrated);
+        self.set_ram(ram::ABSOLUTE_POSITION, position as u16);
+        self.set_ram(
+            ram::DIFFERENTIAL_POSITION,
+            self.velocity.round() as i16 as u16,
+        );
+        self.set_ram(
+            ram::PWM,
+            (self.velocity / MAX_SPEED * max_pwm).clamp(-max_pwm, max_pwm) as i16 as u16,
+        );
+        self.set_ram(ram::ABSOLUTE_GOAL_POSITION, self.goal.round() as u16);
+        self.set_ram(ram::ABSOLUTE_DESIRED_TRAJECTORY_POSITION, position as u16);
+        self.set_ram(ram::DESIRED_VELOCITY, self.velocity.round() as i16 as u16);
+
+        let moving = match self.motion {
+            Motion::Hold => false,
+            Motion::Trajectory {
+                duration, elapsed, ..
+            } => (elapsed as f32) < duration,
+            Motion::Turn { velocity } => velocity != 0.0,
+        } && torque == TORQUE_ON;
+        let margin = f32::from(self.ram_u8(ram::INPOSITION_MARGIN));
+        let inposition = !matches!(self.motion, Motion::Turn { .. })
+            && !moving
+            && (self.position - self.goal).abs() <= margin;
+        let mut detail = self.detail_flags;
+        if moving {
+            detail |= MOVING;
+        }
+        if inposition {
+            detail |= INPOSITION;
+        }
+        if torque == TORQUE_ON {
+            detail |= MOTOR_ON;
+        }
+        self.set_ram(ram::STATUS_DETAIL, detail.into());
+    }
+
+    /// Handles a valid packet, returns the reply if the servo sends one
+    fn handle(&mut self, frame: &[u8]) -> Option<Vec<u8>> {
+        let (id, command, data) = (frame[3], frame[4], &frame[MIN_PACKET_SIZE..]);
+        let own_id = self.id();
+        if id != own_id && id != BROADCAST_ID {
+            return None;
+        }
+        // The measures are sampled when the request arrives
+        self.update_registers();
+
+        let payload = match command {
+            EEP_READ => self.read(Memory::Eep, data),
+            RAM_READ => self.read(Memory::Ram, data),
+            EEP_WRITE => self.write(Memory::Eep, data),
+            RAM_WRITE => self.write(Memory::Ram, data),
+            I_JOG => self.i_jog(data),
+            S_JOG => self.s_jog(data),
+            STAT => Some(Vec::new()),
+            ROLLBACK => self.rollback(data),
+            REBOOT => {
+                self.reboot();
+                Some(Vec::new())
+            }
+            _ => {
+                self.raise(INVALID_PACKET, UNKNOWN_COMMAND);
+                None
+            }
+        };
+
+        let replies = match self.ram_u8(ram::ACK_POLICY) {
+            0 => false,
+            1 => matches!(command, EEP_READ | RAM_READ | STAT),
+            _ => true,
+        };
+        if id == BROADCAST_ID || !replies {
+            return None;
+        }
+        // A failed request is answered with the status only
+        let mut data = payload.unwrap_or_default();
+        data.push(self.ram_u8(ram::STATUS_ERROR));
+        data.push(self.ram_u8(ram::STATUS_DETAIL));
+        Some(packet(own_id, command | ACK_FLAG, &data))
+    }
+
+    /// Address, length and value of the registers read
+    fn read(&mut self, memory: Memory, data: &[u8]) -> Option<Vec<u8>> {
+        let &[address, len] = data else {
+            self.raise(INVALID_PACKET, 0);
+            return None;
+        };
+        let registers = address as usize..address as usize + len as usize;
+        let Some(value) = self.memory(memory).get(registers) else {
+            self.raise(INVALID_PACKET, EXCEED_REG_RANGE);
+            return None;
+        };
+        let mut payload = vec![address, len];
+        payload.extend_from_slice(value);
+        Some(payload)
+    }
+
+    fn write(&mut self, memory: Memory, data: &[u8]) -> Option<Vec<u8>> {
+        if data.len() < 2 || data.len() != 2 + data[1] as usize {
+            self.raise(INVALID_PACKET, 0);
+            return None;
+        }
+        let (address, value) = (data[0] as usize, &data[2..]);
+        let writable = match memory {
+            Memory::Ram => 0..RAM_WRITABLE_END,
+            Memory::Eep => EEP_WRITABLE_START..EEP_SIZE,
+        };
+        if address < writable.start || address + value.len() > writable.end {
+            self.raise(INVALID_PACKET, EXCEED_REG_RANGE);
+            return None;
+        }
+        match memory {
+            Memory::Ram => {
+                self.ram[address..address + value.len()].copy_from_slice(value);
+                self.apply_ram_write(address, value.len());
+            }
+            Memory::Eep => self.eep[address..address + value.len()].copy_from_slice(value),
+        }
+        Some(Vec::new())
+    }
+
+    /// Data: the play time then LSB, MSB, SET and id of each servo
+    fn s_jog(&mut self, data: &[u8]) -> Option<Vec<u8>> {
+        if data.is_empty() || !(data.len() - 1).is_multiple_of(4) {
+            self.raise(INVALID_PACKET, 0);
+            return None;
+        }
+        let playtime = data[0];
+        for jog in data[1..].chunks(4) {
+            if jog[3] == self.id() {
+                self.jog(u16::from_le_bytes([jog[0], jog[1]]), jog[2], playtime);
+            }
+        }
+        Some(Vec::new())
+    }
+
+    /// Data: LSB, MSB, SET, id and play time of each servo
+    fn i_jog(&mut self, data: &[u8]) -> Option<Vec<u8>> {
+        if data.is_empty() || !data.len().is_multiple_of(5) {
+            self.raise(INVALID_PACKET, 0);
+            return None;
+        }
+        for jog in data.chunks(5) {
+            if jog[3] == self.id() {
+                self.jog(u16::from_le_bytes([jog[0], jog[1]]), jog[2], jog[4]);
+            }
+        }
+        Some(Vec::new())
+    }
+
+    fn jog(&mut self, value: u16, set: u8, playtime: u8) {
+        if set & JOG_INVALID != 0 {
+            return;
+        }
+        // Green, blue and red, as in the LED control register
+        self.set_ram(ram::LED_CONTROL, u16::from(set >> 2 & 0x07));
+        if self.ram_u8(ram::TORQUE_CONTROL) != TORQUE_ON {
+            return;
+        }
+
+        if set & JOG_STOP != 0 {
+            self.motion = Motion::Hold;
+            self.goal = self.position;
+        } else if set & JOG_CONTINUOUS != 0 {
+            let speed = f32::from(value & MAX_JOG_VALUE) / f32::from(MAX_JOG_VALUE) * MAX_SPEED;
+            // The model turns clockwise toward the small positions
+            self.motion = Motion::Turn {
+                velocity: if value & SPEED_CLOCKWISE != 0 {
+                    -speed
+                } else {
+                    speed
+                },
+            };
+        } else {
+            let min = self.ram_u16(ram::MIN_POSITION);
+            let max = self.ram_u16(ram::MAX_POSITION);
+            if value < min || value > max {
+                self.raise(EXCEED_POSITION_LIMIT, 0);
+                return;
+            }
+            let distance = (f32::from(value) - self.position).abs();
+            // Too short play times are stretched to the speed of the motor
+            let duration = f32::from(playtime).max((distance / MAX_SPEED).ceil());
+            let ratio = f32::from(self.ram_u8(ram::ACCELERATION_RATIO)) / 100.0;
+            let acceleration = (duration * ratio)
+                .min(f32::from(self.ram_u8(ram::MAX_ACCELERATION)))
+                .min(duration / 2.0);
+            self.motion = Motion::Trajectory {
+                start: self.position,
+                duration,
+                acceleration,
+                elapsed: 0,
+            };
+            self.goal = value.into();
+        }
+        self.update_registers();
+    }
+
+    /// Data: 1 to keep the id, 1 to keep the baud rate. Applied at the next reboot.
+    fn rollback(&mut self, data: &[u8]) -> Option<Vec<u8>> {
+        let &[skip_id, skip_baud_rate] = data else {
+            self.raise(INVALID_PACKET, 0);
+            return None;
+        };
+        let id = self.eep[eep::ID.address as usize];
+        let baud_rate = self.eep[eep::BAUD_RATE.address as usize];
+        self.restore_factory_defaults();
+        if skip_id != 0 {
+            self.eep[eep::ID.address as usize] = id;
+        }
+        if skip_baud_rate != 0 {
+            self.eep[eep::BAUD_RATE.address as usize] = baud_rate;
+        }
+        Some(Vec::new())
+    }
+}
+
+/// Distance covered after `t` ticks of a trapezoidal profile
+fn profile(distance: f32, duration: f32, acceleration: f32, t: f32) -> f32 {
+    if t >= duration {
+        return distance;
+    }
+    let speed = distance / (duration - acceleration);
+    if t < acceleration {
+        speed / acceleration * t * t / 2.0
+    } else if t <= duration - acceleration {
+        speed * acceleration / 2.0 + speed * (t - acceleration)
+    } else {
+        let remaining = duration - t;
+        distance - speed / acceleration * remaining * remaining / 2.0
+    }
+}
+
+/// Whole packet, as sent on the line
+pub fn packet(id: u8, command: u8, data: &[u8]) -> Vec<u8> {
+    let size = (MIN_PACKET_SIZE + data.len()) as u8;
+    let (checksum1, checksum2) = checksums(size, id, command, data);
+    let mut packet = vec![HEADER, HEADER, size, id, command, checksum1, checksum2];
+    packet.extend_from_slice(data);
+    packet
+}
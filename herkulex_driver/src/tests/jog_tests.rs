@@ -0,0 +1,114 @@
+use crate::error::HerkulexError;
+use crate::jog::{IJog, Led, SJog, MAX_I_JOG_SERVOS, MAX_S_JOG_SERVOS};
+use drs_0x01::builder::MessageBuilder;
+use drs_0x01::{JogColor, JogMode, Rotation};
+
+#[test]
+fn s_jog_packet() {
+    let mut jog = SJog::new(0x3C);
+    jog.position(1, 512, Led::GREEN)
+        .unwrap()
+        .position(2, 256, Led::BLUE)
+        .unwrap();
+    // Playtime, then LSB, MSB, SET and id of each servo
+    assert_eq!(
+        &jog.build()[..],
+        &[
+            0xFF, 0xFF, 0x10, 0xFE, 0x06, 0xD8, 0x26, 0x3C, 0x00, 0x02, 0x04, 0x01, 0x00, 0x01,
+            0x08, 0x02
+        ]
+    );
+}
+
+#[test]
+fn s_jog_matches_drs_0x01() {
+    let mut jog = SJog::new(60);
+    jog.position(1, 300, Led::BLUE)
+        .unwrap()
+        .speed(2, 512, Rotation::Clockwise, Led::RED)
+        .unwrap()
+        .speed(3, 100, Rotation::CounterClockwise, Led::GREEN)
+        .unwrap();
+
+    let mut expected = MessageBuilder::new_with_id(0xFE).s_jog(
+        60,
+        JogMode::Normal { position: 300 },
+        JogColor::Blue,
+        1,
+    );
+    let speed = |speed, rotation| JogMode::Continuous { speed, rotation };
+    let _ = expected.s_jog(speed(512, Rotation::Clockwise), JogColor::Red, 2);
+    let _ = expected.s_jog(speed(100, Rotation::CounterClockwise), JogColor::Green, 3);
+    assert_eq!(jog.build(), expected.build());
+}
+
+#[test]
+fn i_jog_matches_drs_0x01() {
+    let mut jog = IJog::new();
+    jog.position(1, 300, Led::BLUE, 20)
+        .unwrap()
+        .speed(2, 512, Rotation::Clockwise, Led::RED, 40)
+        .unwrap();
+
+    let mut expected = MessageBuilder::new_with_id(0xFE).i_jog(
+        20,
+        JogMode::Normal { position: 300 },
+        JogColor::Blue,
+        1,
+    );
+    let mode = JogMode::Continuous {
+        speed: 512,
+        rotation: Rotation::Clockwise,
+    };
+    let _ = expected.s_jog(mode, JogColor::Red, 40, 2);
+    assert_eq!(jog.build(), expected.build());
+}
+
+#[test]
+fn leds_and_stop() {
+    let mut jog = IJog::new();
+    let white = Led {
+        green: true,
+        blue: true,
+        red: true,
+    };
+    jog.stop(7, white)
+        .unwrap()
+        .position(8, 0, Led::OFF, 0)
+        .unwrap();
+    let packet = jog.build();
+    assert_eq!(&packet[7..12], &[0x00, 0x00, 0x1D, 0x07, 0x00]);
+    assert_eq!(packet[14], 0x00);
+}
+
+#[test]
+fn more_servos_than_drs_0x01() {
+    let mut jog = SJog::new(60);
+    for id in 0..MAX_S_JOG_SERVOS as u8 {
+        jog.position(id, 512, Led::OFF).unwrap();
+    }
+    assert_eq!(
+        jog.position(0xFD, 512, Led::OFF).err(),
+        Some(HerkulexError::PacketFull)
+    );
+    let packet = jog.build();
+    assert_eq!(packet.len(), 8 + 4 * MAX_S_JOG_SERVOS);
+    assert_eq!(packet[2] as usize, packet.len());
+
+    let mut jog = IJog::new();
+    for id in 0..MAX_I_JOG_SERVOS as u8 {
+        jog.position(id, 512, Led::OFF, 60).unwrap();
+    }
+    assert!(jog.stop(0xFD, Led::OFF).is_err());
+    assert_eq!(jog.build().len(), 7 + 5 * MAX_I_JOG_SERVOS);
+}
+
+#[test]
+fn values_out_of_range() {
+    let mut jog = SJog::new(60);
+    assert_eq!(
+        jog.position(1, 1024, Led::OFF).err(),
+        Some(HerkulexError::InvalidValue(1024))
+    );
+    assert!(jog.speed(1, 0x4000, Rotation::Clockwise, Led::OFF).is_err());
+}
@@ -0,0 +1,5 @@
+mod jog_tests;
+mod receiver_tests;
+mod registers_tests;
+mod sim_tests;
+mod units_tests;
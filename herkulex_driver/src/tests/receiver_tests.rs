@@ -0,0 +1,162 @@
+use crate::error::HerkulexError;
+use crate::receiver::{Frame, FrameReceiver, MAX_PACKET_SIZE};
+use crate::response::checksums;
+
+/// Reply to the read of the LED control of the servo 0xFD, from the manual
+const LED_CONTROL: [u8; 12] = [
+    0xFF, 0xFF, 0x0C, 0xFD, 0x44, 0xC2, 0x3C, 0x35, 0x01, 0x01, 0x00, 0x42,
+];
+/// Reply to a STAT of the servo 0xFD
+const STAT: [u8; 9] = [0xFF, 0xFF, 0x09, 0xFD, 0x47, 0xB2, 0x4C, 0x00, 0x00];
+
+fn receive(receiver: &mut FrameReceiver, bytes: &[u8]) -> Vec<Result<Frame, HerkulexError>> {
+    bytes
+        .iter()
+        .filter_map(|byte| receiver.push(*byte).transpose())
+        .collect()
+}
+
+fn frame(bytes: &[u8]) -> Result<Frame, HerkulexError> {
+    Ok(Frame::from_slice(bytes).unwrap())
+}
+
+/// A valid packet of `size` bytes
+fn packet(size: usize, id: u8, command: u8) -> std::vec::Vec<u8> {
+    let data: std::vec::Vec<u8> = (0..size - 7).map(|i| i as u8).collect();
+    let (checksum1, checksum2) = checksums(size as u8, id, command, &data);
+    let mut packet = vec![0xFF, 0xFF, size as u8, id, command, checksum1, checksum2];
+    packet.extend(data);
+    packet
+}
+
+#[test]
+fn frames_back_to_back() {
+    let mut receiver = FrameReceiver::new();
+    let bytes = [&LED_CONTROL[..], &STAT[..]].concat();
+    assert_eq!(
+        receive(&mut receiver, &bytes),
+        vec![frame(&LED_CONTROL), frame(&STAT)]
+    );
+}
+
+#[test]
+fn noise_before_the_header() {
+    let mut receiver = FrameReceiver::new();
+    let bytes = [&[0x00, 0xFF, 0x12, 0x44][..], &STAT[..]].concat();
+    assert_eq!(receive(&mut receiver, &bytes), vec![frame(&STAT)]);
+}
+
+#[test]
+fn more_than_two_header_bytes() {
+    let mut receiver = FrameReceiver::new();
+    let bytes = [&[0xFF, 0xFF][..], &STAT[..]].concat();
+    assert_eq!(receive(&mut receiver, &bytes), vec![frame(&STAT)]);
+}
+
+#[test]
+fn frame_split_between_interrupts() {
+    let mut receiver = FrameReceiver::new();
+    assert_eq!(receive(&mut receiver, &LED_CONTROL[..5]), vec![]);
+    assert_eq!(
+        receive(&mut receiver, &LED_CONTROL[5..]),
+        vec![frame(&LED_CONTROL)]
+    );
+}
+
+#[test]
+fn invalid_checksum() {
+    let mut receiver = FrameReceiver::new();
+    let mut corrupted = LED_CONTROL;
+    // The lowest bit isn't covered by the checksums
+    corrupted[9] ^= 0x02;
+    let bytes = [&corrupted[..], &STAT[..]].concat();
+    assert_eq!(
+        receive(&mut receiver, &bytes),
+        vec![Err(HerkulexError::Checksum), frame(&STAT)]
+    );
+}
+
+#[test]
+fn invalid_size() {
+    let mut receiver = FrameReceiver::new();
+    let bytes = [&[0xFF, 0xFF, 0x03][..], &STAT[..]].concat();
+    assert_eq!(
+        receive(&mut receiver, &bytes),
+        vec![Err(HerkulexError::InvalidSize(0x03)), frame(&STAT)]
+    );
+    let bytes = [&[0xFF, 0xFF, MAX_PACKET_SIZE as u8 + 1][..], &STAT[..]].concat();
+    assert_eq!(
+        receive(&mut receiver, &bytes),
+        vec![
+            Err(HerkulexError::InvalidSize(MAX_PACKET_SIZE as u8 + 1)),
+            frame(&STAT)
+        ]
+    );
+}
+
+#[test]
+fn largest_frame() {
+    let mut receiver = FrameReceiver::new();
+    let largest = packet(MAX_PACKET_SIZE, 0x02, 0x44);
+    assert_eq!(receive(&mut receiver, &largest), vec![frame(&largest)]);
+}
+
+#[test]
+fn reset_drops_the_partial_frame() {
+    let mut receiver = FrameReceiver::new();
+    assert_eq!(receive(&mut receiver, &LED_CONTROL[..8]), vec![]);
+    receiver.reset();
+    assert_eq!(receive(&mut receiver, &STAT), vec![frame(&STAT)]);
+}
+
+/// Xorshift, the tests stay reproducible
+fn random_generator() -> impl FnMut() -> u32 {
+    let mut seed = 0x2545_F491_u32;
+    move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed
+    }
+}
+
+/// Valid packets of random sizes between noise without header byte, they are all found
+#[test]
+fn fuzz_packets_in_noise() {
+    let mut random = random_generator();
+    let mut receiver = FrameReceiver::new();
+    for _ in 0..200 {
+        let mut bytes = std::vec::Vec::new();
+        let mut packets = std::vec::Vec::new();
+        for _ in 0..10 {
+            let noise = random() % 16;
+            bytes.extend((0..noise).map(|_| random() as u8 & 0x7F));
+            let size = 7 + random() as usize % (MAX_PACKET_SIZE - 6);
+            let packet = packet(size, random() as u8, random() as u8);
+            bytes.extend(&packet);
+            packets.push(frame(&packet));
+        }
+        assert_eq!(receive(&mut receiver, &bytes), packets);
+    }
+}
+
+/// Random bytes, everything coming out as a frame is valid
+#[test]
+fn fuzz_random_bytes() {
+    let mut random = random_generator();
+    let mut receiver = FrameReceiver::new();
+    // 0xFF is frequent enough to start frames
+    let bytes: std::vec::Vec<u8> = (0..100_000)
+        .map(|_| match random() % 4 {
+            0 => 0xFF,
+            _ => random() as u8,
+        })
+        .collect();
+    for frame in receive(&mut receiver, &bytes).into_iter().flatten() {
+        assert_eq!(frame.len(), frame[2] as usize);
+        assert_eq!(
+            checksums(frame[2], frame[3], frame[4], &frame[7..]),
+            (frame[5], frame[6])
+        );
+    }
+}
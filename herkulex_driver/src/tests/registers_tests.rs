@@ -0,0 +1,138 @@
+//! These tests were meant to decode replies captured from a DRS-0101, but no servo was
+//! available to capture them so that part is left out for now. Only the LED control read of
+//! the servo 0xFD comes from the manual. The replies marked `Synthetic` were written by hand in
+//! the format of the manual with their checksums computed; replace each one with a capture of
+//! the same read once a servo is at hand.
+
+use crate::error::HerkulexError;
+use crate::registers::{eep, ram, Register};
+use crate::response::check_reply;
+
+/// Reads `register` of the servo `id` from `reply`
+fn read(register: Register, id: u8, reply: &[u8]) -> Result<i32, HerkulexError> {
+    let reply = check_reply(&register.request(id), reply)?;
+    register.decode(&reply.value)
+}
+
+#[test]
+fn request_matches_the_manual() {
+    // Read of the LED control of the servo 0xFD, example of the manual
+    let request = ram::LED_CONTROL.request(0xFD);
+    assert_eq!(
+        &request[..],
+        &[0xFF, 0xFF, 0x09, 0xFD, 0x04, 0xC4, 0x3A, 0x35, 0x01]
+    );
+}
+
+#[test]
+fn led_control_reply_of_the_manual() {
+    // Printed in the manual
+    let reply = [
+        0xFF, 0xFF, 0x0C, 0xFD, 0x44, 0xC2, 0x3C, 0x35, 0x01, 0x01, 0x00, 0x42,
+    ];
+    assert_eq!(read(ram::LED_CONTROL, 0xFD, &reply), Ok(1));
+}
+
+#[test]
+fn one_byte_registers_request_one_byte() {
+    // drs_0x01 requests 2 bytes for the temperature
+    assert_eq!(ram::TEMPERATURE.request(2)[8], 1);
+    // Synthetic: 0x4A in the temperature of the servo 2
+    let reply = [
+        0xFF, 0xFF, 0x0C, 0x02, 0x44, 0x76, 0x88, 0x37, 0x01, 0x4A, 0x00, 0x40,
+    ];
+    assert_eq!(read(ram::TEMPERATURE, 2, &reply), Ok(0x4A));
+}
+
+#[test]
+fn two_bytes_registers_are_little_endian() {
+    // Synthetic: 0x01B8 in the position Kp of the servo 0
+    let reply = [
+        0xFF, 0xFF, 0x0D, 0x00, 0x44, 0xAA, 0x54, 0x18, 0x02, 0xB8, 0x01, 0x00, 0x40,
+    ];
+    assert_eq!(read(ram::POSITION_KP, 0, &reply), Ok(0x01B8));
+}
+
+#[test]
+fn positions_are_masked() {
+    // Synthetic: 0x41F3 in the absolute position of the servo 0, the unused bits set
+    let reply = [
+        0xFF, 0xFF, 0x0D, 0x00, 0x44, 0x84, 0x7A, 0x3C, 0x02, 0xF3, 0x41, 0x00, 0x40,
+    ];
+    assert_eq!(read(ram::ABSOLUTE_POSITION, 0, &reply), Ok(0x01F3));
+}
+
+#[test]
+fn signed_registers() {
+    // Synthetic: -3, -512 and -5 in registers of the servos 0 and 2
+    let differential_position = [
+        0xFF, 0xFF, 0x0D, 0x00, 0x44, 0x36, 0xC8, 0x3E, 0x02, 0xFD, 0xFF, 0x00, 0x40,
+    ];
+    assert_eq!(
+        read(ram::DIFFERENTIAL_POSITION, 0, &differential_position),
+        Ok(-3)
+    );
+    let pwm = [
+        0xFF, 0xFF, 0x0D, 0x02, 0x44, 0xB6, 0x48, 0x40, 0x02, 0x00, 0xFE, 0x00, 0x40,
+    ];
+    assert_eq!(read(ram::PWM, 2, &pwm), Ok(-512));
+    let calibration_difference = [
+        0xFF, 0xFF, 0x0C, 0x00, 0x44, 0xDC, 0x22, 0x2F, 0x01, 0xFB, 0x00, 0x40,
+    ];
+    assert_eq!(
+        read(ram::CALIBRATION_DIFFERENCE, 0, &calibration_difference),
+        Ok(-5)
+    );
+}
+
+#[test]
+fn eep_read() {
+    // Synthetic: 2 in the ID of the EEP of the servo 2
+    let reply = [
+        0xFF, 0xFF, 0x0C, 0x02, 0x42, 0x08, 0xF6, 0x06, 0x01, 0x02, 0x00, 0x40,
+    ];
+    assert_eq!(read(eep::ID, 2, &reply), Ok(2));
+    assert_eq!(
+        read(ram::ID, 2, &reply),
+        Err(HerkulexError::WrongCommand {
+            expected: 0x44,
+            received: 0x42
+        })
+    );
+}
+
+#[test]
+fn reply_of_another_register() {
+    // Synthetic, the temperature reply above
+    let temperature = [
+        0xFF, 0xFF, 0x0C, 0x02, 0x44, 0x76, 0x88, 0x37, 0x01, 0x4A, 0x00, 0x40,
+    ];
+    assert_eq!(
+        read(ram::VOLTAGE, 2, &temperature),
+        Err(HerkulexError::WrongRegister {
+            expected: 54,
+            received: 55
+        })
+    );
+}
+
+#[test]
+fn corrupted_reply() {
+    // Synthetic, the absolute position reply above
+    let mut reply = [
+        0xFF, 0xFF, 0x0D, 0x00, 0x44, 0x84, 0x7A, 0x3C, 0x02, 0xF3, 0x41, 0x00, 0x40,
+    ];
+    reply[9] ^= 0x04;
+    assert_eq!(
+        read(ram::ABSOLUTE_POSITION, 0, &reply),
+        Err(HerkulexError::Checksum)
+    );
+}
+
+#[test]
+fn decode_checks_the_width() {
+    assert_eq!(
+        ram::ABSOLUTE_POSITION.decode(&[0x12]),
+        Err(HerkulexError::Truncated)
+    );
+}
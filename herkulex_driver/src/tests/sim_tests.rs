@@ -0,0 +1,243 @@
+use crate::communication::HerkulexCommunication;
+use crate::error::HerkulexError;
+use crate::registers::{eep, ram};
+use crate::response::status_detail::*;
+use crate::response::status_error::*;
+use crate::response::{
+    check_reply, BROADCAST_ID, EEP_WRITE, RAM_READ, RAM_WRITE, REBOOT, ROLLBACK, STAT, S_JOG,
+};
+use crate::sim::{packet, VirtualLine, VirtualServo, TORQUE_ON};
+
+/// Servo with the torque on and replying to every request
+fn servo(id: u8) -> VirtualServo {
+    let mut servo = VirtualServo::new(id);
+    servo.set_register(ram::ACK_POLICY, 2);
+    servo.set_register(ram::TORQUE_CONTROL, TORQUE_ON.into());
+    servo
+}
+
+/// S_JOG to `position` in `playtime` ticks, green LED on
+fn move_to(id: u8, position: u16, playtime: u8) -> Vec<u8> {
+    let [lsb, msb] = position.to_le_bytes();
+    packet(id, S_JOG, &[playtime, lsb, msb, 0x04, id])
+}
+
+#[test]
+fn led_control_reply_of_the_manual() {
+    let mut servo = servo(0xFD);
+    servo.set_register(ram::LED_CONTROL, 0x01);
+    let reply = servo.receive(&[0xFF, 0xFF, 0x09, 0xFD, 0x04, 0xC4, 0x3A, 0x35, 0x01]);
+    assert_eq!(
+        reply,
+        [0xFF, 0xFF, 0x0C, 0xFD, 0x44, 0xC2, 0x3C, 0x35, 0x01, 0x01, 0x00, 0x42]
+    );
+}
+
+#[test]
+fn ram_is_loaded_from_the_eep_at_reboot() {
+    let mut servo = servo(0x01);
+    servo.receive(&packet(
+        0x01,
+        EEP_WRITE,
+        &[eep::MAX_TEMPERATURE.address, 1, 0x80],
+    ));
+    assert_eq!(servo.register(ram::MAX_TEMPERATURE), 0xDF);
+    servo.receive(&packet(0x01, REBOOT, &[]));
+    assert_eq!(servo.register(ram::MAX_TEMPERATURE), 0x80);
+    assert_eq!(servo.register(ram::TORQUE_CONTROL), 0);
+}
+
+#[test]
+fn ack_policy() {
+    let mut servo = VirtualServo::new(0x01);
+    let stat = packet(0x01, STAT, &[]);
+    let write = packet(0x01, RAM_WRITE, &[ram::LED_CONTROL.address, 1, 0x01]);
+    // Factory policy, only the reads and STAT are answered
+    assert!(!servo.receive(&stat).is_empty());
+    assert!(servo.receive(&write).is_empty());
+
+    servo.set_register(ram::ACK_POLICY, 2);
+    assert!(!servo.receive(&write).is_empty());
+    servo.set_register(ram::ACK_POLICY, 0);
+    assert!(servo.receive(&stat).is_empty());
+}
+
+#[test]
+fn broadcast_is_handled_without_reply() {
+    let mut servo = servo(0x01);
+    let write = packet(
+        BROADCAST_ID,
+        RAM_WRITE,
+        &[ram::LED_CONTROL.address, 1, 0x04],
+    );
+    assert!(servo.receive(&write).is_empty());
+    assert_eq!(servo.register(ram::LED_CONTROL), 0x04);
+}
+
+#[test]
+fn other_ids_are_ignored() {
+    let mut servo = servo(0x01);
+    assert!(servo.receive(&packet(0x02, STAT, &[])).is_empty());
+}
+
+#[test]
+fn read_out_of_the_map() {
+    let mut servo = servo(0x01);
+    let request = packet(0x01, RAM_READ, &[73, 2]);
+    let reply = check_reply(&request, &servo.receive(&request));
+    assert!(reply.is_err());
+    assert_eq!(servo.register(ram::STATUS_ERROR), INVALID_PACKET.into());
+    assert_ne!(
+        servo.register(ram::STATUS_DETAIL) as u8 & EXCEED_REG_RANGE,
+        0
+    );
+}
+
+#[test]
+fn measures_are_read_only() {
+    let mut servo = servo(0x01);
+    servo.receive(&packet(
+        0x01,
+        RAM_WRITE,
+        &[ram::ABSOLUTE_POSITION.address, 2, 0, 0],
+    ));
+    assert_eq!(servo.register(ram::ABSOLUTE_POSITION), 512);
+    assert_ne!(
+        servo.register(ram::STATUS_DETAIL) as u8 & EXCEED_REG_RANGE,
+        0
+    );
+}
+
+#[test]
+fn clearing_the_status_error() {
+    let mut servo = servo(0x01);
+    servo.receive(&packet(0x01, 0x42, &[]));
+    assert_ne!(
+        servo.register(ram::STATUS_DETAIL) as u8 & UNKNOWN_COMMAND,
+        0
+    );
+    servo.receive(&packet(0x01, RAM_WRITE, &[ram::STATUS_ERROR.address, 1, 0]));
+    assert_eq!(servo.register(ram::STATUS_ERROR), 0);
+    assert_eq!(
+        servo.register(ram::STATUS_DETAIL) as u8 & UNKNOWN_COMMAND,
+        0
+    );
+}
+
+#[test]
+fn invalid_checksum() {
+    let mut servo = servo(0x01);
+    let mut request = packet(0x01, STAT, &[]);
+    request[5] ^= 0x02;
+    assert!(servo.receive(&request).is_empty());
+    assert_ne!(servo.register(ram::STATUS_DETAIL) as u8 & CHECKSUM_ERROR, 0);
+}
+
+#[test]
+fn reaches_the_goal_in_the_playtime() {
+    let mut servo = servo(0x01);
+    servo.receive(&move_to(0x01, 712, 40));
+    assert_eq!(servo.register(ram::LED_CONTROL), 0x01);
+    assert_eq!(servo.register(ram::ABSOLUTE_GOAL_POSITION), 712);
+
+    let mut positions = Vec::new();
+    for _ in 0..40 {
+        assert_ne!(
+            servo.register(ram::STATUS_DETAIL) as u8 & INPOSITION,
+            INPOSITION
+        );
+        servo.step();
+        positions.push(servo.position());
+    }
+    assert!(positions.windows(2).all(|p| p[0] <= p[1]));
+    assert_eq!(servo.register(ram::ABSOLUTE_POSITION), 712);
+    let detail = servo.register(ram::STATUS_DETAIL) as u8;
+    assert_eq!(detail & (MOVING | INPOSITION), INPOSITION);
+}
+
+#[test]
+fn accelerates_then_decelerates() {
+    let mut servo = servo(0x01);
+    servo.receive(&move_to(0x01, 812, 100));
+    let mut speeds = Vec::new();
+    for _ in 0..100 {
+        let position = servo.position();
+        servo.step();
+        speeds.push(servo.position() - position);
+    }
+    // 25% of the playtime to accelerate and 25% to decelerate
+    assert!(speeds[0] < speeds[10] && speeds[10] < speeds[24]);
+    assert!((speeds[30] - speeds[60]).abs() < 0.01);
+    assert!(speeds[99] < speeds[80]);
+}
+
+#[test]
+fn goal_out_of_the_limits() {
+    let mut servo = servo(0x01);
+    servo.receive(&move_to(0x01, 1010, 40));
+    servo.step();
+    assert_eq!(servo.position(), 512.0);
+    assert_eq!(
+        servo.register(ram::STATUS_ERROR),
+        EXCEED_POSITION_LIMIT.into()
+    );
+}
+
+#[test]
+fn free_servo_doesnt_move() {
+    let mut servo = VirtualServo::new(0x01);
+    servo.receive(&move_to(0x01, 712, 10));
+    servo.step();
+    assert_eq!(servo.position(), 512.0);
+}
+
+#[test]
+fn turns_in_speed_mode() {
+    let mut servo = servo(0x01);
+    // Full speed, the position decreasing
+    servo.receive(&packet(0x01, S_JOG, &[0, 0xFF, 0x43, 0x02, 0x01]));
+    servo.step();
+    assert!(servo.position() < 512.0);
+    assert_eq!(servo.register(ram::CURRENT_CONTROL_MODE), 1);
+    assert_eq!(servo.register(ram::PWM), -0x03FF);
+}
+
+#[test]
+fn overheating_releases_the_torque() {
+    let mut servo = servo(0x01);
+    servo.temperature = 0xE0;
+    servo.step();
+    assert_eq!(servo.register(ram::STATUS_ERROR), EXCEED_TEMPERATURE.into());
+    assert_eq!(servo.register(ram::TORQUE_CONTROL), 0);
+    assert_eq!(servo.register(ram::STATUS_DETAIL) as u8 & MOTOR_ON, 0);
+}
+
+#[test]
+fn rollback_keeps_the_id() {
+    let mut servo = servo(0x01);
+    servo.receive(&packet(0x01, EEP_WRITE, &[eep::ACK_POLICY.address, 1, 2]));
+    servo.receive(&packet(0x01, ROLLBACK, &[1, 1]));
+    servo.receive(&packet(0x01, REBOOT, &[]));
+    assert_eq!(servo.id(), 0x01);
+    assert_eq!(servo.register(ram::ACK_POLICY), 1);
+}
+
+#[test]
+fn replies_of_the_same_id_collide() {
+    let mut line = VirtualLine::new(&[0x01]);
+    let mut twin = VirtualServo::new(0x01);
+    twin.set_register(ram::LED_CONTROL, 0x06);
+    line.add(twin);
+    line.send_message(ram::LED_CONTROL.request(0x01));
+    assert_eq!(line.read_message(), Err(HerkulexError::Timeout));
+}
+
+#[test]
+fn servos_share_the_line() {
+    let mut line = VirtualLine::new(&[0x01, 0x02]);
+    line.with_servo(0x02, |servo| servo.temperature = 0x50);
+    let request = ram::TEMPERATURE.request(0x02);
+    line.send_message(request.clone());
+    let reply = check_reply(&request, &line.read_message().unwrap()).unwrap();
+    assert_eq!(ram::TEMPERATURE.decode(&reply.value), Ok(0x50));
+}
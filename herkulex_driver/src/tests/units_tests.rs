@@ -0,0 +1,128 @@
+use crate::error::HerkulexError;
+use crate::units::*;
+use drs_0x01::Rotation;
+
+#[test]
+fn angles_of_the_positions() {
+    assert_eq!(Angle::from_position(CENTER_POSITION), Angle(0));
+    assert_eq!(Angle::from_position(MIN_POSITION), Angle(-159_575));
+    assert_eq!(Angle::from_position(MAX_POSITION), Angle(159_250));
+    assert_eq!(Angle::from_position(513).degrees(), 0.325);
+}
+
+#[test]
+fn positions_of_the_angles() {
+    assert_eq!(Angle::from_degrees(0).to_position(), Ok(512));
+    assert_eq!(Angle::from_degrees(90).to_position(), Ok(789));
+    assert_eq!(Angle::from_degrees(-90).to_position(), Ok(235));
+    // Halfway between two counts
+    assert_eq!(Angle(162).to_position(), Ok(512));
+    assert_eq!(Angle(163).to_position(), Ok(513));
+    assert_eq!(Angle(-163).to_position(), Ok(511));
+    for position in MIN_POSITION..=MAX_POSITION {
+        assert_eq!(Angle::from_position(position).to_position(), Ok(position));
+    }
+}
+
+#[test]
+fn radians() {
+    let angle = Angle::from_radians(core::f32::consts::FRAC_PI_2);
+    assert!((angle.0 - 90_000).abs() <= 1);
+    assert!((Angle::from_degrees(180).radians() - core::f32::consts::PI).abs() < 1e-6);
+}
+
+#[test]
+fn angles_out_of_the_limits() {
+    assert_eq!(
+        Angle::from_degrees(160).to_position(),
+        Err(HerkulexError::InvalidValue(1004))
+    );
+    assert_eq!(
+        Angle::from_degrees(-160).to_position(),
+        Err(HerkulexError::InvalidValue(20))
+    );
+    assert!(Angle(i32::MIN).to_position().is_err());
+    assert_eq!(Angle::from_degrees(i32::MAX), Angle(i32::MAX));
+    assert!(Angle::from_degrees(i32::MIN).to_position().is_err());
+}
+
+#[test]
+fn velocity_of_the_differential_position() {
+    // 0.325° in 11.2ms
+    assert_eq!(
+        AngularVelocity::from_differential_position(1),
+        AngularVelocity(29_018)
+    );
+    assert_eq!(
+        AngularVelocity::from_differential_position(-10),
+        AngularVelocity(-290_179)
+    );
+}
+
+#[test]
+fn speed_of_a_velocity() {
+    assert!(matches!(
+        NO_LOAD_VELOCITY.to_speed(),
+        Ok((1023, Rotation::CounterClockwise))
+    ));
+    assert!(matches!(
+        AngularVelocity::from_degrees_per_second(-180).to_speed(),
+        Ok((509, Rotation::Clockwise))
+    ));
+    assert!(matches!(
+        AngularVelocity(0).to_speed(),
+        Ok((0, Rotation::CounterClockwise))
+    ));
+    assert!(matches!(
+        AngularVelocity::from_degrees_per_second(400).to_speed(),
+        Err(HerkulexError::InvalidValue(1132))
+    ));
+    assert_eq!(
+        AngularVelocity::from_degrees_per_second(i32::MIN),
+        AngularVelocity(i32::MIN)
+    );
+    assert!(AngularVelocity(i32::MIN).to_speed().is_err());
+}
+
+#[test]
+fn playtime_of_a_velocity() {
+    let velocity = AngularVelocity::from_degrees_per_second(90);
+    // 1s is 89.3 ticks
+    assert_eq!(velocity.playtime(Angle::from_degrees(90)), Ok(90));
+    assert_eq!(velocity.playtime(Angle::from_degrees(-90)), Ok(90));
+    assert_eq!(velocity.playtime(Angle(0)), Ok(0));
+    // 4s, longer than a play time
+    assert!(velocity.playtime(Angle::from_degrees(360)).is_err());
+    assert_eq!(
+        AngularVelocity(0).playtime(Angle::from_degrees(90)),
+        Err(HerkulexError::InvalidValue(0))
+    );
+}
+
+#[test]
+fn voltages() {
+    assert_eq!(millivolts(100), 7400);
+    assert_eq!(voltage_raw(7400), Ok(100));
+    assert_eq!(voltage_raw(7436), Ok(100));
+    assert_eq!(voltage_raw(7437), Ok(101));
+    assert_eq!(voltage_raw(20_000), Err(HerkulexError::InvalidValue(270)));
+}
+
+#[test]
+fn temperature_interpolation() {
+    // Not the table of the datasheet, only points to check the interpolation
+    let table = TemperatureTable(&[(10, -20), (110, 30), (210, 130)]);
+    assert_eq!(table.celsius(10), Ok(-20));
+    assert_eq!(table.celsius(60), Ok(5));
+    assert_eq!(table.celsius(110), Ok(30));
+    assert_eq!(table.celsius(111), Ok(31));
+    assert_eq!(table.celsius(210), Ok(130));
+    assert_eq!(table.celsius(9), Err(HerkulexError::InvalidValue(9)));
+    assert_eq!(table.celsius(211), Err(HerkulexError::InvalidValue(211)));
+    assert!(TemperatureTable(&[]).celsius(0).is_err());
+}
+
+#[test]
+fn maximum_temperature_of_the_datasheet() {
+    assert_eq!(TEMPERATURE_TABLE.celsius(0xDF), Ok(85));
+}
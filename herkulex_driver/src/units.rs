@@ -0,0 +1,170 @@
+//! Physical units of the raw values of the DRS-0101.
+//!
+//! The values are integers so the firmwares don't need floats: angles in thousandths of
+//! degree, speeds in thousandths of degree per second, voltages in millivolts. A position
+//! count is 0.325°, the centre is 512, and a tick is 11.2ms.
+
+use crate::error::HerkulexError;
+use crate::jog::MAX_JOG_VALUE;
+use drs_0x01::Rotation;
+
+/// Position of the centre of the servo
+pub const CENTER_POSITION: u16 = 512;
+/// Millidegrees of a position count
+pub const MILLIDEGREES_PER_COUNT: i32 = 325;
+/// Factory limits of the position, the servo refuses the goals outside
+pub const MIN_POSITION: u16 = 21;
+pub const MAX_POSITION: u16 = 1002;
+/// Duration of a tick, in tenths of millisecond
+const TICK_TENTHS_OF_MS: i64 = 112;
+/// Millivolts of a count of the voltage register
+pub const MILLIVOLTS_PER_COUNT: u16 = 74;
+/// Speed with no load at 7.4V, 60° in 0.166s, reached with the largest jog value
+pub const NO_LOAD_VELOCITY: AngularVelocity = AngularVelocity(361_446);
+
+/// Rounded division, away from 0 on a half
+fn div_round(numerator: i64, denominator: i64) -> i64 {
+    let half = denominator / 2;
+    if (numerator < 0) == (denominator < 0) {
+        (numerator + half) / denominator
+    } else {
+        (numerator - half) / denominator
+    }
+}
+
+/// Angle from the centre of the servo, in thousandths of degree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Angle(pub i32);
+
+impl Angle {
+    /// Saturates at the bounds of an `i32`, out of the limits of the servo anyway
+    pub const fn from_degrees(degrees: i32) -> Angle {
+        Angle(degrees.saturating_mul(1000))
+    }
+
+    pub fn from_radians(radians: f32) -> Angle {
+        Angle((radians.to_degrees() * 1000.0) as i32)
+    }
+
+    pub fn degrees(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+
+    pub fn radians(self) -> f32 {
+        self.degrees().to_radians()
+    }
+
+    /// Angle of a position count
+    pub const fn from_position(position: u16) -> Angle {
+        Angle((position as i32 - CENTER_POSITION as i32) * MILLIDEGREES_PER_COUNT)
+    }
+
+    /// Nearest position count, `InvalidValue` with the count if it is out of the factory
+    /// limits
+    pub fn to_position(self) -> Result<u16, HerkulexError> {
+        let position =
+            div_round(self.0.into(), MILLIDEGREES_PER_COUNT.into()) + i64::from(CENTER_POSITION);
+        if position < MIN_POSITION.into() || position > MAX_POSITION.into() {
+            return Err(HerkulexError::InvalidValue(
+                position.clamp(i32::MIN.into(), i32::MAX.into()) as i32,
+            ));
+        }
+        Ok(position as u16)
+    }
+}
+
+/// Angular velocity, in thousandths of degree per second
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct AngularVelocity(pub i32);
+
+impl AngularVelocity {
+    /// Saturates at the bounds of an `i32`, faster than the servo anyway
+    pub const fn from_degrees_per_second(degrees: i32) -> AngularVelocity {
+        AngularVelocity(degrees.saturating_mul(1000))
+    }
+
+    pub fn degrees_per_second(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+
+    /// Velocity of a differential position, the counts moved during a tick
+    pub fn from_differential_position(counts: i16) -> AngularVelocity {
+        let millidegrees = i64::from(counts) * i64::from(MILLIDEGREES_PER_COUNT);
+        AngularVelocity(div_round(millidegrees * 10_000, TICK_TENTHS_OF_MS) as i32)
+    }
+
+    /// Jog value and direction of a continuous rotation at this velocity, rounded. The jog
+    /// value is a PWM so the velocity is only reached with no load at 7.4V. `InvalidValue` with
+    /// the jog value if it is faster than `NO_LOAD_VELOCITY`.
+    pub fn to_speed(self) -> Result<(u16, Rotation), HerkulexError> {
+        // The positions increase counter clockwise
+        let rotation = if self.0 < 0 {
+            Rotation::Clockwise
+        } else {
+            Rotation::CounterClockwise
+        };
+        let speed = div_round(
+            i64::from(self.0).abs() * i64::from(MAX_JOG_VALUE),
+            NO_LOAD_VELOCITY.0.into(),
+        );
+        match u16::try_from(speed) {
+            Ok(speed) if speed <= MAX_JOG_VALUE => Ok((speed, rotation)),
+            _ => Err(HerkulexError::InvalidValue(
+                speed.min(i32::MAX.into()) as i32
+            )),
+        }
+    }
+
+    /// Ticks to move by `distance` at this velocity, rounded up. `InvalidValue` with the
+    /// number of ticks if the velocity isn't positive or the move is longer than a play time.
+    pub fn playtime(self, distance: Angle) -> Result<u8, HerkulexError> {
+        if self.0 <= 0 {
+            return Err(HerkulexError::InvalidValue(self.0));
+        }
+        // distance / velocity in tenths of ms, divided by the tick
+        let numerator = i64::from(distance.0).abs() * 10_000;
+        let denominator = i64::from(self.0) * TICK_TENTHS_OF_MS;
+        let ticks = (numerator + denominator - 1) / denominator;
+        u8::try_from(ticks)
+            .map_err(|_| HerkulexError::InvalidValue(ticks.min(i32::MAX.into()) as i32))
+    }
+}
+
+/// Millivolts of the raw value of a voltage register
+pub const fn millivolts(raw: u8) -> u16 {
+    raw as u16 * MILLIVOLTS_PER_COUNT
+}
+
+/// Raw value of a voltage register, rounded. `InvalidValue` if it doesn't fit the register.
+pub fn voltage_raw(millivolts: u16) -> Result<u8, HerkulexError> {
+    let raw = div_round(millivolts.into(), MILLIVOLTS_PER_COUNT.into());
+    u8::try_from(raw).map_err(|_| HerkulexError::InvalidValue(raw as i32))
+}
+
+/// Raw values of the temperature register and their temperature in °C. The raw values must be
+/// increasing, the values between two points are interpolated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TemperatureTable(pub(crate) &'static [(u8, i16)]);
+
+/// Conversion table of the temperature register of the datasheet.
+/// Only 0xDF = 85°C, the default maximum temperature, is transcribed so far: the other points
+/// of the datasheet table must be added, until then the other raw values are `InvalidValue`.
+pub const TEMPERATURE_TABLE: TemperatureTable = TemperatureTable(&[(0xDF, 85)]);
+
+impl TemperatureTable {
+    /// Temperature in °C, `InvalidValue` if `raw` is outside the table
+    pub fn celsius(&self, raw: u8) -> Result<i16, HerkulexError> {
+        let invalid = HerkulexError::InvalidValue(raw.into());
+        let next = self.0.iter().position(|(point, _)| *point >= raw);
+        match next {
+            Some(0) if self.0[0].0 == raw => Ok(self.0[0].1),
+            Some(0) | None => Err(invalid),
+            Some(i) => {
+                let (raw0, celsius0) = self.0[i - 1];
+                let (raw1, celsius1) = self.0[i];
+                let offset = i64::from(raw - raw0) * i64::from(celsius1 - celsius0);
+                Ok(celsius0 + div_round(offset, i64::from(raw1 - raw0)) as i16)
+            }
+        }
+    }
+}
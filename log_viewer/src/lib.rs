@@ -0,0 +1,93 @@
+//! Extracts the log records sent to the collector from a bus recording,
+//! see `network_protocol::log`.
+
+use network_protocol::log::{Level, LogRecord, LOG_COMMAND};
+use network_protocol::protocol::errors::RecordingError;
+use network_protocol::protocol::protocol::Protocol;
+use network_protocol::protocol::recording::Recording;
+use network_protocol::protocol::CanId;
+use std::collections::BTreeMap;
+
+/// A log record with the node which sent it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    /// Time at which the record was complete, in microseconds since the start of the recording
+    pub timestamp: u32,
+    pub node: CanId,
+    pub level: Level,
+    pub dropped: u8,
+    pub tag: String,
+    pub text: String,
+}
+
+/// The records sent to `collector`, in the order they were received. When several nodes
+/// recorded the bus, only the frames seen by the first one are used.
+pub fn collect_logs(
+    recording: &Recording,
+    collector: CanId,
+) -> Result<Vec<LogLine>, RecordingError> {
+    let mut protocol = Protocol::new(collector).map_err(RecordingError::Sink)?;
+    let mut recorder = None;
+    let mut lines = Vec::new();
+    for frame in recording.frames() {
+        let frame = frame?;
+        if *recorder.get_or_insert(frame.node) != frame.node {
+            continue;
+        }
+        protocol
+            .process_frame(&frame.frame)
+            .map_err(RecordingError::Sink)?;
+        // The ACKs of the collector are in the recording already
+        while protocol
+            .get_next_frame_to_send()
+            .map_err(RecordingError::Sink)?
+            .is_some()
+        {}
+        while let Some(message) = protocol.received.front_message() {
+            if message.data.first() == Some(&LOG_COMMAND) {
+                // A corrupted record is skipped like any message which isn't a log
+                if let Ok(record) = LogRecord::decode(message.data) {
+                    lines.push(LogLine {
+                        timestamp: frame.timestamp,
+                        node: message.src,
+                        level: record.level,
+                        dropped: record.dropped,
+                        tag: record.tag.to_string(),
+                        text: record.text.to_string(),
+                    });
+                }
+            }
+            protocol.received.pop_front();
+        }
+    }
+    Ok(lines)
+}
+
+/// Names of the nodes given in the recording
+pub fn node_names(recording: &Recording) -> Result<BTreeMap<CanId, String>, RecordingError> {
+    recording
+        .nodes()
+        .map(|node| node.map(|node| (node.id, node.name().to_string())))
+        .collect()
+}
+
+/// `  12.345678 herkulex     WARN  motors: too hot`, the node id is used when it has no name
+pub fn format_line(line: &LogLine, names: &BTreeMap<CanId, String>) -> String {
+    let node = match names.get(&line.node) {
+        Some(name) => name.clone(),
+        None => format!("node {}", usize::from(line.node)),
+    };
+    let mut formatted = format!(
+        "{:>4}.{:06} {:<12} {:<5} {}: {}",
+        line.timestamp / 1_000_000,
+        line.timestamp % 1_000_000,
+        node,
+        line.level,
+        line.tag,
+        line.text
+    );
+    if line.dropped > 0 {
+        formatted.push_str(&format!(" ({} records lost before)", line.dropped));
+    }
+    formatted
+}
@@ -0,0 +1,48 @@
+//! Displays the logs of every node found in a bus recording
+//!
+//! Usage: log_viewer <recording> [collector id, the brain by default]
+
+use log_viewer::{collect_logs, format_line, node_names};
+use network_protocol::protocol::recording::Recording;
+use network_protocol::protocol::CanId;
+use network_protocol::schema::nodes;
+use std::process::exit;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = match args.next() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: log_viewer <recording> [collector id]");
+            exit(2);
+        }
+    };
+    let collector = match args.next().map(|id| id.parse::<usize>()) {
+        None => nodes::BRAIN,
+        Some(Ok(id)) => id,
+        Some(Err(error)) => {
+            eprintln!("invalid collector id: {}", error);
+            exit(2);
+        }
+    };
+    let collector = CanId::new(collector).unwrap_or_else(|error| {
+        eprintln!("invalid collector id: {:?}", error);
+        exit(2);
+    });
+
+    let raw = std::fs::read(&path).unwrap_or_else(|error| {
+        eprintln!("can't read {}: {}", path, error);
+        exit(1);
+    });
+    let result = Recording::parse(&raw).and_then(|recording| {
+        let names = node_names(&recording)?;
+        for line in collect_logs(&recording, collector)? {
+            println!("{}", format_line(&line, &names));
+        }
+        Ok(())
+    });
+    if let Err(error) = result {
+        eprintln!("invalid recording {}: {:?}", path, error);
+        exit(1);
+    }
+}
@@ -0,0 +1,437 @@
+//! Generates `schema.rs` and `PROTOCOL.md` in OUT_DIR from `robot.schema`
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+#[path = "../protocol_derive/src/ids.rs"]
+mod ids;
+
+use ids::REPLY_FLAG;
+
+const SCHEMA: &str = "robot.schema";
+/// The rules for the ids, the derive checks them too
+const IDS: &str = "../protocol_derive/src/ids.rs";
+
+struct Field {
+    name: String,
+    ty: &'static str,
+}
+
+struct Command {
+    name: String,
+    id: u8,
+    /// The command answered, for a reply
+    answers: Option<String>,
+    fields: Vec<Field>,
+}
+
+fn type_size(ty: &str) -> Option<(&'static str, usize)> {
+    Some(match ty {
+        "u8" => ("u8", 1),
+        "i8" => ("i8", 1),
+        "bool" => ("bool", 1),
+        "u16" => ("u16", 2),
+        "i16" => ("i16", 2),
+        "u32" => ("u32", 4),
+        "i32" => ("i32", 4),
+        "f32" => ("f32", 4),
+        _ => return None,
+    })
+}
+
+fn size(command: &Command) -> usize {
+    command
+        .fields
+        .iter()
+        .map(|f| type_size(f.ty).unwrap().1)
+        .sum()
+}
+
+fn parse_id(text: &str, line: usize) -> u8 {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.unwrap_or_else(|_| panic!("{}:{}: invalid id {}", SCHEMA, line, text))
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// FNV-1a over the lines which carry meaning, so editing a comment doesn't change it
+fn hash(lines: &[String]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for line in lines {
+        for byte in line.bytes().chain([b'\n']) {
+            hash ^= u32::from(byte);
+            hash = hash.wrapping_mul(0x0100_0193);
+        }
+    }
+    hash
+}
+
+fn parse(text: &str) -> (Vec<(String, u8)>, Vec<Command>, Vec<String>) {
+    let mut nodes = Vec::new();
+    let mut commands: Vec<Command> = Vec::new();
+    let mut meaningful = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let content = raw.split('#').next().unwrap().trim();
+        if content.is_empty() {
+            continue;
+        }
+        let words: Vec<&str> = content.split_whitespace().collect();
+        meaningful.push(words.join(" "));
+        let field_start = match words[0] {
+            "node" if words.len() == 3 => {
+                nodes.push((words[1].to_string(), parse_id(words[2], line)));
+                continue;
+            }
+            "command" if words.len() >= 3 => {
+                let id = ids::command_id(parse_id(words[2], line))
+                    .unwrap_or_else(|error| panic!("{}:{}: {}", SCHEMA, line, error));
+                commands.push(Command {
+                    name: words[1].to_string(),
+                    id,
+                    answers: None,
+                    fields: Vec::new(),
+                });
+                3
+            }
+            "reply" if words.len() >= 3 => {
+                let answered = commands
+                    .iter()
+                    .find(|c| c.answers.is_none() && c.name == words[2])
+                    .unwrap_or_else(|| panic!("{}:{}: unknown command {}", SCHEMA, line, words[2]));
+                commands.push(Command {
+                    name: words[1].to_string(),
+                    id: ids::reply_id(answered.id)
+                        .unwrap_or_else(|error| panic!("{}:{}: {}", SCHEMA, line, error)),
+                    answers: Some(words[2].to_string()),
+                    fields: Vec::new(),
+                });
+                3
+            }
+            _ => panic!("{}:{}: can't parse `{}`", SCHEMA, line, content),
+        };
+        for field in &words[field_start..] {
+            let (name, ty) = field
+                .split_once(':')
+                .unwrap_or_else(|| panic!("{}:{}: field without type {}", SCHEMA, line, field));
+            let (ty, _) =
+                type_size(ty).unwrap_or_else(|| panic!("{}:{}: unknown type {}", SCHEMA, line, ty));
+            commands.last_mut().unwrap().fields.push(Field {
+                name: name.to_string(),
+                ty,
+            });
+        }
+    }
+
+    for (i, command) in commands.iter().enumerate() {
+        assert!(
+            commands[..i]
+                .iter()
+                .all(|c| c.id != command.id && c.name != command.name),
+            "{}: {} is defined twice or reuses an id",
+            SCHEMA,
+            command.name
+        );
+    }
+    for (i, (name, id)) in nodes.iter().enumerate() {
+        assert!(*id < 12, "{}: node {} uses a multicast id", SCHEMA, name);
+        assert!(
+            nodes[..i].iter().all(|(n, other)| n != name && other != id),
+            "{}: node {} is defined twice or reuses an id",
+            SCHEMA,
+            name
+        );
+    }
+    assert!(
+        commands
+            .iter()
+            .any(|c| c.name == "heartbeat" && c.fields.len() == 1 && c.fields[0].ty == "u32"),
+        "{}: the heartbeat command with a u32 field is required",
+        SCHEMA
+    );
+    (nodes, commands, meaningful)
+}
+
+fn generate_rust(nodes: &[(String, u8)], commands: &[Command], hash: u32) -> String {
+    let mut out = String::new();
+    writeln!(out, "/// Hash of `robot.schema`, sent in the heartbeat").unwrap();
+    writeln!(out, "pub const SCHEMA_HASH: u32 = {:#010x};\n", hash).unwrap();
+
+    writeln!(out, "/// Ids of the nodes on the bus").unwrap();
+    writeln!(out, "pub mod nodes {{").unwrap();
+    for (name, id) in nodes {
+        writeln!(
+            out,
+            "    pub const {}: usize = {};",
+            name.to_uppercase(),
+            id
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    for command in commands {
+        let ty = camel_case(&command.name);
+        let len = size(command);
+        match &command.answers {
+            Some(answered) => writeln!(out, "/// Reply to `{}`", camel_case(answered)),
+            None => writeln!(out, "/// Command `{}`", command.name),
+        }
+        .unwrap();
+        writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Default)]").unwrap();
+        if command.fields.is_empty() {
+            writeln!(out, "pub struct {};\n", ty).unwrap();
+        } else {
+            writeln!(out, "pub struct {} {{", ty).unwrap();
+            for field in &command.fields {
+                writeln!(out, "    pub {}: {},", field.name, field.ty).unwrap();
+            }
+            writeln!(out, "}}\n").unwrap();
+        }
+
+        writeln!(out, "impl {} {{", ty).unwrap();
+        writeln!(out, "    pub const COMMAND: u8 = {:#04x};", command.id).unwrap();
+        writeln!(
+            out,
+            "    /// Length of the payload following the command id"
+        )
+        .unwrap();
+        writeln!(out, "    pub const LEN: usize = {};\n", len).unwrap();
+
+        writeln!(out, "    /// The command id followed by the fields").unwrap();
+        writeln!(out, "    pub fn encode(&self) -> [u8; 1 + Self::LEN] {{").unwrap();
+        writeln!(out, "        #[allow(unused_mut)]").unwrap();
+        writeln!(
+            out,
+            "        let mut data = [Self::COMMAND; 1 + Self::LEN];"
+        )
+        .unwrap();
+        let mut offset = 1;
+        for field in &command.fields {
+            let (_, field_len) = type_size(field.ty).unwrap();
+            let bytes = if field.ty == "bool" {
+                format!("[self.{} as u8]", field.name)
+            } else {
+                format!("self.{}.to_le_bytes()", field.name)
+            };
+            writeln!(
+                out,
+                "        data[{}..{}].copy_from_slice(&{});",
+                offset,
+                offset + field_len,
+                bytes
+            )
+            .unwrap();
+            offset += field_len;
+        }
+        writeln!(out, "        data\n    }}\n").unwrap();
+
+        writeln!(
+            out,
+            "    /// Reads the payload following the command id, the padding after the fields is ignored"
+        )
+        .unwrap();
+        if command.fields.is_empty() {
+            writeln!(
+                out,
+                "    pub fn decode(_payload: &[u8]) -> Result<Self, SchemaError> {{"
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                out,
+                "    pub fn decode(payload: &[u8]) -> Result<Self, SchemaError> {{"
+            )
+            .unwrap();
+            writeln!(out, "        if payload.len() < Self::LEN {{").unwrap();
+            writeln!(
+                out,
+                "            return Err(SchemaError::TooShort(Self::COMMAND));\n        }}"
+            )
+            .unwrap();
+        }
+        let mut offset = 0;
+        let mut values = Vec::new();
+        for field in &command.fields {
+            let (_, field_len) = type_size(field.ty).unwrap();
+            let value = if field.ty == "bool" {
+                format!("payload[{}] != 0", offset)
+            } else {
+                format!(
+                    "{}::from_le_bytes(payload[{}..{}].try_into().unwrap())",
+                    field.ty,
+                    offset,
+                    offset + field_len
+                )
+            };
+            values.push(format!("            {}: {},", field.name, value));
+            offset += field_len;
+        }
+        if values.is_empty() {
+            writeln!(out, "        Ok({})\n    }}", ty).unwrap();
+        } else {
+            writeln!(
+                out,
+                "        Ok({} {{\n{}\n        }})\n    }}",
+                ty,
+                values.join("\n")
+            )
+            .unwrap();
+        }
+        writeln!(out, "}}\n").unwrap();
+    }
+
+    writeln!(out, "/// Every message of the schema").unwrap();
+    writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq)]").unwrap();
+    writeln!(out, "pub enum SchemaMessage {{").unwrap();
+    for command in commands {
+        let ty = camel_case(&command.name);
+        writeln!(out, "    {}({}),", ty, ty).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    writeln!(out, "impl SchemaMessage {{").unwrap();
+    writeln!(
+        out,
+        "    /// Decodes a whole message, starting with its command id"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    pub fn decode(data: &[u8]) -> Result<Self, SchemaError> {{"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "        let (command, payload) = data.split_first().ok_or(SchemaError::Empty)?;"
+    )
+    .unwrap();
+    writeln!(out, "        match *command {{").unwrap();
+    for command in commands {
+        let ty = camel_case(&command.name);
+        writeln!(
+            out,
+            "            {}::COMMAND => {}::decode(payload).map(SchemaMessage::{}),",
+            ty, ty, ty
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "            other => Err(SchemaError::UnknownCommand(other)),"
+    )
+    .unwrap();
+    writeln!(out, "        }}\n    }}\n").unwrap();
+
+    writeln!(out, "    pub fn name(&self) -> &'static str {{").unwrap();
+    writeln!(out, "        match self {{").unwrap();
+    for command in commands {
+        writeln!(
+            out,
+            "            SchemaMessage::{}(_) => \"{}\",",
+            camel_case(&command.name),
+            command.name
+        )
+        .unwrap();
+    }
+    writeln!(out, "        }}\n    }}\n").unwrap();
+
+    writeln!(out, "    #[cfg(feature = \"std\")]").unwrap();
+    writeln!(out, "    pub fn to_vec(&self) -> std::vec::Vec<u8> {{").unwrap();
+    writeln!(out, "        match self {{").unwrap();
+    for command in commands {
+        writeln!(
+            out,
+            "            SchemaMessage::{}(message) => message.encode().to_vec(),",
+            camel_case(&command.name)
+        )
+        .unwrap();
+    }
+    writeln!(out, "        }}\n    }}\n}}").unwrap();
+    out
+}
+
+fn generate_markdown(nodes: &[(String, u8)], commands: &[Command], hash: u32) -> String {
+    let mut out = String::new();
+    writeln!(out, "# CAN protocol\n").unwrap();
+    writeln!(
+        out,
+        "Generated from `network_protocol/{}`, schema hash `{:#010x}`.\n",
+        SCHEMA, hash
+    )
+    .unwrap();
+    writeln!(out, "## Nodes\n").unwrap();
+    writeln!(out, "| Node | Id |").unwrap();
+    writeln!(out, "|------|----|").unwrap();
+    for (name, id) in nodes {
+        writeln!(out, "| {} | {} |", name, id).unwrap();
+    }
+    writeln!(out, "\n## Messages\n").unwrap();
+    writeln!(
+        out,
+        "The first byte is the command id, the fields follow in little endian. \
+         A reply has the id of its command with {:#04x} set, \
+         an error reply is `[0xff, command, reason]`.\n",
+        REPLY_FLAG
+    )
+    .unwrap();
+    writeln!(out, "| Message | Id | Answers | Fields | Length |").unwrap();
+    writeln!(out, "|---------|----|---------|--------|--------|").unwrap();
+    for command in commands {
+        let fields: Vec<String> = command
+            .fields
+            .iter()
+            .map(|f| format!("{}: {}", f.name, f.ty))
+            .collect();
+        writeln!(
+            out,
+            "| {} | {:#04x} | {} | {} | {} |",
+            command.name,
+            command.id,
+            command.answers.as_deref().unwrap_or("-"),
+            if fields.is_empty() {
+                "-".to_string()
+            } else {
+                fields.join(", ")
+            },
+            1 + size(command)
+        )
+        .unwrap();
+    }
+    out
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SCHEMA);
+    println!("cargo:rerun-if-changed={}", IDS);
+    let text = fs::read_to_string(SCHEMA).expect("can't read the schema");
+    let (nodes, commands, meaningful) = parse(&text);
+    let hash = hash(&meaningful);
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(
+        Path::new(&out_dir).join("schema.rs"),
+        generate_rust(&nodes, &commands, hash),
+    )
+    .unwrap();
+    fs::write(
+        Path::new(&out_dir).join("PROTOCOL.md"),
+        generate_markdown(&nodes, &commands, hash),
+    )
+    .unwrap();
+}
@@ -1,6 +1,12 @@
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 
+// The code generated by `ProtocolMessage` names this crate, also when used inside it
+extern crate self as network_protocol;
+
+pub mod log;
 pub mod model;
+pub mod protocol;
+pub mod schema;
 
 use heapless::Vec;
 use crate::model::message::Message;
@@ -41,10 +47,12 @@ pub enum MessageCreationError {
 pub struct MessageSender<Tx: Write, Rx: Read> {
     host_id: u8,
     tx: Tx,
+    #[allow(dead_code)] // todo reading is not implemented yet
     rx: Rx,
     id_mess_counter: u8, // really a u3
     message_queue: [Option<Message>; MAX_ID_MESS_LEN as usize],
     received_buffer: Vec<Message, BUFFER_SIZE>, // todo not really the best type
+    #[allow(dead_code)] // todo reading is not implemented yet
     uncompleted_messages: Vec<Message, BUFFER_SIZE>,
 }
 
@@ -0,0 +1,335 @@
+//! Logging for the firmwares, without semihosting which halts the board when no debugger is
+//! attached. The records are formatted in RAM by a `BusLogger` and sent to a collector node
+//! when the node has nothing else to send, `log_viewer` displays them.
+//! A `WriteLogger` over `cortex_m_semihosting::hio::hstdout()` prints them like `hprintln!`.
+//!
+//! Record layout: `LOG_COMMAND` | level | dropped | tag length | text length | tag | text
+
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::message::Message;
+use crate::protocol::protocol::Protocol;
+use crate::protocol::{CanId, MAX_MESSAGE_LEN};
+use core::fmt;
+use heapless::{Deque, String, Vec};
+
+#[cfg(test)]
+mod tests;
+
+/// First byte of the log records, outside of the ids used by `robot.schema`
+pub const LOG_COMMAND: u8 = 0x7E;
+/// Longer tags lose their first module path segments
+pub const MAX_TAG_LEN: usize = 24;
+const RECORD_HEADER_SIZE: usize = 5;
+/// The text shares the rest of the message with the tag
+pub const MAX_TEXT_LEN: usize = MAX_MESSAGE_LEN - RECORD_HEADER_SIZE;
+
+/// Logs a message with the module path as tag, when `level` is enabled
+///
+/// `log!(logger, Level::Info, "speed {}", speed)`
+#[macro_export]
+macro_rules! log {
+    ($logger:expr, $level:expr, $($arg:tt)+) => {{
+        use $crate::log::Log as _;
+        let level = $level;
+        if $logger.enabled(level) {
+            $logger.log(level, module_path!(), format_args!($($arg)+));
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! error {
+    ($logger:expr, $($arg:tt)+) => { $crate::log!($logger, $crate::log::Level::Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($logger:expr, $($arg:tt)+) => { $crate::log!($logger, $crate::log::Level::Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($logger:expr, $($arg:tt)+) => { $crate::log!($logger, $crate::log::Level::Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($logger:expr, $($arg:tt)+) => { $crate::log!($logger, $crate::log::Level::Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($logger:expr, $($arg:tt)+) => { $crate::log!($logger, $crate::log::Level::Trace, $($arg)+) };
+}
+
+/// The most severe first, a logger keeps the levels up to its `max_level`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl TryFrom<u8> for Level {
+    type Error = LogError;
+
+    fn try_from(value: u8) -> Result<Self, LogError> {
+        match value {
+            1 => Ok(Level::Error),
+            2 => Ok(Level::Warn),
+            3 => Ok(Level::Info),
+            4 => Ok(Level::Debug),
+            5 => Ok(Level::Trace),
+            other => Err(LogError::InvalidLevel(other)),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogError {
+    /// The message doesn't start with `LOG_COMMAND`
+    NotALog,
+    InvalidLevel(u8),
+    TooShort,
+    InvalidUtf8,
+}
+
+/// A logging backend, used through the `log!` macros. The tag is the module path.
+pub trait Log {
+    fn enabled(&self, level: Level) -> bool;
+
+    fn log(&mut self, level: Level, tag: &'static str, args: fmt::Arguments);
+}
+
+impl<L: Log> Log for &mut L {
+    fn enabled(&self, level: Level) -> bool {
+        (**self).enabled(level)
+    }
+
+    fn log(&mut self, level: Level, tag: &'static str, args: fmt::Arguments) {
+        (**self).log(level, tag, args)
+    }
+}
+
+/// Longest prefix of `s` not longer than `max` bytes
+fn truncate(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// The end of a module path is the most useful, its first segments are dropped until it fits
+fn shorten_tag(mut tag: &str) -> &str {
+    while tag.len() > MAX_TAG_LEN {
+        match tag.find("::") {
+            Some(i) => tag = &tag[i + 2..],
+            None => return truncate(tag, MAX_TAG_LEN),
+        }
+    }
+    tag
+}
+
+/// Keeps what fits, `heapless::String` refuses a whole `&str` which doesn't
+struct Truncating<'a, const N: usize>(&'a mut String<N>);
+
+impl<const N: usize> fmt::Write for Truncating<'_, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = N - self.0.len();
+        // Can't fail, it fits
+        self.0.push_str(truncate(s, room)).unwrap();
+        Ok(())
+    }
+}
+
+/// A log record as sent on the bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogRecord<'a> {
+    pub level: Level,
+    /// Records lost by the sender before this one, because its buffer was full
+    pub dropped: u8,
+    pub tag: &'a str,
+    pub text: &'a str,
+}
+
+impl<'a> LogRecord<'a> {
+    /// The tag and then the text are truncated to fit in a message
+    pub fn encode(&self) -> Vec<u8, MAX_MESSAGE_LEN> {
+        let tag = shorten_tag(self.tag);
+        let text = truncate(self.text, MAX_TEXT_LEN - tag.len());
+        let mut data = Vec::new();
+        // Can't fail, the lengths are checked above
+        data.extend_from_slice(&[
+            LOG_COMMAND,
+            self.level as u8,
+            self.dropped,
+            tag.len() as u8,
+            text.len() as u8,
+        ])
+        .unwrap();
+        data.extend_from_slice(tag.as_bytes()).unwrap();
+        data.extend_from_slice(text.as_bytes()).unwrap();
+        data
+    }
+
+    /// Reads a received message, the padding after the text is ignored
+    pub fn decode(data: &'a [u8]) -> Result<Self, LogError> {
+        match data.first() {
+            Some(&LOG_COMMAND) => {}
+            Some(_) => return Err(LogError::NotALog),
+            None => return Err(LogError::TooShort),
+        }
+        if data.len() < RECORD_HEADER_SIZE {
+            return Err(LogError::TooShort);
+        }
+        let level = Level::try_from(data[1])?;
+        let tag_end = RECORD_HEADER_SIZE + data[3] as usize;
+        let text_end = tag_end + data[4] as usize;
+        if data.len() < text_end {
+            return Err(LogError::TooShort);
+        }
+        let utf8 = |bytes| core::str::from_utf8(bytes).map_err(|_| LogError::InvalidUtf8);
+        Ok(LogRecord {
+            level,
+            dropped: data[2],
+            tag: utf8(&data[RECORD_HEADER_SIZE..tag_end])?,
+            text: utf8(&data[tag_end..text_end])?,
+        })
+    }
+}
+
+struct Record {
+    level: Level,
+    tag: &'static str,
+    text: String<MAX_TEXT_LEN>,
+    dropped: u8,
+}
+
+/// Keeps up to `N` records in RAM until `flush` gives them to the protocol.
+/// When it is full the new records are dropped and counted in the next one.
+pub struct BusLogger<const N: usize> {
+    /// The node the records are sent to
+    pub collector: CanId,
+    pub max_level: Level,
+    records: Deque<Record, N>,
+    dropped: u8,
+}
+
+impl<const N: usize> BusLogger<N> {
+    pub fn new(collector: CanId, max_level: Level) -> Self {
+        BusLogger {
+            collector,
+            max_level,
+            records: Deque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Records waiting to be sent
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Gives the oldest record to the protocol if it has nothing else to send, so the logs
+    /// never delay the other messages. To be called from the main loop, returns true if a
+    /// record was given.
+    pub fn flush<const SEND: usize, const RECEIVE: usize, const CONTROL: usize>(
+        &mut self,
+        protocol: &mut Protocol<SEND, RECEIVE, CONTROL>,
+    ) -> Result<bool, ProtocolError> {
+        if !protocol.send_buff.is_empty() {
+            return Ok(false);
+        }
+        let record = match self.records.front() {
+            Some(record) => record,
+            None => return Ok(false),
+        };
+        let data = LogRecord {
+            level: record.level,
+            dropped: record.dropped,
+            tag: record.tag,
+            text: &record.text,
+        }
+        .encode();
+        let id = protocol.next_message_id(self.collector)?;
+        let message = Message::new(id, self.collector, protocol.host_id, &data)?;
+        protocol.add_message_to_send_buff(message)?;
+        self.records.pop_front();
+        Ok(true)
+    }
+}
+
+impl<const N: usize> Log for BusLogger<N> {
+    fn enabled(&self, level: Level) -> bool {
+        level <= self.max_level
+    }
+
+    fn log(&mut self, level: Level, tag: &'static str, args: fmt::Arguments) {
+        if self.records.is_full() {
+            self.dropped = self.dropped.saturating_add(1);
+            return;
+        }
+        let mut text = String::new();
+        let _ = fmt::write(&mut Truncating(&mut text), args);
+        let record = Record {
+            level,
+            tag,
+            text,
+            dropped: self.dropped,
+        };
+        self.dropped = 0;
+        // Can't fail, checked above
+        let _ = self.records.push_back(record);
+    }
+}
+
+/// Prints the records on a `fmt::Write`, one per line: `INFO  tag: text`
+pub struct WriteLogger<W: fmt::Write> {
+    pub writer: W,
+    pub max_level: Level,
+}
+
+impl<W: fmt::Write> WriteLogger<W> {
+    pub fn new(writer: W, max_level: Level) -> Self {
+        WriteLogger { writer, max_level }
+    }
+}
+
+impl<W: fmt::Write> Log for WriteLogger<W> {
+    fn enabled(&self, level: Level) -> bool {
+        level <= self.max_level
+    }
+
+    fn log(&mut self, level: Level, tag: &'static str, args: fmt::Arguments) {
+        // Nowhere to report it
+        let _ = writeln!(self.writer, "{:<5} {}: {}", level, tag, args);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::log::{
+    BusLogger, Level, Log, LogError, LogRecord, WriteLogger, LOG_COMMAND, MAX_TAG_LEN, MAX_TEXT_LEN,
+};
+use crate::protocol::message::Message;
+use crate::protocol::protocol::Protocol;
+use crate::protocol::{CanId, MessageId, MAX_MESSAGE_LEN};
+use crate::{debug, error, info, warn};
+use heapless::String;
+
+fn id(v: usize) -> CanId {
+    CanId::new(v).unwrap()
+}
+
+/// One frame from each side in turn, until both are idle
+fn exchange(node: &mut Protocol, collector: &mut Protocol) {
+    loop {
+        let sent = node.get_next_packet_to_send().unwrap();
+        if let Some(frame) = sent {
+            collector.process_raw_packet(frame).unwrap();
+        }
+        let answer = collector.get_next_packet_to_send().unwrap();
+        if let Some(frame) = answer {
+            node.process_raw_packet(frame).unwrap();
+        }
+        if sent.is_none() && answer.is_none() {
+            return;
+        }
+    }
+}
+
+#[test]
+fn record_layout() {
+    let record = LogRecord {
+        level: Level::Warn,
+        dropped: 3,
+        tag: "ab",
+        text: "xyz",
+    };
+    let data = record.encode();
+    assert_eq!(
+        &data[..],
+        &[LOG_COMMAND, 2, 3, 2, 3, b'a', b'b', b'x', b'y', b'z']
+    );
+    // The padding of the last packet is ignored
+    let mut padded = data.clone();
+    padded.extend_from_slice(&[0, 0, 0]).unwrap();
+    assert_eq!(LogRecord::decode(&padded), Ok(record));
+}
+
+#[test]
+fn invalid_records_are_refused() {
+    assert_eq!(LogRecord::decode(&[1, 2]), Err(LogError::NotALog));
+    assert_eq!(
+        LogRecord::decode(&[LOG_COMMAND, 3, 0]),
+        Err(LogError::TooShort)
+    );
+    assert_eq!(
+        LogRecord::decode(&[LOG_COMMAND, 9, 0, 0, 0]),
+        Err(LogError::InvalidLevel(9))
+    );
+    assert_eq!(
+        LogRecord::decode(&[LOG_COMMAND, 3, 0, 1, 2, b'a', b'b']),
+        Err(LogError::TooShort)
+    );
+    assert_eq!(
+        LogRecord::decode(&[LOG_COMMAND, 3, 0, 0, 1, 0xFF]),
+        Err(LogError::InvalidUtf8)
+    );
+}
+
+#[test]
+fn long_tag_and_text_are_truncated_on_char_boundaries() {
+    let tag = "é".repeat(MAX_TAG_LEN);
+    assert_eq!(
+        LogRecord::decode(
+            &LogRecord {
+                level: Level::Info,
+                dropped: 0,
+                tag: "robot::firmware::motors::herkulex",
+                text: "",
+            }
+            .encode()
+        )
+        .unwrap()
+        .tag,
+        "motors::herkulex"
+    );
+    let text = "x".repeat(200);
+    let data = LogRecord {
+        level: Level::Info,
+        dropped: 0,
+        tag: &tag,
+        text: &text,
+    }
+    .encode();
+    assert_eq!(data.len(), MAX_MESSAGE_LEN);
+    let record = LogRecord::decode(&data).unwrap();
+    assert_eq!(record.tag, "é".repeat(MAX_TAG_LEN / 2));
+    assert_eq!(record.text.len(), MAX_TEXT_LEN - MAX_TAG_LEN);
+}
+
+#[test]
+fn levels_above_the_maximum_are_ignored() {
+    let mut logger: BusLogger<4> = BusLogger::new(id(1), Level::Info);
+    info!(logger, "kept {}", 1);
+    debug!(logger, "ignored");
+    error!(&mut logger, "kept too");
+    assert_eq!(logger.len(), 2);
+}
+
+#[test]
+fn records_are_sent_when_the_bus_is_free() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    let mut logger: BusLogger<4> = BusLogger::new(id(1), Level::Trace);
+    warn!(logger, "temperature {}°C", 71);
+    info!(logger, "second");
+
+    assert_eq!(logger.flush(&mut protocol), Ok(true));
+    // The first record is still in the send buffer
+    assert_eq!(logger.flush(&mut protocol), Ok(false));
+    let message = &protocol.send_buff[0];
+    assert_eq!(usize::from(message.id), 0);
+    assert_eq!(message.id_dest(), id(1));
+    assert_eq!(logger.len(), 1);
+}
+
+#[test]
+fn records_dont_reuse_the_id_of_the_last_message() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    let mut collector = Protocol::new(id(1)).unwrap();
+    let mut logger: BusLogger<4> = BusLogger::new(id(1), Level::Trace);
+    let message = Message::new(MessageId::new(0).unwrap(), id(1), id(2), &[1]).unwrap();
+    protocol.add_message_to_send_buff(message).unwrap();
+    exchange(&mut protocol, &mut collector);
+
+    info!(logger, "after the message");
+    assert_eq!(logger.flush(&mut protocol), Ok(true));
+    assert_eq!(usize::from(protocol.send_buff[0].id), 1);
+    exchange(&mut protocol, &mut collector);
+    assert_eq!(collector.received.len(), 2);
+}
+
+#[test]
+fn full_logger_counts_the_dropped_records() {
+    let mut logger: BusLogger<1> = BusLogger::new(id(1), Level::Trace);
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    let mut collector = Protocol::new(id(1)).unwrap();
+    info!(logger, "first");
+    info!(logger, "lost");
+    info!(logger, "lost");
+    logger.flush(&mut protocol).unwrap();
+    exchange(&mut protocol, &mut collector);
+    info!(logger, "after");
+    logger.flush(&mut protocol).unwrap();
+    exchange(&mut protocol, &mut collector);
+
+    assert_eq!(collector.received.len(), 2);
+    let message = collector.received.iter().nth(1).unwrap();
+    let record = LogRecord::decode(message).unwrap();
+    assert_eq!(record.dropped, 2);
+    assert_eq!(record.text, "after");
+    assert_eq!(record.tag, "log::tests::log_tests");
+}
+
+#[test]
+fn write_logger_prints_one_line_per_record() {
+    let mut logger = WriteLogger::new(String::<64>::new(), Level::Warn);
+    warn!(logger, "hot");
+    info!(logger, "ignored");
+    assert!(logger.enabled(Level::Error));
+    assert_eq!(
+        logger.writer.as_str(),
+        "WARN  network_protocol::log::tests::log_tests: hot\n"
+    );
+}
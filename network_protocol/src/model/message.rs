@@ -50,7 +50,7 @@ impl Message {
         }
         let max_seq_num = self.data.len()/BYTES_LEFT_IN_PACKAGE as usize;
         // we can create an exact number of messages
-        let seq_numbers = 0..(max_seq_num as usize);  //(0..self.data.len()/BYTES_LEFT_IN_PACKAGE as usize).rev();
+        let seq_numbers = 0..max_seq_num;  //(0..self.data.len()/BYTES_LEFT_IN_PACKAGE as usize).rev();
         for i in seq_numbers {
             // if the packet has already been received by the other STM dont' resend it
             if self.ack_received[i] { continue }
@@ -65,7 +65,7 @@ impl Message {
 
             let mut data:[u8; BYTES_LEFT_IN_PACKAGE as usize] = [0; BYTES_LEFT_IN_PACKAGE as usize];
             // on decale chaque fois de la taille du message (6 bytes)
-            data[..BYTES_LEFT_IN_PACKAGE as usize].clone_from_slice(&self.data[(i*BYTES_LEFT_IN_PACKAGE as usize)..((BYTES_LEFT_IN_PACKAGE as usize + i*BYTES_LEFT_IN_PACKAGE as usize) as usize)]);
+            data[..BYTES_LEFT_IN_PACKAGE as usize].clone_from_slice(&self.data[(i*BYTES_LEFT_IN_PACKAGE as usize)..(BYTES_LEFT_IN_PACKAGE as usize + i*BYTES_LEFT_IN_PACKAGE as usize)]);
             Packet::new(header,data).send(tx)?;
         }
         // this means it was sent successfully but we don't know if it was received we need to check the acks
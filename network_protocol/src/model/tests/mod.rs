@@ -2,4 +2,5 @@
 #[cfg(test)]
 mod packet_tests;
 mod header_tests;
+#[allow(deprecated, unused_must_use)]
 mod message_tests;
\ No newline at end of file
@@ -0,0 +1,147 @@
+//! Fixed size encoding of the types deriving `ProtocolMessage`.
+//! The fields are written one after the other in little endian, the bit fields are packed
+//! most significant bits first like `Header`, an enum starts with a tag byte and all its
+//! variants take the size of the largest one.
+
+pub use crate::protocol::errors::CodecError;
+use crate::protocol::MAX_MESSAGE_LEN;
+use heapless::Vec;
+
+pub use protocol_derive::ProtocolMessage;
+
+/// A value with a fixed size on the wire
+pub trait WireFormat: Sized {
+    const SIZE: usize;
+
+    /// `buf` is at least `SIZE` bytes long
+    fn write(&self, buf: &mut [u8]) -> Result<(), CodecError>;
+
+    /// `buf` is at least `SIZE` bytes long
+    fn read(buf: &[u8]) -> Result<Self, CodecError>;
+}
+
+/// A value which can be a `#[protocol(bits = ..)]` field
+pub trait BitField: Sized {
+    fn to_bits(&self) -> u32;
+
+    fn from_bits(bits: u32) -> Result<Self, CodecError>;
+}
+
+/// A message for the `Dispatcher`: its command id followed by its fields
+pub trait ProtocolMessage: WireFormat {
+    const COMMAND: u8;
+
+    fn encode(&self) -> Result<Vec<u8, MAX_MESSAGE_LEN>, CodecError> {
+        let mut data = Vec::new();
+        data.resize(1 + Self::SIZE, 0)
+            .map_err(|_| CodecError::BufferTooSmall)?;
+        data[0] = Self::COMMAND;
+        self.write(&mut data[1..])?;
+        Ok(data)
+    }
+
+    /// Reads the payload following the command id, as given to a handler.
+    /// The padding after the fields is ignored.
+    fn decode(payload: &[u8]) -> Result<Self, CodecError> {
+        if payload.len() < Self::SIZE {
+            return Err(CodecError::TooShort);
+        }
+        Self::read(payload)
+    }
+
+    /// Reads a whole message, starting with its command id
+    fn decode_message(data: &[u8]) -> Result<Self, CodecError> {
+        match data.split_first() {
+            Some((command, payload)) if *command == Self::COMMAND => Self::decode(payload),
+            Some((command, _)) => Err(CodecError::WrongCommand(*command)),
+            None => Err(CodecError::TooShort),
+        }
+    }
+}
+
+macro_rules! wire_number {
+    ($($ty:ty),*) => {
+        $(
+            impl WireFormat for $ty {
+                const SIZE: usize = core::mem::size_of::<$ty>();
+
+                fn write(&self, buf: &mut [u8]) -> Result<(), CodecError> {
+                    buf[..Self::SIZE].copy_from_slice(&self.to_le_bytes());
+                    Ok(())
+                }
+
+                fn read(buf: &[u8]) -> Result<Self, CodecError> {
+                    let mut bytes = [0u8; core::mem::size_of::<$ty>()];
+                    bytes.copy_from_slice(&buf[..Self::SIZE]);
+                    Ok(<$ty>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+wire_number!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+macro_rules! bit_field_number {
+    ($($ty:ty),*) => {
+        $(
+            impl BitField for $ty {
+                fn to_bits(&self) -> u32 {
+                    u32::from(*self)
+                }
+
+                fn from_bits(bits: u32) -> Result<Self, CodecError> {
+                    <$ty>::try_from(bits).map_err(|_| CodecError::ValueTooLarge)
+                }
+            }
+        )*
+    };
+}
+
+bit_field_number!(u8, u16, u32);
+
+impl WireFormat for bool {
+    const SIZE: usize = 1;
+
+    fn write(&self, buf: &mut [u8]) -> Result<(), CodecError> {
+        buf[0] = u8::from(*self);
+        Ok(())
+    }
+
+    fn read(buf: &[u8]) -> Result<Self, CodecError> {
+        match buf[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(CodecError::InvalidTag(other)),
+        }
+    }
+}
+
+impl BitField for bool {
+    fn to_bits(&self) -> u32 {
+        u32::from(*self)
+    }
+
+    fn from_bits(bits: u32) -> Result<Self, CodecError> {
+        Ok(bits != 0)
+    }
+}
+
+impl<T: WireFormat + Copy + Default, const N: usize> WireFormat for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+
+    fn write(&self, buf: &mut [u8]) -> Result<(), CodecError> {
+        for (value, chunk) in self.iter().zip(buf.chunks_mut(T::SIZE.max(1))) {
+            value.write(chunk)?;
+        }
+        Ok(())
+    }
+
+    fn read(buf: &[u8]) -> Result<Self, CodecError> {
+        let mut values = [T::default(); N];
+        for (value, chunk) in values.iter_mut().zip(buf.chunks(T::SIZE.max(1))) {
+            *value = T::read(chunk)?;
+        }
+        Ok(values)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::protocol::Protocol;
+use crate::protocol::{
+    CanId, DEFAULT_ACK_TIMEOUT, DEFAULT_MAX_RETRIES, MAX_IN_FLIGHT_PACKETS, MAX_QUEUED_MESSAGES,
+};
+
+/// Settings of a `Protocol` which can change at runtime, the capacities are const generics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolConfig {
+    /// Packets sent to one destination and waiting for their ACK
+    pub max_in_flight_packets: usize,
+    /// Messages waiting for one destination
+    pub max_queued_messages: usize,
+    /// Time to wait for an ACK before sending a packet again, in microseconds
+    pub ack_timeout: u32,
+    /// Retransmissions of a packet before its message is given up, `None` retries forever
+    pub max_retries: Option<u8>,
+    /// Deadline given to the messages added without one, in microseconds from their addition
+    pub default_lifetime: Option<u32>,
+}
+
+impl Default for ProtocolConfig {
+    /// Retransmits after `DEFAULT_ACK_TIMEOUT` and gives a message up after
+    /// `DEFAULT_MAX_RETRIES` retransmissions of one of its packets. The time only moves with
+    /// `Protocol::poll`, a node which never calls it never retransmits.
+    fn default() -> Self {
+        ProtocolConfig {
+            max_in_flight_packets: MAX_IN_FLIGHT_PACKETS,
+            max_queued_messages: MAX_QUEUED_MESSAGES,
+            ack_timeout: DEFAULT_ACK_TIMEOUT,
+            max_retries: Some(DEFAULT_MAX_RETRIES),
+            default_lifetime: None,
+        }
+    }
+}
+
+/// Builds a `Protocol`, the capacities are chosen with the type:
+/// `Protocol::<4, 2, 8>::builder(host_id)` for a node with little RAM
+pub struct ProtocolBuilder<const SEND: usize, const RECEIVE: usize, const CONTROL: usize> {
+    host_id: CanId,
+    config: ProtocolConfig,
+}
+
+impl<const SEND: usize, const RECEIVE: usize, const CONTROL: usize>
+    ProtocolBuilder<SEND, RECEIVE, CONTROL>
+{
+    pub fn new(host_id: CanId) -> Self {
+        ProtocolBuilder {
+            host_id,
+            config: ProtocolConfig::default(),
+        }
+    }
+
+    pub fn config(mut self, config: ProtocolConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn max_in_flight_packets(mut self, packets: usize) -> Self {
+        self.config.max_in_flight_packets = packets;
+        self
+    }
+
+    pub fn max_queued_messages(mut self, messages: usize) -> Self {
+        self.config.max_queued_messages = messages;
+        self
+    }
+
+    pub fn ack_timeout(mut self, timeout: u32) -> Self {
+        self.config.ack_timeout = timeout;
+        self
+    }
+
+    pub fn max_retries(mut self, retries: u8) -> Self {
+        self.config.max_retries = Some(retries);
+        self
+    }
+
+    /// Never gives a message up, it stays until its ACK or its deadline
+    pub fn retry_forever(mut self) -> Self {
+        self.config.max_retries = None;
+        self
+    }
+
+    pub fn default_lifetime(mut self, lifetime: u32) -> Self {
+        self.config.default_lifetime = Some(lifetime);
+        self
+    }
+
+    pub fn build(self) -> Result<Protocol<SEND, RECEIVE, CONTROL>, ProtocolError> {
+        #[allow(clippy::let_unit_value)]
+        let () = Protocol::<SEND, RECEIVE, CONTROL>::CAPACITIES_CHECK;
+        if self.config.max_in_flight_packets == 0
+            || self.config.max_queued_messages == 0
+            || self.config.max_queued_messages > SEND
+        {
+            return Err(ProtocolError::InvalidConfig);
+        }
+        Protocol::with_config(self.host_id, self.config)
+    }
+}
@@ -0,0 +1,136 @@
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::packet::Packet;
+use crate::protocol::PACKET_DATA_SIZE;
+
+/// Identifies one boot of a node, it must be different after each reboot
+/// (for example a counter kept in a backup register)
+pub type Epoch = u16;
+
+const CONTROL_ACK: u8 = 0;
+const CONTROL_EPOCH_ANNOUNCE: u8 = 1;
+const CONTROL_EPOCH_ACK: u8 = 2;
+const CONTROL_NACK: u8 = 3;
+const CONTROL_VERSION: u8 = 4;
+
+/// Why a receiver refused a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NackReason {
+    /// No room left to store the message, it can be sent again later
+    BufferFull,
+    UnknownCommand,
+    MalformedPayload,
+    /// The CRC of the reassembled message didn't match, it has to be sent again from the start
+    Corrupted,
+}
+
+impl NackReason {
+    /// A permanent rejection will happen again, the sender must not retry
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            NackReason::BufferFull | NackReason::Corrupted => false,
+            NackReason::UnknownCommand | NackReason::MalformedPayload => true,
+        }
+    }
+}
+
+impl From<NackReason> for u8 {
+    fn from(reason: NackReason) -> Self {
+        match reason {
+            NackReason::BufferFull => 1,
+            NackReason::UnknownCommand => 2,
+            NackReason::MalformedPayload => 3,
+            NackReason::Corrupted => 4,
+        }
+    }
+}
+
+impl TryFrom<u8> for NackReason {
+    type Error = ProtocolError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            1 => Ok(NackReason::BufferFull),
+            2 => Ok(NackReason::UnknownCommand),
+            3 => Ok(NackReason::MalformedPayload),
+            4 => Ok(NackReason::Corrupted),
+            _ => Err(ProtocolError::InvalidNackReason(code)),
+        }
+    }
+}
+
+/// Frames with the ACK bit set. The first payload byte tells what kind of control frame it is,
+/// a plain ACK has an empty payload so it stays compatible with older nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlFrame {
+    Ack,
+    /// Sent at boot, peers must forget everything they know about the previous session
+    EpochAnnounce(Epoch),
+    /// Answer to an `EpochAnnounce`, echoes the announced epoch
+    EpochAck(Epoch),
+    /// The message with the id of the header was refused
+    Nack(NackReason),
+    /// The highest protocol version the sender speaks, legacy nodes never send it
+    Version(u8),
+}
+
+impl From<ControlFrame> for [u8; PACKET_DATA_SIZE] {
+    fn from(control: ControlFrame) -> Self {
+        let mut payload = [0u8; PACKET_DATA_SIZE];
+        match control {
+            ControlFrame::Ack => payload[0] = CONTROL_ACK,
+            ControlFrame::EpochAnnounce(epoch) => {
+                payload[0] = CONTROL_EPOCH_ANNOUNCE;
+                payload[1..3].copy_from_slice(&epoch.to_le_bytes());
+            }
+            ControlFrame::EpochAck(epoch) => {
+                payload[0] = CONTROL_EPOCH_ACK;
+                payload[1..3].copy_from_slice(&epoch.to_le_bytes());
+            }
+            ControlFrame::Nack(reason) => {
+                payload[0] = CONTROL_NACK;
+                payload[1] = reason.into();
+            }
+            ControlFrame::Version(version) => {
+                payload[0] = CONTROL_VERSION;
+                payload[1] = version;
+            }
+        }
+        payload
+    }
+}
+
+impl TryFrom<&[u8; PACKET_DATA_SIZE]> for ControlFrame {
+    type Error = ProtocolError;
+
+    fn try_from(payload: &[u8; PACKET_DATA_SIZE]) -> Result<Self, Self::Error> {
+        let epoch = Epoch::from_le_bytes([payload[1], payload[2]]);
+        match payload[0] {
+            CONTROL_ACK if payload[1..].iter().any(|b| *b != 0) => {
+                Err(ProtocolError::ACKCanNotContainData)
+            }
+            CONTROL_ACK => Ok(ControlFrame::Ack),
+            CONTROL_EPOCH_ANNOUNCE => Ok(ControlFrame::EpochAnnounce(epoch)),
+            CONTROL_EPOCH_ACK => Ok(ControlFrame::EpochAck(epoch)),
+            CONTROL_NACK => Ok(ControlFrame::Nack(NackReason::try_from(payload[1])?)),
+            CONTROL_VERSION => Ok(ControlFrame::Version(payload[1])),
+            kind => Err(ProtocolError::InvalidControlFrame(kind)),
+        }
+    }
+}
+
+/// The control frames of extended frames have the same payload, followed by zeros
+impl TryFrom<&Packet> for ControlFrame {
+    type Error = ProtocolError;
+
+    fn try_from(packet: &Packet) -> Result<Self, Self::Error> {
+        let payload = packet
+            .payload
+            .get(..PACKET_DATA_SIZE)
+            .and_then(|payload| <&[u8; PACKET_DATA_SIZE]>::try_from(payload).ok())
+            .ok_or(ProtocolError::MessageTooShort(packet.payload.len()))?;
+        if packet.payload[PACKET_DATA_SIZE..].iter().any(|b| *b != 0) {
+            return Err(ProtocolError::ACKCanNotContainData);
+        }
+        ControlFrame::try_from(payload)
+    }
+}
@@ -0,0 +1,27 @@
+/// Size of the CRC appended at the end of a message
+pub const CRC_SIZE: usize = 2;
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// True if the last `CRC_SIZE` bytes are the CRC of the others
+pub fn check_crc(data: &[u8]) -> bool {
+    if data.len() < CRC_SIZE {
+        return false;
+    }
+    let (content, crc) = data.split_at(data.len() - CRC_SIZE);
+    crc16(content).to_be_bytes() == crc
+}
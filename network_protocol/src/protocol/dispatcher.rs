@@ -0,0 +1,152 @@
+use crate::protocol::control::NackReason;
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::message::Message;
+use crate::protocol::protocol::Protocol;
+use crate::protocol::{CanId, MAX_MESSAGE_LEN};
+use heapless::Vec;
+
+/// Set in the command id of the replies, a reply is never answered so two nodes can't keep
+/// answering each other. The commands go from 0 to `REPLY_FLAG - 1`.
+pub const REPLY_FLAG: u8 = 0x80;
+
+/// Command id of the standard error reply: `[ERROR_REPLY, command, reason]` where `reason` is
+/// the code of a `NackReason`
+pub const ERROR_REPLY: u8 = u8::MAX;
+
+/// Handles one command: gets the node state, the source of the message and the payload
+/// following the command id, which ends with the zeros padding the last packet.
+/// What it writes in the reply is sent back to the source.
+pub type Handler<C> = fn(&mut C, CanId, &[u8], &mut Reply) -> Result<(), NackReason>;
+
+/// Answer to a command, it starts with the id of the command it answers and `REPLY_FLAG`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    data: Vec<u8, MAX_MESSAGE_LEN>,
+}
+
+impl Reply {
+    fn new(command: u8) -> Self {
+        let mut data = Vec::new();
+        // Can't fail, the vector is empty
+        data.push(command | REPLY_FLAG).unwrap();
+        Reply { data }
+    }
+
+    fn error(command: u8, reason: NackReason) -> Self {
+        let mut reply = Reply::new(ERROR_REPLY);
+        // Can't fail, there is room for much more
+        reply
+            .data
+            .extend_from_slice(&[command, reason.into()])
+            .unwrap();
+        reply
+    }
+
+    pub fn extend_from_slice(&mut self, data: &[u8]) -> Result<(), ProtocolError> {
+        self.data
+            .extend_from_slice(data)
+            .map_err(|_| ProtocolError::MessageTooLong)
+    }
+
+    /// True if the handler wrote nothing, no reply is sent then
+    pub fn is_empty(&self) -> bool {
+        self.data.len() == 1
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Routes the received messages to the handler of their command id, the first byte.
+/// `N` handlers can be registered.
+pub struct Dispatcher<C, const N: usize> {
+    handlers: Vec<(u8, Handler<C>), N>,
+}
+
+impl<C, const N: usize> Default for Dispatcher<C, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, const N: usize> Dispatcher<C, N> {
+    pub fn new() -> Self {
+        Dispatcher {
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Replaces the handler already registered for `command`, if any. The replies can be
+    /// handled too by registering their id, what their handler writes is dropped.
+    pub fn register(&mut self, command: u8, handler: Handler<C>) -> Result<(), ProtocolError> {
+        match self.handlers.iter_mut().find(|(c, _)| *c == command) {
+            Some((_, registered)) => *registered = handler,
+            None => self
+                .handlers
+                .push((command, handler))
+                .map_err(|_| ProtocolError::TooManyHandlers)?,
+        }
+        Ok(())
+    }
+
+    /// Handles every message waiting in `protocol.received` and queues the replies.
+    /// The datagrams and the replies are handled too but never answered.
+    /// Stops at the first request whose source can't be sent a reply yet, it is handled
+    /// by a later call. Returns the number of messages handled.
+    pub fn dispatch<const SEND: usize, const RECEIVE: usize, const CONTROL: usize>(
+        &mut self,
+        protocol: &mut Protocol<SEND, RECEIVE, CONTROL>,
+        context: &mut C,
+    ) -> Result<usize, ProtocolError> {
+        let mut handled = 0;
+        while let Some(message) = protocol.received.front_message() {
+            let src = message.src;
+            let answered = !message.is_datagram()
+                && message
+                    .data
+                    .first()
+                    .is_none_or(|command| command & REPLY_FLAG == 0);
+            if answered && (!protocol.can_send_to(src) || protocol.next_message_id(src).is_err()) {
+                break;
+            }
+            let reply = self.handle(context, src, message.data);
+            let queued = if answered && !reply.is_empty() {
+                Self::queue_reply(protocol, src, &reply)
+            } else {
+                Ok(())
+            };
+            // The handler ran, the request is never handled twice
+            protocol.received.pop_front();
+            handled += 1;
+            queued?;
+        }
+        Ok(handled)
+    }
+
+    fn queue_reply<const SEND: usize, const RECEIVE: usize, const CONTROL: usize>(
+        protocol: &mut Protocol<SEND, RECEIVE, CONTROL>,
+        dest: CanId,
+        reply: &Reply,
+    ) -> Result<(), ProtocolError> {
+        let id = protocol.next_message_id(dest)?;
+        let message = Message::new(id, dest, protocol.host_id, reply.as_slice())?;
+        protocol.add_message_to_send_buff(message)
+    }
+
+    fn handle(&self, context: &mut C, src: CanId, data: &[u8]) -> Reply {
+        let (command, payload) = match data.split_first() {
+            Some((command, payload)) => (*command, payload),
+            None => return Reply::error(ERROR_REPLY, NackReason::MalformedPayload),
+        };
+        let handler = match self.handlers.iter().find(|(c, _)| *c == command) {
+            Some((_, handler)) => handler,
+            None => return Reply::error(command, NackReason::UnknownCommand),
+        };
+        let mut reply = Reply::new(command);
+        match handler(context, src, payload, &mut reply) {
+            Ok(()) => reply,
+            Err(reason) => Reply::error(command, reason),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::protocol::control::NackReason;
+use crate::protocol::{CanId, MessageId};
+
+#[derive(Debug, PartialEq)]
+pub enum SendError {
+    DidntReceiveACK,
+    SendFailed,
+    Rejected(NackReason),
+    /// Too many messages are already waiting for this destination
+    QueueFull,
+    /// The deadline of the message was reached before it was delivered
+    Expired,
+}
+
+/// A message which will never be delivered
+#[derive(Debug, PartialEq)]
+pub struct SendFailure {
+    pub id_dest: CanId,
+    pub id: MessageId,
+    pub error: SendError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProtocolError {
+    InvalidId(usize),
+    MessageTooShort(usize),
+    MessageTooLong,
+    ParametersTooLong,
+    SrcAndDestCanNotBeEqual,
+    ACKCanNotContainData,
+    InvalidControlFrame(u8),
+    InvalidNackReason(u8),
+    /// Only datagrams can be sent to a broadcast or group id
+    MulticastMustBeDatagram,
+    /// A broadcast or group id can't be the source of a frame
+    MulticastSource,
+    /// The version field of an extended header is not one this node speaks
+    UnsupportedVersion(u8),
+    /// The ids of the header or the payload of the packet don't fit in a standard frame
+    ExtendedFrameOnly,
+    /// No room left for the state of another peer, see `MAX_PEERS`
+    TooManyPeers,
+    /// A `ProtocolConfig` which can't work with the capacities of the `Protocol`
+    InvalidConfig,
+    /// No room left to register a handler in the `Dispatcher`
+    TooManyHandlers,
+    /// No room left in `acks_to_send`, the answer is dropped
+    ControlQueueFull,
+    SendFailed(SendError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecError {
+    /// The payload is shorter than the fields
+    TooShort,
+    BufferTooSmall,
+    /// A value doesn't fit in its bit field
+    ValueTooLarge,
+    /// Not the tag of a variant of the enum, or not a bool
+    InvalidTag(u8),
+    /// The message has another command id
+    WrongCommand(u8),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RoutingError {
+    InvalidFrame(ProtocolError),
+    /// The gateway has no route to this node
+    NoRoute(CanId),
+    /// The source of the frame isn't in the routing table
+    UnknownSource(CanId),
+    /// The source isn't behind the interface the frame comes from, the frame made a loop
+    WrongInterface {
+        src: CanId,
+        interface: u8,
+    },
+    UnknownInterface(u8),
+    /// Multicast ids and the gateway itself have no route
+    NotRoutable(CanId),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RecordingError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    Corrupted,
+    TooManyNodes,
+    WriteFailed,
+    Sink(ProtocolError),
+}
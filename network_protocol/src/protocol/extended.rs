@@ -0,0 +1,123 @@
+//! Extended addressing: the header moves from the first 2 data bytes of a standard frame
+//! to the 29-bit id of an extended frame, with 8-bit node ids and 5-bit message ids, and the
+//! data packets carry all 8 data bytes. Legacy nodes never see it, a node only uses it with
+//! the peers which announced they speak it (see `ControlFrame::Version`) and with the node ids
+//! above `MAX_CAN_ID`.
+//!
+//! 29-bit id layout: version (2) | dest (8) | src (8) | message id (5) | seq (5) | ack (1)
+//!
+//! The sequence field is as wide as the message id but `MAX_MESSAGE_LEN` never needs more
+//! than 16 packets, a sequence number above `MAX_SEQ_ID` is rejected.
+
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::header::Header;
+use crate::protocol::packet::Packet;
+use crate::protocol::{CanId, MessageId, SeqId, CAN_PACKET_SIZE, PACKET_DATA_SIZE};
+use heapless::Vec;
+
+/// Only the 2 bytes header in the data of standard frames
+pub const PROTOCOL_VERSION_LEGACY: u8 = 0;
+/// Header in the id of extended frames
+pub const PROTOCOL_VERSION_EXTENDED: u8 = 1;
+/// The highest version this node speaks
+pub const PROTOCOL_VERSION: u8 = PROTOCOL_VERSION_EXTENDED;
+
+pub const MAX_EXTENDED_ID: u32 = 2u32.pow(29) - 1;
+
+const VERSION_MASK: u32 = 0b11;
+const VERSION_OFFSET: u32 = 27;
+const NODE_ID_MASK: u32 = 0xFF;
+const DEST_OFFSET: u32 = 19;
+const SRC_OFFSET: u32 = 11;
+const COUNTER_MASK: u32 = 0b11111;
+const ID_MESSAGE_OFFSET: u32 = 6;
+const SEQ_NUMBER_OFFSET: u32 = 1;
+const IS_ACK_MASK: u32 = 0b1;
+
+impl From<&Header> for u32 {
+    fn from(header: &Header) -> Self {
+        (PROTOCOL_VERSION_EXTENDED as u32) << VERSION_OFFSET
+            | (usize::from(header.id_dest) as u32) << DEST_OFFSET
+            | (usize::from(header.id_src) as u32) << SRC_OFFSET
+            | (usize::from(header.id_message) as u32) << ID_MESSAGE_OFFSET
+            | (usize::from(header.seq_number) as u32) << SEQ_NUMBER_OFFSET
+            | header.is_ack as u32
+    }
+}
+
+/// Fails with `InvalidId` for a sequence number above `MAX_SEQ_ID`
+impl TryFrom<u32> for Header {
+    type Error = ProtocolError;
+
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        if id > MAX_EXTENDED_ID {
+            return Err(ProtocolError::InvalidId(id as usize));
+        }
+        let version = ((id >> VERSION_OFFSET) & VERSION_MASK) as u8;
+        if version != PROTOCOL_VERSION_EXTENDED {
+            return Err(ProtocolError::UnsupportedVersion(version));
+        }
+        Header::new(
+            CanId::new(((id >> DEST_OFFSET) & NODE_ID_MASK) as usize)?,
+            CanId::new(((id >> SRC_OFFSET) & NODE_ID_MASK) as usize)?,
+            id & IS_ACK_MASK == 1,
+            MessageId::new(((id >> ID_MESSAGE_OFFSET) & COUNTER_MASK) as usize)?,
+            SeqId::new(((id >> SEQ_NUMBER_OFFSET) & COUNTER_MASK) as usize)?,
+        )
+    }
+}
+
+/// A frame as seen by the CAN peripheral
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanFrame {
+    /// Legacy format, the header is in the first 2 data bytes
+    Standard([u8; CAN_PACKET_SIZE]),
+    /// The header is in the 29-bit id
+    Extended {
+        id: u32,
+        data: [u8; CAN_PACKET_SIZE],
+    },
+}
+
+impl CanFrame {
+    pub fn data(&self) -> &[u8; CAN_PACKET_SIZE] {
+        match self {
+            CanFrame::Standard(data) | CanFrame::Extended { data, .. } => data,
+        }
+    }
+
+    /// A shorter payload is followed by zeros. The receiver takes the 8 bytes of a data
+    /// packet so every packet of a message has to be sent in the same kind of frame, only
+    /// control frames keep their 6 bytes payload.
+    pub fn extended(packet: &Packet) -> CanFrame {
+        let mut data = [0u8; CAN_PACKET_SIZE];
+        data[..packet.payload.len()].copy_from_slice(&packet.payload);
+        CanFrame::Extended {
+            id: (&packet.header).into(),
+            data,
+        }
+    }
+}
+
+impl TryFrom<&CanFrame> for Packet {
+    type Error = ProtocolError;
+
+    fn try_from(frame: &CanFrame) -> Result<Self, Self::Error> {
+        match frame {
+            CanFrame::Standard(raw) => Packet::try_from(raw),
+            CanFrame::Extended { id, data } => {
+                let header = Header::try_from(*id)?;
+                let len = if header.is_ack {
+                    PACKET_DATA_SIZE
+                } else {
+                    CAN_PACKET_SIZE
+                };
+                Ok(Packet {
+                    header,
+                    // Can't fail, the payload is at most a whole frame
+                    payload: Vec::from_slice(&data[..len]).unwrap(),
+                })
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::protocol::errors::ProtocolError;
-use crate::protocol::{CanId, MessageId, SeqId};
+use crate::protocol::{CanId, MessageId, SeqId, DATAGRAM_MESSAGE_ID, MAX_CAN_ID, MAX_MES_ID};
 
 const DEST_MASK: u8 = 0b11110000;
 const DEST_OFFSET: usize = 4;
@@ -33,6 +33,13 @@ impl Header {
         if id_dest == id_src {
             return Err(ProtocolError::SrcAndDestCanNotBeEqual);
         }
+        if id_src.is_multicast() {
+            return Err(ProtocolError::MulticastSource);
+        }
+        // Nobody answers a multicast frame
+        if id_dest.is_multicast() && (is_ack || usize::from(id_message) != DATAGRAM_MESSAGE_ID) {
+            return Err(ProtocolError::MulticastMustBeDatagram);
+        }
 
         Ok(Header {
             id_dest,
@@ -42,6 +49,14 @@ impl Header {
             seq_number,
         })
     }
+
+    /// True if the ids fit in the 2 bytes header of a standard frame, the others only go in
+    /// extended frames
+    pub fn fits_standard(&self) -> bool {
+        usize::from(self.id_dest) <= MAX_CAN_ID
+            && usize::from(self.id_src) <= MAX_CAN_ID
+            && usize::from(self.id_message) <= MAX_MES_ID
+    }
 }
 
 impl TryFrom<&[u8; 8]> for Header {
@@ -72,14 +87,23 @@ impl TryFrom<&[u8; 8]> for Header {
 impl TryInto<[u8; 2]> for &Header {
     type Error = ProtocolError;
 
+    /// Fails with `InvalidId` if an id only fits in an extended frame
     fn try_into(self) -> Result<[u8; 2], Self::Error> {
+        if !self.fits_standard() {
+            let id = [self.id_dest, self.id_src]
+                .into_iter()
+                .map(usize::from)
+                .find(|id| *id > MAX_CAN_ID)
+                .unwrap_or(usize::from(self.id_message));
+            return Err(ProtocolError::InvalidId(id));
+        }
         let mut raw = [0u8, 0u8];
 
-        raw[0] |= (self.id_dest.v << DEST_OFFSET) as u8;
-        raw[0] |= (self.id_src.v << SRC_OFFSET) as u8;
-        raw[1] |= (self.id_message.v << ID_MESSAGE_OFFSET) as u8;
-        raw[1] |= (self.seq_number.v << SEQ_NUMBER_OFFSET) as u8;
-        raw[1] |= (if self.is_ack { 1 } else { 0 } << IS_ACK_OFFSET) as u8;
+        raw[0] |= self.id_dest.v << DEST_OFFSET;
+        raw[0] |= self.id_src.v << SRC_OFFSET;
+        raw[1] |= self.id_message.v << ID_MESSAGE_OFFSET;
+        raw[1] |= self.seq_number.v << SEQ_NUMBER_OFFSET;
+        raw[1] |= u8::from(self.is_ack) << IS_ACK_OFFSET;
 
         Ok(raw)
     }
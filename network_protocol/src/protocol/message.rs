@@ -0,0 +1,288 @@
+use crate::protocol::crc::{crc16, CRC_SIZE};
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::header::Header;
+use crate::protocol::packet::Packet;
+use crate::protocol::{
+    CanId, MessageId, SeqId, CAN_PACKET_SIZE, DATAGRAM_MESSAGE_ID, MAX_MESSAGE_LEN, MAX_SEQ_NUMBER,
+    PACKET_DATA_SIZE,
+};
+use heapless::Vec;
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub id: MessageId,
+    id_dest: CanId,
+    id_src: CanId,
+    /// The data given by the user, then the padding and the CRC if any
+    data: Vec<u8, MAX_MESSAGE_LEN>,
+    /// Payload bytes of each packet, see `with_extended_packets`
+    packet_size: usize,
+    ack_received: Vec<bool, MAX_SEQ_NUMBER>,
+    /// Packets which went on the bus at least once
+    sent: Vec<bool, MAX_SEQ_NUMBER>,
+    replace_key: Option<u8>,
+    deadline: Option<u32>,
+    /// Length of the data given by the user
+    len: usize,
+    crc: bool,
+    /// When the last packet went on the bus, in microseconds
+    last_sent: u32,
+    /// Times the packet waiting for its ACK has been sent again
+    retries: u8,
+}
+
+impl Message {
+    /// A reliable message, every packet is sent until it is acknowledged.
+    /// The id `DATAGRAM_MESSAGE_ID` is reserved for datagrams.
+    pub fn new(
+        id: MessageId,
+        id_dest: CanId,
+        id_src: CanId,
+        data: &[u8],
+    ) -> Result<Message, ProtocolError> {
+        if usize::from(id) == DATAGRAM_MESSAGE_ID {
+            return Err(ProtocolError::InvalidId(DATAGRAM_MESSAGE_ID));
+        }
+        if id_dest.is_multicast() {
+            return Err(ProtocolError::MulticastMustBeDatagram);
+        }
+        Self::build(id, id_dest, id_src, data, PACKET_DATA_SIZE)
+    }
+
+    /// Fire and forget, sent once and never answered. Without ACKs the loss of a packet
+    /// can't be noticed so a datagram must fit in a single packet.
+    /// It is the only kind of message which can be sent to a broadcast or group id.
+    pub fn datagram(id_dest: CanId, id_src: CanId, data: &[u8]) -> Result<Message, ProtocolError> {
+        if data.len() > PACKET_DATA_SIZE {
+            return Err(ProtocolError::MessageTooLong);
+        }
+        Self::build(
+            MessageId::new(DATAGRAM_MESSAGE_ID)?,
+            id_dest,
+            id_src,
+            data,
+            PACKET_DATA_SIZE,
+        )
+    }
+
+    /// A message waiting for the same destination with the same key is replaced by this one,
+    /// unless it has already started to be sent
+    pub fn with_replace_key(mut self, key: u8) -> Self {
+        self.replace_key = Some(key);
+        self
+    }
+
+    /// The message is dropped by `Protocol::drop_expired_messages` once `deadline` is reached,
+    /// in microseconds on the same clock
+    pub fn with_deadline(mut self, deadline: u32) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Appends a CRC-16 over the whole message: the data is padded with zeros so the CRC
+    /// ends the last packet, where the receiver expects it
+    pub fn with_crc(self) -> Result<Self, ProtocolError> {
+        if self.crc {
+            return Ok(self);
+        }
+        let packet_size = self.packet_size;
+        self.rebuild(packet_size, true)
+    }
+
+    /// Splits the message in packets of 8 bytes, sent in extended frames. `Protocol` does it
+    /// for the peers which speak the extended version.
+    pub fn with_extended_packets(self) -> Result<Self, ProtocolError> {
+        let crc = self.crc;
+        self.rebuild(CAN_PACKET_SIZE, crc)
+    }
+
+    /// The same message, split again before it is sent
+    fn rebuild(self, packet_size: usize, crc: bool) -> Result<Self, ProtocolError> {
+        let mut data: Vec<u8, { MAX_MESSAGE_LEN + CAN_PACKET_SIZE }> = Vec::new();
+        // Can't fail, the data given by the user is at most MAX_MESSAGE_LEN long
+        data.extend_from_slice(&self.data[..self.len]).unwrap();
+        if crc {
+            let padded_len = (self.len + CRC_SIZE).div_ceil(packet_size) * packet_size;
+            data.resize(padded_len - CRC_SIZE, 0)
+                .map_err(|_| ProtocolError::MessageTooLong)?;
+            let crc = crc16(&data);
+            data.extend_from_slice(&crc.to_be_bytes())
+                .map_err(|_| ProtocolError::MessageTooLong)?;
+        }
+
+        let mut message = Self::build(self.id, self.id_dest, self.id_src, &data, packet_size)?;
+        message.replace_key = self.replace_key;
+        message.deadline = self.deadline;
+        message.len = self.len;
+        message.crc = crc;
+        Ok(message)
+    }
+
+    fn build(
+        id: MessageId,
+        id_dest: CanId,
+        id_src: CanId,
+        data: &[u8],
+        packet_size: usize,
+    ) -> Result<Message, ProtocolError> {
+        if id_dest == id_src {
+            Err(ProtocolError::SrcAndDestCanNotBeEqual)
+        } else if id_src.is_multicast() {
+            Err(ProtocolError::MulticastSource)
+        } else if data.len() > MAX_MESSAGE_LEN {
+            Err(ProtocolError::MessageTooLong)
+        } else {
+            // An empty message still needs one packet
+            let packet_count = data.len().div_ceil(packet_size).max(1);
+
+            let mut ack_received_vec = Vec::<bool, MAX_SEQ_NUMBER>::new();
+            for _ in 0..packet_count {
+                ack_received_vec.push(false).unwrap(); // cant crashas Vec always bigger than  sata_len
+            }
+
+            Ok(Message {
+                id,
+                id_dest,
+                id_src,
+                // Vérifié au dessus, ne peut pas paniquer
+                data: Vec::from_slice(data).unwrap(),
+                packet_size,
+                sent: ack_received_vec.clone(),
+                ack_received: ack_received_vec,
+                replace_key: None,
+                deadline: None,
+                len: data.len(),
+                crc: false,
+                last_sent: 0,
+                retries: 0,
+            })
+        }
+    }
+
+    pub fn mark_ack_as_received(&mut self, seq_num: SeqId) {
+        // An ACK for a sequence number this message doesn't have is ignored
+        let index = self
+            .ack_received
+            .len()
+            .checked_sub(usize::from(seq_num) + 1);
+        if let Some(index) = index {
+            if !self.ack_received[index] {
+                self.retries = 0;
+            }
+            self.ack_received[index] = true;
+        }
+    }
+
+    /// Returns the first packet which has not been acknowledged yet, sent or not
+    pub fn get_next_packet_to_send(&mut self) -> Result<Option<Packet>, ProtocolError> {
+        match self.ack_received.iter().position(|b| !(*b)) {
+            Some(index_packet) => self.packet_at(index_packet).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the first packet which has never been sent. The packets of a message are sent
+    /// one at a time: the receiver can only tell a message is complete if they arrive in order.
+    pub fn get_next_new_packet(&mut self) -> Result<Option<Packet>, ProtocolError> {
+        if self.packets_in_flight() > 0 {
+            return Ok(None);
+        }
+        match (0..self.sent.len()).find(|i| !self.sent[*i] && !self.ack_received[*i]) {
+            Some(index_packet) => self.packet_at(index_packet).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Number of packets sent and waiting for their ACK
+    pub fn packets_in_flight(&self) -> usize {
+        self.sent
+            .iter()
+            .zip(self.ack_received.iter())
+            .filter(|(sent, ack)| **sent && !**ack)
+            .count()
+    }
+
+    fn packet_at(&mut self, index_packet: usize) -> Result<Packet, ProtocolError> {
+        let header = Header::new(
+            self.id_dest,
+            self.id_src,
+            false,
+            self.id,
+            SeqId::new(self.ack_received.len() - index_packet - 1)?,
+        )?;
+
+        // The last packet is padded with zeros
+        let start = (index_packet * self.packet_size).min(self.data.len());
+        let end = (start + self.packet_size).min(self.data.len());
+        let mut payload = Vec::new();
+        // Can't fail, a packet is at most a whole frame
+        payload.extend_from_slice(&self.data[start..end]).unwrap();
+        payload.resize(self.packet_size, 0).unwrap();
+        self.sent[index_packet] = true;
+        if self.is_datagram() {
+            // Nobody will acknowledge it, it is done once sent
+            self.ack_received[index_packet] = true;
+        }
+
+        Ok(Packet { header, payload })
+    }
+
+    pub fn id_dest(&self) -> CanId {
+        self.id_dest
+    }
+
+    pub fn is_datagram(&self) -> bool {
+        usize::from(self.id) == DATAGRAM_MESSAGE_ID
+    }
+
+    pub fn replace_key(&self) -> Option<u8> {
+        self.replace_key
+    }
+
+    pub fn deadline(&self) -> Option<u32> {
+        self.deadline
+    }
+
+    pub fn last_sent(&self) -> u32 {
+        self.last_sent
+    }
+
+    pub fn retries(&self) -> u8 {
+        self.retries
+    }
+
+    pub(crate) fn record_send(&mut self, now: u32, retransmission: bool) {
+        self.last_sent = now;
+        if retransmission {
+            self.retries = self.retries.saturating_add(1);
+        }
+    }
+
+    pub fn packet_size(&self) -> usize {
+        self.packet_size
+    }
+
+    pub fn has_crc(&self) -> bool {
+        self.crc
+    }
+
+    /// True once a packet went on the bus, the receiver might have part of the message
+    pub fn has_started(&self) -> bool {
+        self.sent.iter().any(|b| *b)
+    }
+
+    /// Forget the ACKs received so far so the whole message is sent again
+    pub fn reset_acks(&mut self) {
+        for ack in self.ack_received.iter_mut() {
+            *ack = false;
+        }
+        for sent in self.sent.iter_mut() {
+            *sent = false;
+        }
+    }
+
+    /// Return true if all ACK of the different packets have been received
+    pub fn all_ack_received(&self) -> bool {
+        self.ack_received.iter().all(|b| *b)
+    }
+}
@@ -0,0 +1,123 @@
+//
+
+pub mod codec;
+pub mod config;
+pub mod control;
+pub mod crc;
+pub mod dispatcher;
+pub mod errors;
+pub mod extended;
+pub mod header;
+pub mod message;
+pub mod packet;
+#[allow(clippy::module_inception)]
+pub mod protocol;
+pub mod receive;
+pub mod recording;
+pub mod router;
+
+#[cfg(test)]
+mod tests;
+
+use crate::protocol::errors::ProtocolError;
+use heapless::Vec;
+
+pub const U4_MAX: usize = 2usize.pow(4) - 1;
+pub const U3_MAX: usize = 2usize.pow(3) - 1;
+
+pub const MAX_SEQ_NUMBER: usize = U4_MAX;
+pub const PACKET_DATA_SIZE: usize = 6;
+pub const CAN_PACKET_SIZE: usize = 8;
+pub const MAX_MESSAGE_LEN: usize = MAX_SEQ_NUMBER * 6;
+
+/// Limits of the header of a standard frame
+pub const MAX_CAN_ID: usize = U4_MAX;
+pub const MAX_MES_ID: usize = U3_MAX;
+pub const MAX_SEQ_ID: usize = U4_MAX;
+
+/// Limits of the extended header, the ids above the standard limits only go in extended frames
+pub const MAX_NODE_ID: usize = 2usize.pow(8) - 1;
+pub const MAX_EXT_MES_ID: usize = 2usize.pow(5) - 1;
+
+/// Peers a node keeps a state for: epochs, protocol versions, CRC, last message ids
+pub const MAX_PEERS: usize = 32;
+
+/// Frames sent to this id are delivered to every node
+pub const BROADCAST_ID: usize = MAX_CAN_ID;
+/// The ids from `FIRST_GROUP_ID` up to `BROADCAST_ID` are multicast groups,
+/// each node chooses the groups it joins
+pub const FIRST_GROUP_ID: usize = 12;
+
+/// Messages with this id are datagrams, they are never acknowledged
+pub const DATAGRAM_MESSAGE_ID: usize = MAX_MES_ID;
+
+/// Packets sent to one destination and waiting for their ACK, no new packet goes to it past that.
+/// A message has at most one packet in flight so this is also the number of messages sent at once.
+pub const MAX_IN_FLIGHT_PACKETS: usize = 2;
+/// Messages waiting for one destination, so a dead node can't fill the whole send buffer
+pub const MAX_QUEUED_MESSAGES: usize = 4;
+/// Time to wait for an ACK before sending a packet again, in microseconds: a few frames
+/// at 1 Mbit/s with room for a node busy with something else
+pub const DEFAULT_ACK_TIMEOUT: u32 = 10_000;
+/// Retransmissions of a packet before its message is given up, so a node which is gone
+/// doesn't keep its messages forever
+pub const DEFAULT_MAX_RETRIES: u8 = 5;
+
+/// Sets the value kept for `id` in a small map of the last `N` ids seen, the id used the
+/// longest time ago is forgotten when there is no room left
+pub(crate) fn remember<T, const N: usize>(map: &mut Vec<(CanId, T), N>, id: CanId, value: T) {
+    map.retain(|(known, _)| *known != id);
+    if map.is_full() {
+        map.remove(0);
+    }
+    // Can't fail, a place was made above
+    map.push((id, value)).ok();
+}
+
+macro_rules! can_id {
+    ($name:ident, $max_size:expr) => {
+        #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+        pub struct $name {
+            v: u8,
+        }
+
+        impl $name {
+            pub fn new(v: usize) -> Result<Self, ProtocolError> {
+                if v > $max_size {
+                    Err(ProtocolError::InvalidId(v))
+                } else {
+                    Ok(Self { v: v as u8 })
+                }
+            }
+        }
+
+        impl From<$name> for usize {
+            fn from(s: $name) -> Self {
+                s.v as usize
+            }
+        }
+    };
+}
+
+can_id!(CanId, MAX_NODE_ID);
+
+impl CanId {
+    pub fn is_broadcast(&self) -> bool {
+        usize::from(*self) == BROADCAST_ID
+    }
+
+    pub fn is_group(&self) -> bool {
+        (FIRST_GROUP_ID..BROADCAST_ID).contains(&usize::from(*self))
+    }
+
+    /// Multicast frames are never acknowledged so only datagrams can be sent to these ids
+    pub fn is_multicast(&self) -> bool {
+        self.is_group() || self.is_broadcast()
+    }
+}
+
+// The ids above MAX_MES_ID only go in extended frames
+can_id!(MessageId, MAX_EXT_MES_ID);
+
+// SeqId(0) is the last packet
+can_id!(SeqId, MAX_SEQ_ID);
@@ -0,0 +1,102 @@
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::header::Header;
+use crate::protocol::{CanId, BROADCAST_ID, CAN_PACKET_SIZE, PACKET_DATA_SIZE};
+use heapless::Vec;
+
+/// Byte of the payload of a standard control frame which holds its real destination
+const CONTROL_DEST_INDEX: usize = PACKET_DATA_SIZE - 1;
+
+/// This is only for definition and fields should never be accessed nor modified directly
+/// use the methods provided with the struct instead
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub header: Header,
+    /// `PACKET_DATA_SIZE` bytes, the data packets of extended frames use the whole
+    /// `CAN_PACKET_SIZE`
+    pub payload: Vec<u8, CAN_PACKET_SIZE>,
+}
+
+impl Packet {
+    pub fn new(header: Header, data: [u8; PACKET_DATA_SIZE]) -> Packet {
+        Packet {
+            header,
+            // Can't fail, the payload is shorter than a frame
+            payload: Vec::from_slice(&data).unwrap(),
+        }
+    }
+
+    /// A data packet of an extended frame, all the data bytes are payload
+    pub fn extended(header: Header, data: [u8; CAN_PACKET_SIZE]) -> Packet {
+        Packet {
+            header,
+            // Can't fail, the payload is a whole frame
+            payload: Vec::from_slice(&data).unwrap(),
+        }
+    }
+
+    /// A frame with the ACK bit which isn't a plain ACK. Legacy nodes take every frame with
+    /// the ACK bit for an ACK, see `TryInto<[u8; CAN_PACKET_SIZE]>`.
+    fn is_control(&self) -> bool {
+        self.header.is_ack && self.payload.iter().any(|b| *b != 0)
+    }
+}
+
+/// The other control frames than the plain ACK are sent to `BROADCAST_ID` in standard
+/// frames, their real destination is in the last payload byte. Legacy nodes drop them as they
+/// are not addressed to their id, and a node of the bus can't have the broadcast id.
+/// Fails if the header or the payload only fit in an extended frame.
+impl TryInto<[u8; CAN_PACKET_SIZE]> for &Packet {
+    type Error = ProtocolError;
+
+    fn try_into(self) -> Result<[u8; CAN_PACKET_SIZE], Self::Error> {
+        if self.payload.len() != PACKET_DATA_SIZE {
+            return Err(ProtocolError::ExtendedFrameOnly);
+        }
+        let mut packet = [0u8; CAN_PACKET_SIZE];
+        let slice: [u8; 2] = (&self.header).try_into()?;
+        packet[0..2].copy_from_slice(&slice);
+        packet[2..].copy_from_slice(&self.payload);
+        if self.is_control() {
+            let mut header = self.header.clone();
+            header.id_dest = CanId::new(BROADCAST_ID)?;
+            let slice: [u8; 2] = (&header).try_into()?;
+            packet[0..2].copy_from_slice(&slice);
+            // Fits, the header was checked above
+            packet[2 + CONTROL_DEST_INDEX] = usize::from(self.header.id_dest) as u8;
+        }
+        Ok(packet)
+    }
+}
+
+impl TryInto<[u8; CAN_PACKET_SIZE]> for Packet {
+    type Error = ProtocolError;
+
+    fn try_into(self) -> Result<[u8; CAN_PACKET_SIZE], Self::Error> {
+        (&self).try_into()
+    }
+}
+
+impl TryInto<[u8; CAN_PACKET_SIZE]> for &mut Packet {
+    type Error = ProtocolError;
+
+    fn try_into(self) -> Result<[u8; CAN_PACKET_SIZE], Self::Error> {
+        (&*self).try_into()
+    }
+}
+
+impl TryFrom<&[u8; CAN_PACKET_SIZE]> for Packet {
+    type Error = ProtocolError;
+
+    fn try_from(value: &[u8; CAN_PACKET_SIZE]) -> Result<Self, Self::Error> {
+        let mut packet = Packet {
+            header: Header::try_from(value)?,
+            // Can't fail, the payload is shorter than a frame
+            payload: Vec::from_slice(&value[2..]).unwrap(),
+        };
+        if packet.is_control() && packet.header.id_dest.is_broadcast() {
+            packet.header.id_dest = CanId::new(packet.payload[CONTROL_DEST_INDEX].into())?;
+            packet.payload[CONTROL_DEST_INDEX] = 0;
+        }
+        Ok(packet)
+    }
+}
@@ -0,0 +1,655 @@
+use crate::protocol::config::{ProtocolBuilder, ProtocolConfig};
+use crate::protocol::control::{ControlFrame, Epoch, NackReason};
+use crate::protocol::crc::{check_crc, CRC_SIZE};
+use crate::protocol::errors::{ProtocolError, SendError, SendFailure};
+use crate::protocol::extended::{CanFrame, PROTOCOL_VERSION, PROTOCOL_VERSION_EXTENDED};
+use crate::protocol::header::Header;
+use crate::protocol::message::Message;
+use crate::protocol::packet::Packet;
+use crate::protocol::receive::{ReceivePool, Stored, RECEIVE_POOL_SIZE};
+use crate::protocol::{
+    remember, CanId, MessageId, SeqId, BROADCAST_ID, CAN_PACKET_SIZE, DATAGRAM_MESSAGE_ID,
+    FIRST_GROUP_ID, MAX_CAN_ID, MAX_EXT_MES_ID, MAX_MES_ID, MAX_NODE_ID, MAX_PEERS,
+    PACKET_DATA_SIZE,
+};
+use core::mem::swap;
+use heapless::spsc::Consumer;
+use heapless::Vec;
+
+/// Decides whether a complete message is accepted
+pub type MessageFilter = fn(&[u8]) -> Result<(), NackReason>;
+
+/// `SEND` messages can wait to be sent, `RECEIVE` messages to be reassembled or read and
+/// `CONTROL` answers to be sent. The defaults suit most nodes, see `ProtocolBuilder` otherwise.
+pub struct Protocol<
+    const SEND: usize = 8,
+    const RECEIVE: usize = RECEIVE_POOL_SIZE,
+    const CONTROL: usize = 16,
+> {
+    pub host_id: CanId,
+    pub config: ProtocolConfig,
+    /// The complete messages, and the ones being reassembled
+    pub received: ReceivePool<RECEIVE>,
+    pub acks_to_send: Vec<Packet, CONTROL>,
+    /// Shared by every destination, the messages of one destination are sent in order
+    pub send_buff: Vec<Message, SEND>,
+    pub epoch: Option<Epoch>,
+    pub peer_epochs: Vec<(CanId, Epoch), MAX_PEERS>,
+    /// Peers which have not acknowledged our epoch yet, their data packets are dropped
+    /// until they do as they might belong to the previous session
+    pub pending_epoch_acks: Vec<CanId, MAX_PEERS>,
+    /// Called on every complete message before it is acknowledged, a rejected message is
+    /// answered with a NACK instead and never reaches `received`
+    pub message_filter: Option<MessageFilter>,
+    /// Messages refused for good by their receiver, the oldest are kept if it overflows
+    pub send_errors: Vec<SendFailure, SEND>,
+    /// Highest protocol version announced by each peer, the others are legacy nodes
+    pub peer_versions: Vec<(CanId, u8), MAX_PEERS>,
+    /// The unicast messages exchanged with these peers end with a CRC-16,
+    /// both sides must have the other one in the list
+    pub crc_peers: Vec<CanId, MAX_PEERS>,
+    /// Number of reassembled messages discarded because of a bad CRC
+    pub crc_errors: u32,
+    /// Multicast groups this node belongs to, see `FIRST_GROUP_ID`
+    pub groups: Vec<CanId, { BROADCAST_ID - FIRST_GROUP_ID }>,
+    /// The destination served first by the next call to `get_next_packet_to_send`
+    next_dest: usize,
+    /// Id following the last message queued for the latest `MAX_PEERS` destinations,
+    /// see `next_message_id`
+    next_ids: Vec<(CanId, u8), MAX_PEERS>,
+    /// Time given to the last call to `poll`, in microseconds
+    now: u32,
+}
+
+impl Protocol {
+    pub fn new(host_id: CanId) -> Result<Self, ProtocolError> {
+        ProtocolBuilder::new(host_id).build()
+    }
+}
+
+impl<const SEND: usize, const RECEIVE: usize, const CONTROL: usize>
+    Protocol<SEND, RECEIVE, CONTROL>
+{
+    /// Evaluated by `ProtocolBuilder::build`, a bad capacity doesn't compile
+    pub(crate) const CAPACITIES_CHECK: () = {
+        assert!(SEND > 0, "the send buffer can't be empty");
+        assert!(
+            RECEIVE > 0 && RECEIVE <= u8::MAX as usize,
+            "the receive pool needs between 1 and 255 slots"
+        );
+        assert!(CONTROL > 0, "the control queue can't be empty");
+    };
+
+    pub fn builder(host_id: CanId) -> ProtocolBuilder<SEND, RECEIVE, CONTROL> {
+        ProtocolBuilder::new(host_id)
+    }
+
+    pub(crate) fn with_config(
+        host_id: CanId,
+        config: ProtocolConfig,
+    ) -> Result<Self, ProtocolError> {
+        Ok(Protocol {
+            host_id,
+            config,
+            acks_to_send: Vec::new(),
+            received: ReceivePool::new(),
+            send_buff: Vec::new(),
+            epoch: None,
+            peer_epochs: Vec::new(),
+            pending_epoch_acks: Vec::new(),
+            message_filter: None,
+            send_errors: Vec::new(),
+            peer_versions: Vec::new(),
+            crc_peers: Vec::new(),
+            crc_errors: 0,
+            groups: Vec::new(),
+            next_dest: 0,
+            next_ids: Vec::new(),
+            now: 0,
+        })
+    }
+
+    /// The datagrams sent to `group` will be received by this node
+    pub fn join_group(&mut self, group: CanId) -> Result<(), ProtocolError> {
+        if !group.is_group() {
+            return Err(ProtocolError::InvalidId(usize::from(group)));
+        }
+        if !self.groups.contains(&group) {
+            // Can't fail, there is a slot for every group id
+            self.groups.push(group).unwrap();
+        }
+        Ok(())
+    }
+
+    pub fn leave_group(&mut self, group: CanId) {
+        self.groups.retain(|id| *id != group);
+    }
+
+    /// True if the frames sent to `id_dest` are meant for this node
+    pub fn is_addressed_to_us(&self, id_dest: CanId) -> bool {
+        id_dest == self.host_id || id_dest.is_broadcast() || self.groups.contains(&id_dest)
+    }
+
+    /// Starts a new session, to be called at boot before sending anything.
+    /// The peers will flush the reassembly and pending-ACK state they have for this node.
+    pub fn announce_epoch(&mut self, epoch: Epoch, peers: &[CanId]) -> Result<(), ProtocolError> {
+        self.epoch = Some(epoch);
+        self.pending_epoch_acks.clear();
+        for peer in peers {
+            if *peer == self.host_id {
+                return Err(ProtocolError::SrcAndDestCanNotBeEqual);
+            }
+            self.pending_epoch_acks
+                .push(*peer)
+                .map_err(|_| ProtocolError::TooManyPeers)?;
+        }
+        self.resend_epoch_announces()
+    }
+
+    /// Sends the announce again to the peers which did not answer yet
+    pub fn resend_epoch_announces(&mut self) -> Result<(), ProtocolError> {
+        if let Some(epoch) = self.epoch {
+            for i in 0..self.pending_epoch_acks.len() {
+                let peer = self.pending_epoch_acks[i];
+                self.send_control(peer, ControlFrame::EpochAnnounce(epoch))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Tells the peers which protocol version this node speaks, the ones which speak it too
+    /// answer and are then sent extended frames. Legacy nodes ignore it.
+    pub fn announce_version(&mut self, peers: &[CanId]) -> Result<(), ProtocolError> {
+        for peer in peers {
+            self.send_control(*peer, ControlFrame::Version(PROTOCOL_VERSION))?;
+        }
+        Ok(())
+    }
+
+    /// The version both this node and `peer` speak
+    pub fn version_with(&self, peer: CanId) -> u8 {
+        self.peer_versions
+            .iter()
+            .find(|(id, _)| *id == peer)
+            .map_or(0, |(_, version)| (*version).min(PROTOCOL_VERSION))
+    }
+
+    /// True if the frames exchanged with `peer` can be extended ones: it announced it speaks
+    /// them, or one of the ids doesn't fit in a standard frame. The members of a group might
+    /// be legacy nodes.
+    pub fn speaks_extended(&self, peer: CanId) -> bool {
+        usize::from(self.host_id) > MAX_CAN_ID
+            || usize::from(peer) > MAX_CAN_ID
+            || (!peer.is_multicast() && self.version_with(peer) >= PROTOCOL_VERSION_EXTENDED)
+    }
+
+    fn send_control(&mut self, dest: CanId, control: ControlFrame) -> Result<(), ProtocolError> {
+        let header = Header::new(dest, self.host_id, true, MessageId::new(0)?, SeqId::new(0)?)?;
+        self.acks_to_send
+            .push(Packet::new(header, control.into()))
+            .map_err(|_| ProtocolError::ControlQueueFull)
+    }
+
+    fn process_control_packet(&mut self, packet: Packet) -> Result<(), ProtocolError> {
+        let src = packet.header.id_src;
+        match ControlFrame::try_from(&packet)? {
+            ControlFrame::Ack => self.process_ack_packet(packet),
+            ControlFrame::EpochAnnounce(epoch) => {
+                match self.peer_epochs.iter_mut().find(|(id, _)| *id == src) {
+                    Some((_, known)) if *known == epoch => {}
+                    Some((_, known)) => {
+                        *known = epoch;
+                        self.flush_peer(src);
+                    }
+                    None => {
+                        self.peer_epochs
+                            .push((src, epoch))
+                            .map_err(|_| ProtocolError::TooManyPeers)?;
+                        self.flush_peer(src);
+                    }
+                }
+                self.send_control(src, ControlFrame::EpochAck(epoch))?;
+            }
+            ControlFrame::EpochAck(epoch) => {
+                if Some(epoch) == self.epoch {
+                    self.pending_epoch_acks.retain(|id| *id != src);
+                }
+            }
+            ControlFrame::Nack(reason) => self.process_nack_packet(packet, reason),
+            ControlFrame::Version(version) => {
+                let known = self.peer_versions.iter().find(|(id, _)| *id == src);
+                // Answers only once so two nodes don't echo each other forever, the version
+                // is kept once the answer is queued so it is answered again otherwise
+                if known.is_none_or(|(_, known)| *known != version) {
+                    self.send_control(src, ControlFrame::Version(PROTOCOL_VERSION))?;
+                    match self.peer_versions.iter_mut().find(|(id, _)| *id == src) {
+                        Some((_, known)) => *known = version,
+                        None => self
+                            .peer_versions
+                            .push((src, version))
+                            .map_err(|_| ProtocolError::TooManyPeers)?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn process_nack_packet(&mut self, packet: Packet, reason: NackReason) {
+        let position = self
+            .send_buff
+            .iter()
+            .position(|m| m.id == packet.header.id_message && m.id_dest() == packet.header.id_src);
+        match position {
+            // The receiver dropped the whole message, the ACKs it sent are worthless
+            Some(i) if reason == NackReason::Corrupted => self.send_buff[i].reset_acks(),
+            // A transient rejection changes nothing, the packet will be sent again
+            Some(_) if !reason.is_permanent() => {}
+            Some(i) => {
+                self.send_buff.remove(i);
+                // The receiver won't be more interested next time, the error is lost if nobody
+                // looks at them
+                let _ = self.send_errors.push(SendFailure {
+                    id_dest: packet.header.id_src,
+                    id: packet.header.id_message,
+                    error: SendError::Rejected(reason),
+                });
+            }
+            None => {}
+        }
+    }
+
+    /// Forgets everything belonging to the previous session of `peer`
+    fn flush_peer(&mut self, peer: CanId) {
+        self.received.drop_in_progress_from(peer);
+        // The answers to the old session are dropped, our own announce has to go through
+        self.acks_to_send.retain(|p| {
+            p.header.id_dest != peer
+                || matches!(
+                    ControlFrame::try_from(p),
+                    Ok(ControlFrame::EpochAnnounce(_))
+                )
+        });
+        // The peer lost what it had already received, everything has to be sent again
+        for message in self.send_buff.iter_mut() {
+            if message.id_dest() == peer {
+                message.reset_acks();
+            }
+        }
+    }
+
+    fn process_ack_packet(&mut self, packet: Packet) {
+        match self
+            .send_buff
+            .iter()
+            .position(|m| m.id == packet.header.id_message && m.id_dest() == packet.header.id_src)
+        {
+            None => {}
+            Some(i) => {
+                self.send_buff[i].mark_ack_as_received(packet.header.seq_number);
+                if self.send_buff[i].all_ack_received() {
+                    self.send_buff.remove(i);
+                }
+            }
+        }
+    }
+
+    pub fn process_data_packet(&mut self, packet: Packet) -> Result<(), ProtocolError> {
+        let answer = self.store_data_packet(&packet)?;
+        if usize::from(packet.header.id_message) == DATAGRAM_MESSAGE_ID {
+            // Datagrams are never answered, not even to refuse them
+            return Ok(());
+        }
+        self.reply(&packet, answer)
+    }
+
+    /// Returns the answer to send for this packet
+    fn store_data_packet(&mut self, packet: &Packet) -> Result<ControlFrame, ProtocolError> {
+        let slot = match self.received.store(packet) {
+            // A retransmission of a packet we already have, its ACK was probably lost
+            Stored::Duplicate | Stored::Incomplete => return Ok(ControlFrame::Ack),
+            Stored::Full => return Ok(ControlFrame::Nack(NackReason::BufferFull)),
+            Stored::Malformed => return Ok(ControlFrame::Nack(NackReason::MalformedPayload)),
+            Stored::Complete(slot) => slot,
+        };
+        let data = self.received.assembled(slot);
+        let mut len = data.len();
+        if packet.header.id_dest == self.host_id && self.crc_peers.contains(&packet.header.id_src) {
+            if !check_crc(data) {
+                self.received.release(slot);
+                self.crc_errors = self.crc_errors.wrapping_add(1);
+                return Ok(ControlFrame::Nack(NackReason::Corrupted));
+            }
+            len -= CRC_SIZE;
+        }
+        if let Some(Err(reason)) = self.message_filter.map(|filter| filter(&data[..len])) {
+            self.received.release(slot);
+            return Ok(ControlFrame::Nack(reason));
+        }
+        self.received.publish(slot, len);
+        Ok(ControlFrame::Ack)
+    }
+
+    pub fn process_raw_packet(&mut self, mess: [u8; 8]) -> Result<(), ProtocolError> {
+        self.process_packet(Packet::try_from(&mess)?)
+    }
+
+    /// Processes the frames pushed by the CAN RX interrupt, which then never touches `Protocol`
+    /// and doesn't need a lock. Stops at the first invalid frame, and while there is no room
+    /// left for an answer in `acks_to_send`: the next frames stay queued until the answers
+    /// are sent.
+    pub fn process_queued_frames<const N: usize>(
+        &mut self,
+        frames: &mut Consumer<'_, [u8; CAN_PACKET_SIZE], N>,
+    ) -> Result<(), ProtocolError> {
+        while !self.acks_to_send.is_full() {
+            match frames.dequeue() {
+                Some(frame) => self.process_raw_packet(frame)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as `process_raw_packet` for both standard and extended frames
+    pub fn process_frame(&mut self, frame: &CanFrame) -> Result<(), ProtocolError> {
+        self.process_packet(Packet::try_from(frame)?)
+    }
+
+    fn process_packet(&mut self, packet: Packet) -> Result<(), ProtocolError> {
+        let multicast = packet.header.id_dest.is_multicast();
+        if !self.is_addressed_to_us(packet.header.id_dest) {
+            Ok(())
+        } else {
+            if multicast
+                && (packet.header.is_ack
+                    || usize::from(packet.header.id_message) != DATAGRAM_MESSAGE_ID)
+            {
+                // Not allowed by `Header::new`, answering it would flood the bus
+            } else if packet.header.is_ack {
+                self.process_control_packet(packet)?;
+            } else if self.pending_epoch_acks.contains(&packet.header.id_src) {
+                // Might be the end of a message started before our reboot, it will be sent again
+            } else {
+                self.process_data_packet(packet)?;
+            }
+            Ok(())
+        }
+    }
+
+    pub fn send_ack(&mut self, packet_to_respond: &Packet) -> Result<(), ProtocolError> {
+        self.reply(packet_to_respond, ControlFrame::Ack)
+    }
+
+    pub fn send_nack(
+        &mut self,
+        packet_to_respond: &Packet,
+        reason: NackReason,
+    ) -> Result<(), ProtocolError> {
+        self.reply(packet_to_respond, ControlFrame::Nack(reason))
+    }
+
+    /// The answer carries the message id and sequence number of the packet it answers to
+    fn reply(
+        &mut self,
+        packet_to_respond: &Packet,
+        control: ControlFrame,
+    ) -> Result<(), ProtocolError> {
+        let mut ack_header = packet_to_respond.header.clone();
+        swap(&mut ack_header.id_src, &mut ack_header.id_dest);
+        ack_header.is_ack = true;
+        self.acks_to_send
+            .push(Packet::new(ack_header, control.into()))
+            .map_err(|_| ProtocolError::ControlQueueFull)
+    }
+
+    pub fn add_message_to_send_buff(&mut self, mut mes: Message) -> Result<(), ProtocolError> {
+        if let (None, Some(lifetime)) = (mes.deadline(), self.config.default_lifetime) {
+            mes = mes.with_deadline(self.now.wrapping_add(lifetime));
+        }
+        if self.speaks_extended(mes.id_dest()) {
+            mes = mes.with_extended_packets()?;
+        } else if usize::from(mes.id) > MAX_MES_ID {
+            return Err(ProtocolError::InvalidId(usize::from(mes.id)));
+        }
+        if self.crc_peers.contains(&mes.id_dest()) {
+            mes = mes.with_crc()?;
+        }
+        if let Some(key) = mes.replace_key() {
+            let pending = self.send_buff.iter_mut().find(|m| {
+                m.id_dest() == mes.id_dest() && m.replace_key() == Some(key) && !m.has_started()
+            });
+            if let Some(pending) = pending {
+                // Takes the place of the stale message in the queue
+                *pending = mes;
+                return Ok(());
+            }
+        }
+        let queued = self
+            .send_buff
+            .iter()
+            .filter(|m| m.id_dest() == mes.id_dest())
+            .count();
+        if queued >= self.config.max_queued_messages {
+            return Err(ProtocolError::SendFailed(SendError::QueueFull));
+        }
+        let (dest, id) = (mes.id_dest(), usize::from(mes.id));
+        self.send_buff
+            .push(mes)
+            .map_err(|_| ProtocolError::SendFailed(SendError::QueueFull))?;
+        if id != DATAGRAM_MESSAGE_ID {
+            let next = ((id + 1) % self.message_ids(dest)) as u8;
+            remember(&mut self.next_ids, dest, next);
+        }
+        Ok(())
+    }
+
+    /// True if a new message to `dest` would be accepted by `add_message_to_send_buff`
+    pub fn can_send_to(&self, dest: CanId) -> bool {
+        let queued = self
+            .send_buff
+            .iter()
+            .filter(|m| m.id_dest() == dest)
+            .count();
+        !self.send_buff.is_full() && queued < self.config.max_queued_messages
+    }
+
+    /// An id for the next message to `dest`: it follows the last one queued for it and no
+    /// message waiting for `dest` uses it. The receiver takes a message with the same id as
+    /// the previous one for a retransmission, so every sender on the node should get its
+    /// ids here.
+    /// The ids above `MAX_MES_ID` are only given for the peers which speak extended frames.
+    /// Only the last `MAX_PEERS` destinations are remembered, the ids to an older one start
+    /// from 0 again.
+    pub fn next_message_id(&self, dest: CanId) -> Result<MessageId, ProtocolError> {
+        let next = self
+            .next_ids
+            .iter()
+            .find(|(id, _)| *id == dest)
+            .map_or(0, |(_, next)| usize::from(*next));
+        let count = self.message_ids(dest);
+        (0..count)
+            .map(|offset| (next + offset) % count)
+            .find(|id| {
+                *id != DATAGRAM_MESSAGE_ID
+                    && !self
+                        .send_buff
+                        .iter()
+                        .any(|m| m.id_dest() == dest && usize::from(m.id) == *id)
+            })
+            .ok_or(ProtocolError::SendFailed(SendError::QueueFull))
+            .and_then(MessageId::new)
+    }
+
+    /// Number of message ids the header to `dest` has room for, the datagram id included
+    fn message_ids(&self, dest: CanId) -> usize {
+        if self.speaks_extended(dest) {
+            MAX_EXT_MES_ID + 1
+        } else {
+            DATAGRAM_MESSAGE_ID
+        }
+    }
+
+    /// Same as `get_next_packet_to_send`, the packets which need it and the control frames
+    /// to a peer which speaks the extended version are sent in extended frames
+    pub fn get_next_frame_to_send(&mut self) -> Result<Option<CanFrame>, ProtocolError> {
+        let packet = match self.next_packet()? {
+            Some(packet) => packet,
+            None => return Ok(None),
+        };
+        let header = &packet.header;
+        // The packets of a message all go in the same kind of frame, see `CanFrame::extended`
+        let extended = packet.payload.len() > PACKET_DATA_SIZE
+            || !header.fits_standard()
+            || (header.is_ack && self.speaks_extended(header.id_dest));
+        if extended {
+            Ok(Some(CanFrame::extended(&packet)))
+        } else {
+            Ok(Some(CanFrame::Standard((&packet).try_into()?)))
+        }
+    }
+
+    /// Gives the time to the protocol, in microseconds: the expired messages are dropped, the
+    /// packets whose ACK is late can be sent again and the messages which used all their
+    /// retries are given up. Without it no packet is ever sent again.
+    pub fn poll(&mut self, now: u32) {
+        self.now = now;
+        self.drop_expired_messages(now);
+        let max_retries = match self.config.max_retries {
+            Some(max_retries) => max_retries,
+            None => return,
+        };
+        let mut i = 0;
+        while i < self.send_buff.len() {
+            let message = &self.send_buff[i];
+            if message.retries() >= max_retries && self.ack_is_late(message) {
+                let _ = self.send_errors.push(SendFailure {
+                    id_dest: message.id_dest(),
+                    id: message.id,
+                    error: SendError::DidntReceiveACK,
+                });
+                self.send_buff.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn ack_is_late(&self, message: &Message) -> bool {
+        message.packets_in_flight() > 0
+            && self.now.wrapping_sub(message.last_sent()) >= self.config.ack_timeout
+    }
+
+    /// Drops the messages whose deadline is reached at `now`, in microseconds.
+    /// The reliable ones are reported in `send_errors`.
+    pub fn drop_expired_messages(&mut self, now: u32) {
+        let mut i = 0;
+        while i < self.send_buff.len() {
+            let message = &self.send_buff[i];
+            // The clock may wrap around
+            match message.deadline() {
+                Some(deadline) if now.wrapping_sub(deadline) as i32 >= 0 => {
+                    if !message.is_datagram() {
+                        let _ = self.send_errors.push(SendFailure {
+                            id_dest: message.id_dest(),
+                            id: message.id,
+                            error: SendError::Expired,
+                        });
+                    }
+                    self.send_buff.remove(i);
+                }
+                _ => i += 1,
+            }
+        }
+    }
+
+    /// The answers go first. Then the destinations take turns: new packets are sent while
+    /// a destination has less than `max_in_flight_packets` waiting for their ACK, and the
+    /// unacknowledged packets are only sent again when there is nothing new to send and
+    /// their ACK is late.
+    /// A node which stops answering only delays its own messages.
+    /// Only standard frames, a node which talks to extended peers uses `get_next_frame_to_send`.
+    pub fn get_next_packet_to_send(&mut self) -> Result<Option<[u8; 8]>, ProtocolError> {
+        match self.next_packet()? {
+            Some(packet) => Ok(Some(packet.try_into()?)),
+            None => Ok(None),
+        }
+    }
+
+    fn next_packet(&mut self) -> Result<Option<Packet>, ProtocolError> {
+        if let Some(packet) = self.acks_to_send.pop() {
+            return Ok(Some(packet));
+        }
+
+        // The destinations with a message waiting, in turn from `next_dest`
+        let mut dests: Vec<CanId, SEND> = Vec::new();
+        for message in self.send_buff.iter() {
+            if !dests.contains(&message.id_dest()) {
+                // Can't fail, there are as many slots as messages in the send buffer
+                dests.push(message.id_dest()).unwrap();
+            }
+        }
+        let next_dest = self.next_dest;
+        dests.sort_unstable_by_key(|dest| {
+            (usize::from(*dest) + MAX_NODE_ID + 1 - next_dest) % (MAX_NODE_ID + 1)
+        });
+        for retransmit in [false, true] {
+            for dest in dests.iter() {
+                if let Some(packet) = self.next_packet_for(*dest, retransmit)? {
+                    self.next_dest = usize::from(*dest) + 1;
+                    // A datagram is done as soon as its last packet is sent
+                    self.send_buff.retain(|m| !m.all_ack_received());
+                    return Ok(Some(packet));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_packet_for(
+        &mut self,
+        dest: CanId,
+        retransmit: bool,
+    ) -> Result<Option<Packet>, ProtocolError> {
+        let now = self.now;
+        if retransmit {
+            let max_retries = self.config.max_retries;
+            let position = self.send_buff.iter().position(|m| {
+                m.id_dest() == dest
+                    && self.ack_is_late(m)
+                    && max_retries.is_none_or(|max| m.retries() < max)
+            });
+            return match position {
+                Some(i) => {
+                    let packet = self.send_buff[i].get_next_packet_to_send()?;
+                    self.send_buff[i].record_send(now, true);
+                    Ok(packet)
+                }
+                None => Ok(None),
+            };
+        }
+        let max_in_flight = self.config.max_in_flight_packets;
+        let queue = self.send_buff.iter_mut().filter(|m| m.id_dest() == dest);
+        let mut in_flight = 0;
+        // The values sharing a replace key must arrive in order, a message waits for the
+        // previous one with the same key
+        let mut pending_keys: Vec<u8, SEND> = Vec::new();
+        for message in queue {
+            in_flight += message.packets_in_flight();
+            if in_flight >= max_in_flight {
+                return Ok(None);
+            }
+            match message.replace_key() {
+                Some(key) if pending_keys.contains(&key) => continue,
+                // Can't fail, there are as many slots as messages in the send buffer
+                Some(key) => pending_keys.push(key).unwrap(),
+                None => {}
+            }
+            if let Some(packet) = message.get_next_new_packet()? {
+                message.record_send(now, false);
+                return Ok(Some(packet));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,287 @@
+use crate::protocol::packet::Packet;
+use crate::protocol::{
+    remember, CanId, MessageId, CAN_PACKET_SIZE, DATAGRAM_MESSAGE_ID, MAX_PEERS, MAX_SEQ_ID,
+    PACKET_DATA_SIZE,
+};
+use core::ops::Index;
+use heapless::spsc::Queue;
+use heapless::{Deque, Vec};
+
+/// Default number of slots shared by the messages being reassembled and the messages
+/// waiting to be read
+pub const RECEIVE_POOL_SIZE: usize = 8;
+
+/// Room for the longest message a sequence number can describe in standard frames, the
+/// longest message fits in the packets of extended frames too
+const SLOT_SIZE: usize = (MAX_SEQ_ID + 1) * PACKET_DATA_SIZE;
+
+/// Raw frames pushed by the CAN RX interrupt through the `Producer` half and handed to
+/// `Protocol::process_queued_frames` by the main loop. It holds `N - 1` frames.
+pub type FrameQueue<const N: usize> = Queue<[u8; CAN_PACKET_SIZE], N>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Free,
+    /// `received` has one bit per sequence number already written, all the packets of a
+    /// message have the size of the first one
+    InProgress {
+        src: CanId,
+        id: MessageId,
+        received: u16,
+        packet_size: u8,
+    },
+    Ready {
+        src: CanId,
+        id: MessageId,
+        start: u8,
+        end: u8,
+    },
+}
+
+/// A received message, borrowed from the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceivedMessage<'a> {
+    pub src: CanId,
+    pub id: MessageId,
+    pub data: &'a [u8],
+}
+
+impl ReceivedMessage<'_> {
+    pub fn is_datagram(&self) -> bool {
+        usize::from(self.id) == DATAGRAM_MESSAGE_ID
+    }
+}
+
+/// What `ReceivePool::store` did with a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stored {
+    /// Already written or part of the last message completed by its source,
+    /// it was sent again because its ACK was lost
+    Duplicate,
+    Incomplete,
+    /// Every packet of the message in this slot is there
+    Complete(usize),
+    /// No free slot for a new message
+    Full,
+    /// Not the size of the other packets of the message, or beyond the end of the slot
+    Malformed,
+}
+
+/// Received messages stay where their packets were written: the payload of SeqId(s) goes
+/// `s` packets before the end of a slot, so a complete message is already contiguous and is
+/// read as a borrowed slice. The packets of a message must come in order, from the first one
+/// down to SeqId(0), as the sender keeps a single packet of a message in flight: a message
+/// is complete once SeqId(0) follows the packets above it.
+#[derive(Debug)]
+pub struct ReceivePool<const N: usize = RECEIVE_POOL_SIZE> {
+    buffers: [[u8; SLOT_SIZE]; N],
+    slots: [Slot; N],
+    /// The `Ready` slots, oldest first
+    ready: Deque<u8, N>,
+    /// Id of the last message published by the latest `MAX_PEERS` sources, its packets sent
+    /// again are not taken for a new message
+    last_complete: Vec<(CanId, MessageId), MAX_PEERS>,
+}
+
+impl<const N: usize> Default for ReceivePool<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ReceivePool<N> {
+    pub const fn new() -> Self {
+        ReceivePool {
+            buffers: [[0; SLOT_SIZE]; N],
+            slots: [Slot::Free; N],
+            ready: Deque::new(),
+            last_complete: Vec::new(),
+        }
+    }
+
+    /// Number of messages waiting to be read
+    pub fn len(&self) -> usize {
+        self.ready.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ready.is_empty()
+    }
+
+    /// True when a new message can't be started, the messages in progress use slots too
+    pub fn is_full(&self) -> bool {
+        !self.slots.contains(&Slot::Free)
+    }
+
+    /// The oldest message
+    pub fn front(&self) -> Option<&[u8]> {
+        self.ready.front().map(|slot| self.message(*slot as usize))
+    }
+
+    /// The oldest message with its source
+    pub fn front_message(&self) -> Option<ReceivedMessage<'_>> {
+        let slot = *self.ready.front()? as usize;
+        match self.slots[slot] {
+            Slot::Ready { src, id, .. } => Some(ReceivedMessage {
+                src,
+                id,
+                data: self.message(slot),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Frees the oldest message
+    pub fn pop_front(&mut self) {
+        if let Some(slot) = self.ready.pop_front() {
+            self.slots[slot as usize] = Slot::Free;
+        }
+    }
+
+    /// Frees every message waiting to be read, the ones in progress are kept
+    pub fn clear(&mut self) {
+        while !self.ready.is_empty() {
+            self.pop_front();
+        }
+    }
+
+    /// The messages waiting to be read, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.ready.iter().map(|slot| self.message(*slot as usize))
+    }
+
+    /// Source and id of the messages being reassembled
+    pub fn in_progress(&self) -> impl Iterator<Item = (CanId, MessageId)> + '_ {
+        self.slots.iter().filter_map(|slot| match slot {
+            Slot::InProgress { src, id, .. } => Some((*src, *id)),
+            _ => None,
+        })
+    }
+
+    fn message(&self, slot: usize) -> &[u8] {
+        match self.slots[slot] {
+            Slot::Ready { start, end, .. } => &self.buffers[slot][start as usize..end as usize],
+            _ => &[],
+        }
+    }
+
+    pub(crate) fn store(&mut self, packet: &Packet) -> Stored {
+        let src = packet.header.id_src;
+        let id = packet.header.id_message;
+        let seq = usize::from(packet.header.seq_number);
+        let size = packet.payload.len();
+        if size == 0 || (seq + 1) * size > SLOT_SIZE {
+            return Stored::Malformed;
+        }
+        let position = self.slots.iter().position(
+            |slot| matches!(slot, Slot::InProgress { src: s, id: i, .. } if *s == src && *i == id),
+        );
+        if position.is_none() && self.last_complete.contains(&(src, id)) {
+            return Stored::Duplicate;
+        }
+        let slot = match position.or_else(|| self.slots.iter().position(|s| *s == Slot::Free)) {
+            Some(slot) => slot,
+            None => return Stored::Full,
+        };
+        if self.slots[slot] == Slot::Free {
+            self.slots[slot] = Slot::InProgress {
+                src,
+                id,
+                received: 0,
+                packet_size: size as u8,
+            };
+        }
+        let received = match &mut self.slots[slot] {
+            Slot::InProgress { packet_size, .. } if *packet_size as usize != size => {
+                return Stored::Malformed;
+            }
+            Slot::InProgress { received, .. } if *received & (1 << seq) != 0 => {
+                return Stored::Duplicate;
+            }
+            Slot::InProgress { received, .. } => {
+                *received |= 1 << seq;
+                *received
+            }
+            // Only in progress slots were picked above
+            _ => unreachable!(),
+        };
+        let offset = SLOT_SIZE - (seq + 1) * size;
+        self.buffers[slot][offset..offset + size].copy_from_slice(&packet.payload);
+
+        // Complete once every packet from the first one down to SeqId(0) is there
+        let first = MAX_SEQ_ID - received.leading_zeros() as usize;
+        if u32::from(received) == (1u32 << (first + 1)) - 1 {
+            Stored::Complete(slot)
+        } else {
+            Stored::Incomplete
+        }
+    }
+
+    /// The whole data of the complete message in `slot`
+    pub(crate) fn assembled(&self, slot: usize) -> &[u8] {
+        &self.buffers[slot][Self::start(self.slots[slot])..]
+    }
+
+    /// Makes the first `len` bytes of the complete message in `slot` readable
+    pub(crate) fn publish(&mut self, slot: usize, len: usize) {
+        let (src, id) = match self.slots[slot] {
+            Slot::InProgress { src, id, .. } => (src, id),
+            // Only complete messages are published
+            _ => return,
+        };
+        let start = Self::start(self.slots[slot]);
+        self.slots[slot] = Slot::Ready {
+            src,
+            id,
+            start: start as u8,
+            end: (start + len) as u8,
+        };
+        // Can't fail, there is a place for every slot
+        self.ready.push_back(slot as u8).unwrap();
+        // The datagrams all share the same id and are never sent again
+        if usize::from(id) != DATAGRAM_MESSAGE_ID {
+            remember(&mut self.last_complete, src, id);
+        }
+    }
+
+    /// Drops the message in `slot` before it was published
+    pub(crate) fn release(&mut self, slot: usize) {
+        self.slots[slot] = Slot::Free;
+    }
+
+    /// Drops the messages `src` had started to send, the complete ones are kept.
+    /// Its next message may reuse the id of the last one.
+    pub(crate) fn drop_in_progress_from(&mut self, src: CanId) {
+        self.last_complete.retain(|(known, _)| *known != src);
+        for slot in self.slots.iter_mut() {
+            if matches!(slot, Slot::InProgress { src: s, .. } if *s == src) {
+                *slot = Slot::Free;
+            }
+        }
+    }
+
+    fn start(slot: Slot) -> usize {
+        match slot {
+            Slot::InProgress {
+                received,
+                packet_size,
+                ..
+            } => {
+                let packets = MAX_SEQ_ID + 1 - received.leading_zeros() as usize;
+                SLOT_SIZE - packets * packet_size as usize
+            }
+            Slot::Ready { start, .. } => start as usize,
+            Slot::Free => SLOT_SIZE,
+        }
+    }
+}
+
+impl<const N: usize> Index<usize> for ReceivePool<N> {
+    type Output = [u8];
+
+    /// The `index`-th oldest message
+    fn index(&self, index: usize) -> &Self::Output {
+        let slot = self.ready.iter().nth(index).expect("no such message");
+        self.message(*slot as usize)
+    }
+}
@@ -0,0 +1,329 @@
+use crate::protocol::errors::{ProtocolError, RecordingError};
+use crate::protocol::extended::{CanFrame, MAX_EXTENDED_ID};
+use crate::protocol::protocol::Protocol;
+use crate::protocol::{CanId, CAN_PACKET_SIZE};
+use crate::Write;
+
+/// Every recording starts with these bytes
+pub const RECORDING_MAGIC: [u8; 4] = *b"CREC";
+/// Version 2 added the kind of the frame and the 29-bit id of the extended frames
+pub const RECORDING_VERSION: u8 = 2;
+pub const NODE_NAME_LEN: usize = 12;
+
+const NODE_INFO_SIZE: usize = 1 + NODE_NAME_LEN;
+/// timestamp (4) | node (1) | direction (1) | kind (1) | extended id (4) | data (8)
+const RECORD_SIZE: usize = 4 + 1 + 1 + 1 + 4 + CAN_PACKET_SIZE;
+const STANDARD_FRAME: u8 = 0;
+const EXTENDED_FRAME: u8 = 1;
+const RECORDING_HEADER_SIZE: usize = RECORDING_MAGIC.len() + 1 + 1;
+
+/// Metadata about a node present on the bus during the recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeInfo {
+    pub id: CanId,
+    name: [u8; NODE_NAME_LEN],
+}
+
+impl NodeInfo {
+    /// The name is truncated to `NODE_NAME_LEN` bytes
+    pub fn new(id: CanId, name: &str) -> NodeInfo {
+        let mut raw = [0u8; NODE_NAME_LEN];
+        let len = name.len().min(NODE_NAME_LEN);
+        raw[..len].copy_from_slice(&name.as_bytes()[..len]);
+        NodeInfo { id, name: raw }
+    }
+
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(NODE_NAME_LEN);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+}
+
+/// Whether the frame was received or sent by the recording node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Rx,
+    Tx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordedFrame {
+    /// Time at which the frame was seen, in microseconds since the start of the recording
+    pub timestamp: u32,
+    /// The node which recorded the frame
+    pub node: CanId,
+    pub direction: Direction,
+    pub frame: CanFrame,
+}
+
+impl From<&RecordedFrame> for [u8; RECORD_SIZE] {
+    fn from(frame: &RecordedFrame) -> Self {
+        let mut raw = [0u8; RECORD_SIZE];
+        raw[0..4].copy_from_slice(&frame.timestamp.to_le_bytes());
+        raw[4] = usize::from(frame.node) as u8;
+        raw[5] = match frame.direction {
+            Direction::Rx => 0,
+            Direction::Tx => 1,
+        };
+        let (kind, id, data) = match &frame.frame {
+            CanFrame::Standard(data) => (STANDARD_FRAME, 0, data),
+            CanFrame::Extended { id, data } => (EXTENDED_FRAME, *id, data),
+        };
+        raw[6] = kind;
+        raw[7..11].copy_from_slice(&id.to_le_bytes());
+        raw[11..].copy_from_slice(data);
+        raw
+    }
+}
+
+impl TryFrom<&[u8]> for RecordedFrame {
+    type Error = RecordingError;
+
+    fn try_from(raw: &[u8]) -> Result<Self, Self::Error> {
+        if raw.len() < RECORD_SIZE {
+            return Err(RecordingError::Truncated);
+        }
+        let direction = match raw[5] {
+            0 => Direction::Rx,
+            1 => Direction::Tx,
+            _ => return Err(RecordingError::Corrupted),
+        };
+        let mut data = [0u8; CAN_PACKET_SIZE];
+        data.copy_from_slice(&raw[11..RECORD_SIZE]);
+        // Can't panic because we checked the length above
+        let id = u32::from_le_bytes(raw[7..11].try_into().unwrap());
+        let frame = match raw[6] {
+            STANDARD_FRAME => CanFrame::Standard(data),
+            EXTENDED_FRAME if id <= MAX_EXTENDED_ID => CanFrame::Extended { id, data },
+            _ => return Err(RecordingError::Corrupted),
+        };
+        Ok(RecordedFrame {
+            // Can't panic because we checked the length above
+            timestamp: u32::from_le_bytes(raw[0..4].try_into().unwrap()),
+            node: CanId::new(raw[4] as usize).map_err(|_| RecordingError::Corrupted)?,
+            direction,
+            frame,
+        })
+    }
+}
+
+/// Writes a recording: a header with the nodes metadata followed by timestamped frames
+pub struct Recorder<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut writer: W, nodes: &[NodeInfo]) -> Result<Self, RecordingError> {
+        if nodes.len() > u8::MAX as usize {
+            return Err(RecordingError::TooManyNodes);
+        }
+        write_all(&mut writer, &RECORDING_MAGIC)?;
+        write_all(&mut writer, &[RECORDING_VERSION, nodes.len() as u8])?;
+        for node in nodes {
+            write_all(&mut writer, &[usize::from(node.id) as u8])?;
+            write_all(&mut writer, &node.name)?;
+        }
+        Ok(Recorder { writer })
+    }
+
+    pub fn record(&mut self, frame: &RecordedFrame) -> Result<(), RecordingError> {
+        let raw: [u8; RECORD_SIZE] = frame.into();
+        write_all(&mut self.writer, &raw)
+    }
+
+    pub fn flush(&mut self) -> Result<(), RecordingError> {
+        self.writer.flush().map_err(|_| RecordingError::WriteFailed)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn write_all<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), RecordingError> {
+    for b in bytes {
+        writer.write(*b).map_err(|_| RecordingError::WriteFailed)?;
+    }
+    Ok(())
+}
+
+/// A recording parsed from its binary representation, nothing is copied
+#[derive(Debug, Clone, Copy)]
+pub struct Recording<'a> {
+    nodes: &'a [u8],
+    frames: &'a [u8],
+}
+
+impl<'a> Recording<'a> {
+    pub fn parse(raw: &'a [u8]) -> Result<Self, RecordingError> {
+        if raw.len() < RECORDING_HEADER_SIZE {
+            return Err(RecordingError::Truncated);
+        }
+        if raw[..RECORDING_MAGIC.len()] != RECORDING_MAGIC {
+            return Err(RecordingError::BadMagic);
+        }
+        let version = raw[RECORDING_MAGIC.len()];
+        if version != RECORDING_VERSION {
+            return Err(RecordingError::UnsupportedVersion(version));
+        }
+        let nodes_end =
+            RECORDING_HEADER_SIZE + raw[RECORDING_MAGIC.len() + 1] as usize * NODE_INFO_SIZE;
+        if raw.len() < nodes_end || !(raw.len() - nodes_end).is_multiple_of(RECORD_SIZE) {
+            return Err(RecordingError::Truncated);
+        }
+        Ok(Recording {
+            nodes: &raw[RECORDING_HEADER_SIZE..nodes_end],
+            frames: &raw[nodes_end..],
+        })
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = Result<NodeInfo, RecordingError>> + 'a {
+        self.nodes.chunks_exact(NODE_INFO_SIZE).map(|raw| {
+            let mut name = [0u8; NODE_NAME_LEN];
+            name.copy_from_slice(&raw[1..]);
+            Ok(NodeInfo {
+                id: CanId::new(raw[0] as usize).map_err(|_| RecordingError::Corrupted)?,
+                name,
+            })
+        })
+    }
+
+    pub fn frames(&self) -> RecordedFrames<'a> {
+        RecordedFrames {
+            raw: self.frames.chunks_exact(RECORD_SIZE),
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len() / RECORD_SIZE
+    }
+}
+
+pub struct RecordedFrames<'a> {
+    raw: core::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> Iterator for RecordedFrames<'a> {
+    type Item = Result<RecordedFrame, RecordingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.next().map(RecordedFrame::try_from)
+    }
+}
+
+/// Anything that can be fed with frames coming from the bus
+pub trait FrameSink {
+    fn feed_frame(&mut self, frame: &CanFrame) -> Result<(), ProtocolError>;
+}
+
+impl<const SEND: usize, const RECEIVE: usize, const CONTROL: usize> FrameSink
+    for Protocol<SEND, RECEIVE, CONTROL>
+{
+    fn feed_frame(&mut self, frame: &CanFrame) -> Result<(), ProtocolError> {
+        self.process_frame(frame)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaySpeed {
+    /// Frames are released with the same delays as during the recording
+    Original,
+    /// Delays between frames are divided by the given factor
+    Accelerated(u32),
+    /// Every frame is released as soon as it is polled
+    Instant,
+}
+
+/// Feeds a recording back at the original or an accelerated speed.
+/// The replayer has no clock of its own, the caller gives the current time
+/// in microseconds to `poll`.
+pub struct Replayer<'a> {
+    frames: RecordedFrames<'a>,
+    speed: ReplaySpeed,
+    next: Option<RecordedFrame>,
+    /// (time of the first poll, timestamp of the first frame)
+    origin: Option<(u32, u32)>,
+}
+
+impl<'a> Replayer<'a> {
+    pub fn new(recording: &Recording<'a>, speed: ReplaySpeed) -> Replayer<'a> {
+        Replayer {
+            frames: recording.frames(),
+            speed,
+            next: None,
+            origin: None,
+        }
+    }
+
+    /// Returns the next frame if it is due at `now`
+    pub fn poll(&mut self, now: u32) -> Result<Option<RecordedFrame>, RecordingError> {
+        let frame = match self.next.take() {
+            Some(frame) => frame,
+            None => match self.frames.next() {
+                Some(frame) => frame?,
+                None => return Ok(None),
+            },
+        };
+        let (start, first_timestamp) = *self.origin.get_or_insert((now, frame.timestamp));
+        let recorded_delay = frame.timestamp.wrapping_sub(first_timestamp);
+        let due = match self.speed {
+            ReplaySpeed::Original => recorded_delay,
+            ReplaySpeed::Accelerated(factor) => recorded_delay / factor.max(1),
+            ReplaySpeed::Instant => 0,
+        };
+        if now.wrapping_sub(start) >= due {
+            Ok(Some(frame))
+        } else {
+            self.next = Some(frame);
+            Ok(None)
+        }
+    }
+
+    /// Feeds the received frames which are due at `now` to the sink, returns the number of
+    /// frames fed. The frames sent by the recording nodes are skipped, they were on the bus
+    /// as the frames received by another one.
+    pub fn replay_into<S: FrameSink>(
+        &mut self,
+        now: u32,
+        sink: &mut S,
+    ) -> Result<usize, RecordingError> {
+        self.replay(now, None, sink)
+    }
+
+    /// Same as `replay_into` with only the frames `node` received: when every node recorded
+    /// its own traffic, the sink sees the bus as `node` did
+    pub fn replay_node_into<S: FrameSink>(
+        &mut self,
+        now: u32,
+        node: CanId,
+        sink: &mut S,
+    ) -> Result<usize, RecordingError> {
+        self.replay(now, Some(node), sink)
+    }
+
+    fn replay<S: FrameSink>(
+        &mut self,
+        now: u32,
+        node: Option<CanId>,
+        sink: &mut S,
+    ) -> Result<usize, RecordingError> {
+        let mut count = 0;
+        while let Some(frame) = self.poll(now)? {
+            if frame.direction != Direction::Rx || node.is_some_and(|node| frame.node != node) {
+                continue;
+            }
+            sink.feed_frame(&frame.frame)
+                .map_err(RecordingError::Sink)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next.is_none() && self.frames.raw.len() == 0
+    }
+}
@@ -0,0 +1,146 @@
+//! Forwarding between the interfaces of a gateway (CAN buses, serial link...).
+//! The `CanId` space stays flat: every node id is unique across the buses and the gateway
+//! has a static table giving the interface behind which each node is.
+//!
+//! The gateway forwards the frames unchanged, ACKs included, so the acknowledgement stays
+//! end to end: the sender only gets its ACK once the destination has the packet. The
+//! `ack_timeout` of the nodes must account for the extra hops.
+//!
+//! A frame is only forwarded if its source is behind the interface it comes from, so a frame
+//! which made a loop, or spoofs a node of another bus, is dropped. The routes must form a
+//! tree: with two paths between buses, both gateways would forward the same frame.
+
+use crate::protocol::errors::RoutingError;
+use crate::protocol::extended::CanFrame;
+use crate::protocol::header::Header;
+use crate::protocol::packet::Packet;
+use crate::protocol::{CanId, DATAGRAM_MESSAGE_ID, MAX_NODE_ID};
+
+/// Index of an interface of the gateway
+pub type InterfaceId = u8;
+
+/// A gateway has at most 8 interfaces
+pub const MAX_INTERFACES: usize = 8;
+
+/// Set of interfaces a frame has to be sent on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Interfaces(u8);
+
+impl Interfaces {
+    pub fn none() -> Self {
+        Interfaces(0)
+    }
+
+    pub fn one(interface: InterfaceId) -> Self {
+        Interfaces(1 << interface)
+    }
+
+    pub fn contains(&self, interface: InterfaceId) -> bool {
+        (interface as usize) < MAX_INTERFACES && self.0 & (1 << interface) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = InterfaceId> + '_ {
+        (0..MAX_INTERFACES as InterfaceId).filter(move |interface| self.contains(*interface))
+    }
+}
+
+/// Static routing table of a gateway
+#[derive(Debug, Clone)]
+pub struct Router {
+    /// Id of the node of the gateway itself, its frames are never forwarded
+    pub host_id: CanId,
+    interfaces: u8,
+    routes: [Option<InterfaceId>; MAX_NODE_ID + 1],
+}
+
+impl Router {
+    /// A gateway with `interfaces` interfaces, numbered from 0
+    pub fn new(host_id: CanId, interfaces: u8) -> Result<Self, RoutingError> {
+        if interfaces == 0 || interfaces as usize > MAX_INTERFACES {
+            return Err(RoutingError::UnknownInterface(interfaces));
+        }
+        Ok(Router {
+            host_id,
+            interfaces,
+            routes: [None; MAX_NODE_ID + 1],
+        })
+    }
+
+    /// `node` is reached through `interface`, replaces its previous route
+    pub fn add_route(&mut self, node: CanId, interface: InterfaceId) -> Result<(), RoutingError> {
+        if node.is_multicast() || node == self.host_id {
+            return Err(RoutingError::NotRoutable(node));
+        }
+        if interface >= self.interfaces {
+            return Err(RoutingError::UnknownInterface(interface));
+        }
+        self.routes[usize::from(node)] = Some(interface);
+        Ok(())
+    }
+
+    pub fn route_to(&self, node: CanId) -> Option<InterfaceId> {
+        self.routes[usize::from(node)]
+    }
+
+    fn all(&self) -> Interfaces {
+        Interfaces(((1u16 << self.interfaces) - 1) as u8)
+    }
+
+    /// Interfaces on which a frame sent by the gateway itself to `dest` goes
+    pub fn interfaces_for(&self, dest: CanId) -> Result<Interfaces, RoutingError> {
+        if dest.is_multicast() {
+            Ok(self.all())
+        } else {
+            self.route_to(dest)
+                .map(Interfaces::one)
+                .ok_or(RoutingError::NoRoute(dest))
+        }
+    }
+
+    /// Interfaces on which a frame received on `from` is forwarded, none when its destination
+    /// is on the same bus or is the gateway. The gateway node still processes the frame.
+    pub fn route(&self, frame: &CanFrame, from: InterfaceId) -> Result<Interfaces, RoutingError> {
+        if from >= self.interfaces {
+            return Err(RoutingError::UnknownInterface(from));
+        }
+        let Header {
+            id_dest,
+            id_src,
+            is_ack,
+            id_message,
+            ..
+        } = Packet::try_from(frame)
+            .map_err(RoutingError::InvalidFrame)?
+            .header;
+        match self.route_to(id_src) {
+            Some(interface) if interface == from => {}
+            Some(_) => {
+                return Err(RoutingError::WrongInterface {
+                    src: id_src,
+                    interface: from,
+                })
+            }
+            None => return Err(RoutingError::UnknownSource(id_src)),
+        }
+
+        if id_dest.is_multicast() {
+            // Nobody answers a multicast frame, the nodes drop the others
+            if is_ack || usize::from(id_message) != DATAGRAM_MESSAGE_ID {
+                return Ok(Interfaces::none());
+            }
+            return Ok(Interfaces(self.all().0 & !Interfaces::one(from).0));
+        }
+        if id_dest == self.host_id {
+            return Ok(Interfaces::none());
+        }
+        match self.route_to(id_dest) {
+            Some(interface) if interface == from => Ok(Interfaces::none()),
+            Some(interface) => Ok(Interfaces::one(interface)),
+            None => Err(RoutingError::NoRoute(id_dest)),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::protocol::codec::{BitField, ProtocolMessage, WireFormat};
+use crate::protocol::errors::CodecError;
+use crate::protocol::header::Header;
+use crate::protocol::{CanId, MessageId, SeqId, MAX_MESSAGE_LEN};
+
+/// Same layout as `Header`
+#[derive(ProtocolMessage, Debug, PartialEq)]
+struct PackedHeader {
+    #[protocol(bits = 4)]
+    dest: u8,
+    #[protocol(bits = 4)]
+    src: u8,
+    #[protocol(bits = 3)]
+    message: u8,
+    #[protocol(bits = 4)]
+    seq: u8,
+    #[protocol(bits = 1)]
+    ack: bool,
+}
+
+#[derive(ProtocolMessage, Debug, PartialEq, Clone, Copy, Default)]
+enum Mode {
+    #[default]
+    Idle,
+    Moving,
+    Blocked = 5,
+}
+
+#[derive(ProtocolMessage, Debug, PartialEq)]
+#[protocol(command = 0x20)]
+struct SetMode {
+    #[protocol(bits = 4)]
+    motor: u8,
+    #[protocol(bits = 4)]
+    mode: Mode,
+    speed: i16,
+}
+
+#[derive(ProtocolMessage, Debug, PartialEq)]
+#[protocol(command = 0x21)]
+enum Order {
+    Stop,
+    Goto { x: i16, y: i16 },
+    Turn(f32),
+}
+
+#[derive(ProtocolMessage, Debug, PartialEq)]
+#[protocol(command = 0x22)]
+struct Ping;
+
+#[derive(ProtocolMessage, Debug, PartialEq)]
+#[protocol(command = 0x23)]
+struct Positions([u16; 4], bool);
+
+#[test]
+fn bit_fields_are_packed_like_the_header() {
+    let header = Header::new(
+        CanId::new(3).unwrap(),
+        CanId::new(9).unwrap(),
+        true,
+        MessageId::new(5).unwrap(),
+        SeqId::new(11).unwrap(),
+    )
+    .unwrap();
+    let packed = PackedHeader {
+        dest: 3,
+        src: 9,
+        message: 5,
+        seq: 11,
+        ack: true,
+    };
+    let expected: [u8; 2] = (&header).try_into().unwrap();
+
+    let mut buf = [0u8; 2];
+    packed.write(&mut buf).unwrap();
+    assert_eq!(PackedHeader::SIZE, 2);
+    assert_eq!(buf, expected);
+    assert_eq!(PackedHeader::read(&buf), Ok(packed));
+}
+
+#[test]
+fn message_starts_with_its_command() {
+    let message = SetMode {
+        motor: 2,
+        mode: Mode::Blocked,
+        speed: -2,
+    };
+    let data = message.encode().unwrap();
+    assert_eq!(&data[..], &[0x20, 0x25, 0xFE, 0xFF]);
+    assert_eq!(SetMode::decode_message(&data), Ok(message));
+    assert_eq!(SetMode::decode(&data[1..3]), Err(CodecError::TooShort));
+    assert_eq!(
+        SetMode::decode_message(&[0x21, 0, 0, 0]),
+        Err(CodecError::WrongCommand(0x21))
+    );
+}
+
+#[test]
+fn padding_after_the_fields_is_ignored() {
+    let decoded = SetMode::decode(&[0x10, 1, 0, 0, 0, 0]);
+    assert_eq!(
+        decoded,
+        Ok(SetMode {
+            motor: 1,
+            mode: Mode::Idle,
+            speed: 1
+        })
+    );
+}
+
+#[test]
+fn value_too_large_for_its_bits_is_refused() {
+    let message = SetMode {
+        motor: 16,
+        mode: Mode::Idle,
+        speed: 0,
+    };
+    assert_eq!(message.encode(), Err(CodecError::ValueTooLarge));
+}
+
+#[test]
+fn fieldless_enum_is_a_bit_field() {
+    assert_eq!(Mode::Moving.to_bits(), 1);
+    assert_eq!(Mode::from_bits(5), Ok(Mode::Blocked));
+    assert_eq!(Mode::from_bits(2), Err(CodecError::InvalidTag(2)));
+    assert_eq!(Mode::from_bits(0x105), Err(CodecError::ValueTooLarge));
+    assert_eq!(
+        SetMode::decode(&[0x12, 0, 0]),
+        Err(CodecError::InvalidTag(2))
+    );
+}
+
+#[test]
+fn enum_variants_are_padded_to_the_largest() {
+    assert_eq!(Order::SIZE, 5);
+    assert_eq!(&Order::Stop.encode().unwrap()[..], &[0x21, 0, 0, 0, 0, 0]);
+    let goto = Order::Goto { x: 1, y: -1 };
+    let data = goto.encode().unwrap();
+    assert_eq!(&data[..], &[0x21, 1, 1, 0, 0xFF, 0xFF]);
+    assert_eq!(Order::decode_message(&data), Ok(goto));
+    let turn = Order::Turn(1.5);
+    assert_eq!(Order::decode_message(&turn.encode().unwrap()), Ok(turn));
+    assert_eq!(
+        Order::decode(&[7, 0, 0, 0, 0]),
+        Err(CodecError::InvalidTag(7))
+    );
+}
+
+#[test]
+fn unit_struct_is_only_its_command() {
+    assert_eq!(&Ping.encode().unwrap()[..], &[0x22]);
+    assert_eq!(Ping::decode_message(&[0x22]), Ok(Ping));
+}
+
+#[test]
+fn arrays_are_written_in_order() {
+    let message = Positions([1, 2, 0x300, 4], true);
+    let data = message.encode().unwrap();
+    assert_eq!(&data[..], &[0x23, 1, 0, 2, 0, 0, 3, 4, 0, 1]);
+    assert_eq!(Positions::decode_message(&data), Ok(message));
+    assert_eq!(
+        Positions::decode(&[0, 0, 0, 0, 0, 0, 0, 0, 2]),
+        Err(CodecError::InvalidTag(2))
+    );
+}
+
+#[test]
+fn largest_message_fits() {
+    #[derive(ProtocolMessage)]
+    #[protocol(command = 0x24)]
+    struct Largest([u8; MAX_MESSAGE_LEN - 1]);
+
+    assert_eq!(
+        Largest([0; MAX_MESSAGE_LEN - 1]).encode().unwrap().len(),
+        MAX_MESSAGE_LEN
+    );
+}
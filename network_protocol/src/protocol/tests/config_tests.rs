@@ -0,0 +1,119 @@
+use crate::protocol::errors::{ProtocolError, SendError, SendFailure};
+use crate::protocol::message::Message;
+use crate::protocol::packet::Packet;
+use crate::protocol::protocol::Protocol;
+use crate::protocol::{CanId, MessageId, SeqId, DEFAULT_ACK_TIMEOUT, DEFAULT_MAX_RETRIES};
+
+fn id(v: usize) -> CanId {
+    CanId::new(v).unwrap()
+}
+
+fn message(id_message: usize) -> Message {
+    Message::new(MessageId::new(id_message).unwrap(), id(2), id(1), &[1; 12]).unwrap()
+}
+
+fn next_seq(protocol: &mut Protocol) -> Option<SeqId> {
+    let raw = protocol.get_next_packet_to_send().unwrap()?;
+    Some(Packet::try_from(&raw).unwrap().header.seq_number)
+}
+
+#[test]
+fn packet_is_sent_again_once_its_ack_is_late() {
+    let mut protocol: Protocol = Protocol::builder(id(1)).ack_timeout(1_000).build().unwrap();
+    protocol.add_message_to_send_buff(message(0)).unwrap();
+    protocol.poll(5_000);
+    assert_eq!(next_seq(&mut protocol), Some(SeqId::new(1).unwrap()));
+    assert_eq!(next_seq(&mut protocol), None);
+
+    protocol.poll(5_999);
+    assert_eq!(next_seq(&mut protocol), None);
+    protocol.poll(6_000);
+    assert_eq!(next_seq(&mut protocol), Some(SeqId::new(1).unwrap()));
+    assert_eq!(protocol.send_buff[0].retries(), 1);
+}
+
+#[test]
+fn message_is_given_up_after_its_retries() {
+    let mut protocol: Protocol = Protocol::builder(id(1))
+        .ack_timeout(100)
+        .max_retries(2)
+        .build()
+        .unwrap();
+    protocol.add_message_to_send_buff(message(0)).unwrap();
+    assert!(next_seq(&mut protocol).is_some());
+    for now in [100, 200] {
+        protocol.poll(now);
+        assert!(next_seq(&mut protocol).is_some());
+    }
+    protocol.poll(250);
+    assert_eq!(next_seq(&mut protocol), None);
+    assert!(protocol.send_errors.is_empty());
+
+    protocol.poll(300);
+    assert!(protocol.send_buff.is_empty());
+    assert_eq!(
+        protocol.send_errors,
+        [SendFailure {
+            id_dest: id(2),
+            id: MessageId::new(0).unwrap(),
+            error: SendError::DidntReceiveACK,
+        }]
+    );
+}
+
+#[test]
+fn default_lifetime_starts_when_the_message_is_added() {
+    let mut protocol: Protocol = Protocol::builder(id(1))
+        .default_lifetime(1_000)
+        .build()
+        .unwrap();
+    protocol.poll(500);
+    protocol.add_message_to_send_buff(message(0)).unwrap();
+    protocol
+        .add_message_to_send_buff(message(1).with_deadline(10_000))
+        .unwrap();
+    assert_eq!(protocol.send_buff[0].deadline(), Some(1_500));
+
+    protocol.poll(1_500);
+    assert_eq!(protocol.send_buff.len(), 1);
+    assert_eq!(protocol.send_buff[0].id, MessageId::new(1).unwrap());
+}
+
+#[test]
+fn small_node_has_small_queues() {
+    let mut protocol = Protocol::<2, 1, 4>::builder(id(1))
+        .max_queued_messages(2)
+        .build()
+        .unwrap();
+    protocol.add_message_to_send_buff(message(0)).unwrap();
+    protocol.add_message_to_send_buff(message(1)).unwrap();
+    assert_eq!(
+        protocol.add_message_to_send_buff(message(2)),
+        Err(ProtocolError::SendFailed(SendError::QueueFull))
+    );
+
+    // the default allows more queued messages than there is room for
+    assert!(matches!(
+        Protocol::<2, 1, 4>::builder(id(1)).build(),
+        Err(ProtocolError::InvalidConfig)
+    ));
+}
+
+#[test]
+fn default_config_gives_up_on_a_silent_node() {
+    let mut protocol = Protocol::new(id(1)).unwrap();
+    protocol.add_message_to_send_buff(message(0)).unwrap();
+    assert!(next_seq(&mut protocol).is_some());
+    protocol.poll(DEFAULT_ACK_TIMEOUT - 1);
+    assert_eq!(next_seq(&mut protocol), None);
+
+    let mut now = 0;
+    for _ in 0..DEFAULT_MAX_RETRIES {
+        now += DEFAULT_ACK_TIMEOUT;
+        protocol.poll(now);
+        assert!(next_seq(&mut protocol).is_some());
+    }
+    protocol.poll(now + DEFAULT_ACK_TIMEOUT);
+    assert!(protocol.send_buff.is_empty());
+    assert_eq!(protocol.send_errors[0].error, SendError::DidntReceiveACK);
+}
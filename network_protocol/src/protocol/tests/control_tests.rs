@@ -0,0 +1,96 @@
+use crate::protocol::control::{ControlFrame, NackReason};
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::header::Header;
+use crate::protocol::packet::Packet;
+use crate::protocol::{CanId, MessageId, SeqId};
+
+fn answer(control: ControlFrame) -> Packet {
+    let header = Header::new(
+        CanId::new(3).unwrap(),
+        CanId::new(1).unwrap(),
+        true,
+        MessageId::new(2).unwrap(),
+        SeqId::new(0).unwrap(),
+    )
+    .unwrap();
+    Packet::new(header, control.into())
+}
+
+#[test]
+fn plain_ack_has_an_empty_payload() {
+    let payload: [u8; 6] = ControlFrame::Ack.into();
+    assert_eq!(payload, [0; 6]);
+    assert_eq!(ControlFrame::try_from(&payload), Ok(ControlFrame::Ack));
+}
+
+#[test]
+fn epoch_and_version_frames_round_trip() {
+    for control in [
+        ControlFrame::EpochAnnounce(0x1234),
+        ControlFrame::EpochAck(0xFFFF),
+        ControlFrame::Version(1),
+    ] {
+        let payload: [u8; 6] = control.into();
+        assert_eq!(ControlFrame::try_from(&payload), Ok(control));
+    }
+}
+
+#[test]
+fn unknown_control_frame() {
+    assert_eq!(
+        ControlFrame::try_from(&[0x42, 0, 0, 0, 0, 0]),
+        Err(ProtocolError::InvalidControlFrame(0x42))
+    );
+}
+
+#[test]
+fn nack_frames_round_trip() {
+    for reason in [
+        NackReason::BufferFull,
+        NackReason::UnknownCommand,
+        NackReason::MalformedPayload,
+        NackReason::Corrupted,
+    ] {
+        let payload: [u8; 6] = ControlFrame::Nack(reason).into();
+        assert_eq!(payload[0], 3);
+        assert_eq!(
+            ControlFrame::try_from(&payload),
+            Ok(ControlFrame::Nack(reason))
+        );
+    }
+    assert_eq!(
+        ControlFrame::try_from(&[3, 0x42, 0, 0, 0, 0]),
+        Err(ProtocolError::InvalidNackReason(0x42))
+    );
+}
+
+#[test]
+fn ack_can_not_contain_data() {
+    assert_eq!(
+        ControlFrame::try_from(&[0, 0, 0, 0, 0, 1]),
+        Err(ProtocolError::ACKCanNotContainData)
+    );
+}
+
+#[test]
+fn plain_acks_go_to_their_destination() {
+    let raw: [u8; 8] = answer(ControlFrame::Ack).try_into().unwrap();
+    assert_eq!(raw, [0x31, 0x41, 0, 0, 0, 0, 0, 0]);
+}
+
+/// A legacy node takes every frame with the ACK bit sent to its id for an ACK
+#[test]
+fn other_control_frames_are_sent_to_the_broadcast_id() {
+    for control in [
+        ControlFrame::EpochAnnounce(0x1234),
+        ControlFrame::Nack(NackReason::BufferFull),
+        ControlFrame::Version(1),
+    ] {
+        let raw: [u8; 8] = answer(control).try_into().unwrap();
+        assert_eq!(raw[0] >> 4, 15);
+        assert_eq!(raw[7], 3);
+        let packet = Packet::try_from(&raw).unwrap();
+        assert_eq!(packet.header, answer(control).header);
+        assert_eq!(ControlFrame::try_from(&packet), Ok(control));
+    }
+}
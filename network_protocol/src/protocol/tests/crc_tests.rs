@@ -0,0 +1,51 @@
+use crate::protocol::crc::{check_crc, crc16};
+use crate::protocol::message::Message;
+use crate::protocol::{CanId, MessageId, SeqId};
+
+#[test]
+fn crc16_check_value() {
+    assert_eq!(crc16(b"123456789"), 0x29B1);
+    assert_eq!(crc16(&[]), 0xFFFF);
+}
+
+#[test]
+fn check_crc_detects_a_flipped_bit() {
+    let mut data = [1, 2, 3, 4, 0, 0];
+    data[4..].copy_from_slice(&crc16(&[1, 2, 3, 4]).to_be_bytes());
+    assert!(check_crc(&data));
+    data[1] ^= 0x10;
+    assert!(!check_crc(&data));
+    assert!(!check_crc(&[0]));
+}
+
+#[test]
+fn crc_ends_the_last_packet() {
+    let id = |v| CanId::new(v).unwrap();
+    let mut message = Message::new(MessageId::new(0).unwrap(), id(2), id(1), &[7; 5])
+        .unwrap()
+        .with_crc()
+        .unwrap();
+    assert!(message.has_crc());
+
+    // 5 bytes of data and 2 of CRC need a second packet
+    let first = message.get_next_packet_to_send().unwrap().unwrap();
+    assert_eq!(first.header.seq_number, SeqId::new(1).unwrap());
+    assert_eq!(first.payload, [7, 7, 7, 7, 7, 0]);
+    message.mark_ack_as_received(SeqId::new(1).unwrap());
+    let last = message.get_next_packet_to_send().unwrap().unwrap();
+    let mut expected = [0u8; 12];
+    expected[..5].copy_from_slice(&[7; 5]);
+    let crc = crc16(&expected[..10]).to_be_bytes();
+    assert_eq!(last.payload, [0, 0, 0, 0, crc[0], crc[1]]);
+}
+
+#[test]
+fn crc_does_not_fit() {
+    let id = |v| CanId::new(v).unwrap();
+    assert!(
+        Message::new(MessageId::new(0).unwrap(), id(2), id(1), &[0; 89])
+            .unwrap()
+            .with_crc()
+            .is_err()
+    );
+}
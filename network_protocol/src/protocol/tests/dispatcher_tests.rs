@@ -0,0 +1,169 @@
+use crate::protocol::control::NackReason;
+use crate::protocol::dispatcher::{Dispatcher, Reply, ERROR_REPLY, REPLY_FLAG};
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::message::Message;
+use crate::protocol::protocol::Protocol;
+use crate::protocol::{CanId, MessageId};
+
+fn id(v: usize) -> CanId {
+    CanId::new(v).unwrap()
+}
+
+/// Sends everything `from` has to `to` and the answers back, one frame at a time
+fn deliver(from: &mut Protocol, to: &mut Protocol) {
+    for _ in 0..64 {
+        let mut idle = true;
+        if let Some(frame) = from.get_next_packet_to_send().unwrap() {
+            to.process_raw_packet(frame).unwrap();
+            idle = false;
+        }
+        if let Some(frame) = to.get_next_packet_to_send().unwrap() {
+            from.process_raw_packet(frame).unwrap();
+            idle = false;
+        }
+        if idle {
+            return;
+        }
+    }
+    panic!("the nodes never stop talking");
+}
+
+fn send(from: &mut Protocol, to: &mut Protocol, id_message: usize, data: &[u8]) {
+    let message = Message::new(
+        MessageId::new(id_message).unwrap(),
+        to.host_id,
+        from.host_id,
+        data,
+    )
+    .unwrap();
+    from.add_message_to_send_buff(message).unwrap();
+    deliver(from, to);
+}
+
+fn double(total: &mut u32, _: CanId, payload: &[u8], reply: &mut Reply) -> Result<(), NackReason> {
+    let value = *payload.first().ok_or(NackReason::MalformedPayload)?;
+    *total += u32::from(value);
+    reply.extend_from_slice(&[value * 2]).unwrap();
+    Ok(())
+}
+
+fn refuse(_: &mut u32, _: CanId, _: &[u8], _: &mut Reply) -> Result<(), NackReason> {
+    Err(NackReason::MalformedPayload)
+}
+
+#[test]
+fn handler_gets_the_payload_and_answers() {
+    let mut requester = Protocol::new(id(1)).unwrap();
+    let mut node = Protocol::new(id(2)).unwrap();
+    let mut dispatcher: Dispatcher<u32, 4> = Dispatcher::new();
+    dispatcher.register(3, double).unwrap();
+    let mut total = 0;
+
+    send(&mut requester, &mut node, 0, &[3, 21]);
+    assert_eq!(dispatcher.dispatch(&mut node, &mut total), Ok(1));
+    assert_eq!(total, 21);
+    assert!(node.received.is_empty());
+
+    deliver(&mut node, &mut requester);
+    assert_eq!(requester.received.len(), 1);
+    assert_eq!(requester.received[0][..2], [3 | REPLY_FLAG, 42]);
+}
+
+#[test]
+fn unknown_and_refused_commands_get_an_error_reply() {
+    let mut requester = Protocol::new(id(1)).unwrap();
+    let mut node = Protocol::new(id(2)).unwrap();
+    let mut dispatcher: Dispatcher<u32, 4> = Dispatcher::new();
+    dispatcher.register(5, refuse).unwrap();
+
+    send(&mut requester, &mut node, 0, &[4]);
+    send(&mut requester, &mut node, 1, &[5]);
+    assert_eq!(dispatcher.dispatch(&mut node, &mut 0), Ok(2));
+    deliver(&mut node, &mut requester);
+
+    let replies: heapless::Vec<&[u8], 2> = requester.received.iter().map(|r| &r[..3]).collect();
+    assert_eq!(
+        replies,
+        [
+            &[ERROR_REPLY, 4, NackReason::UnknownCommand.into()][..],
+            &[ERROR_REPLY, 5, NackReason::MalformedPayload.into()][..],
+        ]
+    );
+}
+
+#[test]
+fn replies_and_datagrams_are_not_answered() {
+    let mut requester = Protocol::new(id(1)).unwrap();
+    let mut node = Protocol::new(id(2)).unwrap();
+    let mut dispatcher: Dispatcher<u32, 4> = Dispatcher::new();
+    dispatcher.register(3, double).unwrap();
+    dispatcher.register(3 | REPLY_FLAG, double).unwrap();
+
+    send(&mut requester, &mut node, 0, &[3 | REPLY_FLAG, 1]);
+    send(&mut requester, &mut node, 1, &[ERROR_REPLY, 9, 2]);
+    requester
+        .add_message_to_send_buff(Message::datagram(id(2), id(1), &[3, 1]).unwrap())
+        .unwrap();
+    deliver(&mut requester, &mut node);
+
+    let mut total = 0;
+    assert_eq!(dispatcher.dispatch(&mut node, &mut total), Ok(3));
+    assert_eq!(total, 2);
+    assert!(node.send_buff.is_empty());
+}
+
+#[test]
+fn handlers_are_replaced_and_limited() {
+    let mut dispatcher: Dispatcher<u32, 1> = Dispatcher::new();
+    dispatcher.register(3, refuse).unwrap();
+    dispatcher.register(3, double).unwrap();
+    assert_eq!(
+        dispatcher.register(4, double),
+        Err(ProtocolError::TooManyHandlers)
+    );
+}
+
+#[test]
+fn request_waits_for_room_for_its_reply() {
+    let mut requester = Protocol::new(id(1)).unwrap();
+    let mut node = Protocol::builder(id(2))
+        .max_queued_messages(1)
+        .build()
+        .unwrap();
+    let mut dispatcher: Dispatcher<u32, 4> = Dispatcher::new();
+    dispatcher.register(3, double).unwrap();
+    let mut total = 0;
+
+    send(&mut requester, &mut node, 0, &[3, 1]);
+    send(&mut requester, &mut node, 1, &[3, 2]);
+    assert_eq!(dispatcher.dispatch(&mut node, &mut total), Ok(1));
+    assert_eq!(total, 1);
+    assert_eq!(node.received.len(), 1);
+
+    deliver(&mut node, &mut requester);
+    assert_eq!(dispatcher.dispatch(&mut node, &mut total), Ok(1));
+    assert_eq!(total, 3);
+    deliver(&mut node, &mut requester);
+    let replies: heapless::Vec<&[u8], 2> = requester.received.iter().map(|r| &r[..2]).collect();
+    assert_eq!(
+        replies,
+        [&[3 | REPLY_FLAG, 2][..], &[3 | REPLY_FLAG, 4][..]]
+    );
+}
+
+#[test]
+fn replies_take_their_ids_from_the_protocol() {
+    let mut requester = Protocol::new(id(1)).unwrap();
+    let mut node = Protocol::new(id(2)).unwrap();
+    let mut dispatcher: Dispatcher<u32, 4> = Dispatcher::new();
+    dispatcher.register(3, double).unwrap();
+    node.add_message_to_send_buff(
+        Message::new(MessageId::new(0).unwrap(), id(1), id(2), &[9]).unwrap(),
+    )
+    .unwrap();
+
+    send(&mut requester, &mut node, 0, &[3, 1]);
+    assert_eq!(dispatcher.dispatch(&mut node, &mut 0), Ok(1));
+    assert_eq!(node.send_buff.len(), 1);
+    assert_eq!(node.send_buff[0].id, MessageId::new(1).unwrap());
+}
@@ -0,0 +1,87 @@
+use crate::protocol::control::ControlFrame;
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::extended::{CanFrame, PROTOCOL_VERSION_EXTENDED};
+use crate::protocol::header::Header;
+use crate::protocol::packet::Packet;
+use crate::protocol::{CanId, MessageId, SeqId};
+
+fn header(dest: usize, src: usize, is_ack: bool, id_message: usize, seq: usize) -> Header {
+    Header::new(
+        CanId::new(dest).unwrap(),
+        CanId::new(src).unwrap(),
+        is_ack,
+        MessageId::new(id_message).unwrap(),
+        SeqId::new(seq).unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn extended_header_layout() {
+    let id: u32 = (&header(200, 17, true, 30, 11)).into();
+    assert_eq!(id >> 27, PROTOCOL_VERSION_EXTENDED as u32);
+    assert_eq!((id >> 19) & 0xFF, 200);
+    assert_eq!((id >> 11) & 0xFF, 17);
+    assert_eq!((id >> 6) & 0x1F, 30);
+    assert_eq!((id >> 1) & 0x1F, 11);
+    assert_eq!(id & 1, 1);
+    assert!(id < 1 << 29);
+    assert_eq!(Header::try_from(id), Ok(header(200, 17, true, 30, 11)));
+}
+
+#[test]
+fn unknown_version_is_rejected() {
+    let id: u32 = (&header(2, 1, true, 0, 0)).into();
+    let next_version = (id & !(0b11 << 27)) | (2 << 27);
+    assert_eq!(
+        Header::try_from(next_version),
+        Err(ProtocolError::UnsupportedVersion(2))
+    );
+    assert_eq!(
+        Header::try_from(1u32 << 29),
+        Err(ProtocolError::InvalidId(1 << 29))
+    );
+}
+
+#[test]
+fn sequence_numbers_stop_at_the_longest_message() {
+    let id: u32 = (&header(2, 1, false, 0, 0)).into();
+    assert_eq!(
+        Header::try_from(id | (21 << 1)),
+        Err(ProtocolError::InvalidId(21))
+    );
+}
+
+#[test]
+fn wide_fields_do_not_fit_in_a_standard_frame() {
+    let wide_dest: Result<[u8; 2], _> = (&header(40, 1, false, 0, 0)).try_into();
+    assert_eq!(wide_dest, Err(ProtocolError::InvalidId(40)));
+    let wide_message: Result<[u8; 2], _> = (&header(2, 1, false, 12, 0)).try_into();
+    assert_eq!(wide_message, Err(ProtocolError::InvalidId(12)));
+    assert!(header(2, 1, false, 6, 9).fits_standard());
+}
+
+#[test]
+fn data_packets_use_the_whole_frame() {
+    let packet = Packet::extended(header(3, 40, false, 6, 9), [1, 2, 3, 4, 5, 6, 7, 8]);
+    let frame = CanFrame::extended(&packet);
+    match frame {
+        CanFrame::Extended { data, .. } => assert_eq!(data, [1, 2, 3, 4, 5, 6, 7, 8]),
+        CanFrame::Standard(_) => panic!("expected an extended frame"),
+    }
+    let decoded = Packet::try_from(&frame).unwrap();
+    assert_eq!(decoded.header, packet.header);
+    assert_eq!(decoded.payload, packet.payload);
+    let raw: Result<[u8; 8], _> = (&packet).try_into();
+    assert_eq!(raw, Err(ProtocolError::ExtendedFrameOnly));
+}
+
+#[test]
+fn control_frames_keep_their_payload() {
+    let control = ControlFrame::EpochAnnounce(0x1234);
+    let packet = Packet::new(header(200, 1, true, 0, 0), control.into());
+    let decoded = Packet::try_from(&CanFrame::extended(&packet)).unwrap();
+    assert_eq!(decoded.header, packet.header);
+    assert_eq!(decoded.payload, packet.payload);
+    assert_eq!(ControlFrame::try_from(&decoded), Ok(control));
+}
@@ -0,0 +1,74 @@
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::header::Header;
+use crate::protocol::{CanId, MessageId, SeqId, BROADCAST_ID, DATAGRAM_MESSAGE_ID};
+
+fn id(v: usize) -> CanId {
+    CanId::new(v).unwrap()
+}
+
+fn datagram_id() -> MessageId {
+    MessageId::new(DATAGRAM_MESSAGE_ID).unwrap()
+}
+
+#[test]
+fn header_round_trip() {
+    let header = Header::new(
+        id(3),
+        id(9),
+        true,
+        MessageId::new(5).unwrap(),
+        SeqId::new(11).unwrap(),
+    )
+    .unwrap();
+    let raw: [u8; 2] = (&header).try_into().unwrap();
+    // dest | src, then message id (3 bits) | seq (4 bits) | ack
+    assert_eq!(raw, [0x39, 0b1011_0111]);
+    let mut frame = [0u8; 8];
+    frame[..2].copy_from_slice(&raw);
+    assert_eq!(Header::try_from(&frame), Ok(header));
+}
+
+#[test]
+fn broadcast_header_round_trip() {
+    let header = Header::new(
+        id(BROADCAST_ID),
+        id(1),
+        false,
+        datagram_id(),
+        SeqId::new(0).unwrap(),
+    )
+    .unwrap();
+    assert!(header.id_dest.is_broadcast());
+    let raw: [u8; 2] = (&header).try_into().unwrap();
+    assert_eq!(raw[0], 0xF1);
+    let mut frame = [0u8; 8];
+    frame[..2].copy_from_slice(&raw);
+    assert_eq!(Header::try_from(&frame), Ok(header));
+}
+
+#[test]
+fn multicast_ids() {
+    assert!(!id(11).is_multicast());
+    assert!(id(12).is_group());
+    assert!(id(14).is_group());
+    assert!(!id(15).is_group());
+    assert!(id(15).is_broadcast());
+}
+
+#[test]
+fn multicast_is_only_for_datagrams() {
+    let seq = SeqId::new(0).unwrap();
+    assert_eq!(
+        Header::new(id(13), id(1), false, MessageId::new(0).unwrap(), seq),
+        Err(ProtocolError::MulticastMustBeDatagram)
+    );
+    assert_eq!(
+        Header::new(id(BROADCAST_ID), id(1), true, datagram_id(), seq),
+        Err(ProtocolError::MulticastMustBeDatagram)
+    );
+    assert_eq!(
+        Header::new(id(1), id(BROADCAST_ID), false, datagram_id(), seq),
+        Err(ProtocolError::MulticastSource)
+    );
+    assert!(Header::new(id(13), id(1), false, datagram_id(), seq).is_ok());
+}
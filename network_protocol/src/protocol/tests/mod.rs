@@ -0,0 +1,11 @@
+mod codec_tests;
+mod config_tests;
+mod control_tests;
+mod crc_tests;
+mod dispatcher_tests;
+mod extended_tests;
+mod header_tests;
+mod protocol_tests;
+mod receive_tests;
+mod recording_tests;
+mod router_tests;
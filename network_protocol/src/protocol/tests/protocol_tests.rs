@@ -0,0 +1,731 @@
+use crate::protocol::control::{ControlFrame, NackReason};
+use crate::protocol::errors::{ProtocolError, SendError, SendFailure};
+use crate::protocol::header::Header;
+use crate::protocol::message::Message;
+use crate::protocol::packet::Packet;
+use crate::protocol::protocol::Protocol;
+use crate::protocol::receive::FrameQueue;
+use crate::protocol::{CanId, MessageId, SeqId, BROADCAST_ID, DATAGRAM_MESSAGE_ID, MAX_PEERS};
+
+fn id(v: usize) -> CanId {
+    CanId::new(v).unwrap()
+}
+
+fn data_packet(src: usize, dest: usize, id_message: usize, seq: usize) -> [u8; 8] {
+    let header = Header::new(
+        id(dest),
+        id(src),
+        false,
+        MessageId::new(id_message).unwrap(),
+        SeqId::new(seq).unwrap(),
+    )
+    .unwrap();
+    Packet::new(header, [seq as u8; 6]).try_into().unwrap()
+}
+
+fn control_packet(src: usize, dest: usize, control: ControlFrame) -> [u8; 8] {
+    let header = Header::new(
+        id(dest),
+        id(src),
+        true,
+        MessageId::new(0).unwrap(),
+        SeqId::new(0).unwrap(),
+    )
+    .unwrap();
+    Packet::new(header, control.into()).try_into().unwrap()
+}
+
+/// The answer of `src` to `packet`
+fn control_packet_for(src: usize, dest: usize, packet: &Packet, control: ControlFrame) -> [u8; 8] {
+    let header = Header::new(
+        id(dest),
+        id(src),
+        true,
+        packet.header.id_message,
+        packet.header.seq_number,
+    )
+    .unwrap();
+    Packet::new(header, control.into()).try_into().unwrap()
+}
+
+fn sent_controls(protocol: &mut Protocol) -> heapless::Vec<ControlFrame, 16> {
+    let mut res = heapless::Vec::new();
+    // the control frames always go before the data
+    while !protocol.acks_to_send.is_empty() {
+        let raw = protocol.get_next_packet_to_send().unwrap().unwrap();
+        let packet = Packet::try_from(&raw).unwrap();
+        res.push(ControlFrame::try_from(&packet).unwrap()).unwrap();
+    }
+    res
+}
+
+#[test]
+fn packets_are_reassembled_in_sequence_order() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 1))
+        .unwrap();
+    // retransmission of a packet we already have
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 1))
+        .unwrap();
+    assert!(protocol.received.is_empty());
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 2))
+        .unwrap();
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 0))
+        .unwrap();
+
+    assert_eq!(protocol.received.len(), 1);
+    assert_eq!(
+        protocol.received[0],
+        [2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(protocol.received.in_progress().count(), 0);
+}
+
+#[test]
+fn last_packet_sent_again_is_acked_but_not_delivered() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 1))
+        .unwrap();
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 0))
+        .unwrap();
+    protocol.received.pop_front();
+    sent_controls(&mut protocol);
+
+    // Its ACK was lost
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 0))
+        .unwrap();
+    assert!(protocol.received.is_empty());
+    assert_eq!(protocol.received.in_progress().count(), 0);
+    assert_eq!(sent_controls(&mut protocol), [ControlFrame::Ack]);
+}
+
+#[test]
+fn same_message_id_from_two_sources_is_not_mixed() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 1))
+        .unwrap();
+    protocol
+        .process_raw_packet(data_packet(4, 2, 3, 0))
+        .unwrap();
+    assert_eq!(protocol.received.len(), 1);
+    assert_eq!(protocol.received.in_progress().count(), 1);
+}
+
+#[test]
+fn announce_is_sent_to_every_peer() {
+    let mut protocol = Protocol::new(id(1)).unwrap();
+    protocol.announce_epoch(7, &[id(2), id(3)]).unwrap();
+    assert_eq!(
+        sent_controls(&mut protocol),
+        [
+            ControlFrame::EpochAnnounce(7),
+            ControlFrame::EpochAnnounce(7)
+        ]
+    );
+    assert_eq!(protocol.pending_epoch_acks, [id(2), id(3)]);
+
+    protocol
+        .process_raw_packet(control_packet(2, 1, ControlFrame::EpochAck(7)))
+        .unwrap();
+    assert_eq!(protocol.pending_epoch_acks, [id(3)]);
+    // an ACK for an older epoch is ignored
+    protocol
+        .process_raw_packet(control_packet(3, 1, ControlFrame::EpochAck(6)))
+        .unwrap();
+    assert_eq!(protocol.pending_epoch_acks, [id(3)]);
+
+    protocol.resend_epoch_announces().unwrap();
+    assert_eq!(
+        sent_controls(&mut protocol),
+        [ControlFrame::EpochAnnounce(7)]
+    );
+}
+
+#[test]
+fn flood_of_control_frames_fills_the_control_queue() {
+    let mut protocol = Protocol::<8, 8, 2>::builder(id(1)).build().unwrap();
+    for peer in [2, 3] {
+        protocol
+            .process_raw_packet(control_packet(peer, 1, ControlFrame::Version(1)))
+            .unwrap();
+    }
+    let flood = control_packet(4, 1, ControlFrame::EpochAnnounce(3));
+    assert_eq!(
+        protocol.process_raw_packet(flood),
+        Err(ProtocolError::ControlQueueFull)
+    );
+    let version = control_packet(5, 1, ControlFrame::Version(1));
+    assert_eq!(
+        protocol.process_raw_packet(version),
+        Err(ProtocolError::ControlQueueFull)
+    );
+    assert_eq!(protocol.version_with(id(5)), 0);
+
+    // Answered once there is room again
+    while protocol.get_next_packet_to_send().unwrap().is_some() {}
+    protocol.process_raw_packet(version).unwrap();
+    assert_eq!(protocol.version_with(id(5)), 1);
+    assert_eq!(protocol.acks_to_send.len(), 1);
+}
+
+#[test]
+fn answers_beyond_the_control_queue_are_refused() {
+    let mut protocol = Protocol::<8, 8, 2>::builder(id(2)).build().unwrap();
+    for seq in [3, 2] {
+        protocol
+            .process_raw_packet(data_packet(1, 2, 0, seq))
+            .unwrap();
+    }
+    assert_eq!(
+        protocol.process_raw_packet(data_packet(1, 2, 0, 1)),
+        Err(ProtocolError::ControlQueueFull)
+    );
+}
+
+#[test]
+fn queued_frames_wait_for_room_for_their_answer() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    let mut queue: FrameQueue<64> = FrameQueue::new();
+    let (mut producer, mut consumer) = queue.split();
+    // The same packet sent again and again while nobody reads the bus
+    for _ in 0..41 {
+        producer.enqueue(data_packet(1, 2, 0, 1)).unwrap();
+    }
+    protocol.process_queued_frames(&mut consumer).unwrap();
+    assert_eq!(protocol.acks_to_send.len(), 16);
+    assert_eq!(consumer.len(), 25);
+
+    let mut answers = 0;
+    while consumer.ready() {
+        answers += sent_controls(&mut protocol).len();
+        protocol.process_queued_frames(&mut consumer).unwrap();
+    }
+    answers += sent_controls(&mut protocol).len();
+    assert_eq!(answers, 41);
+}
+
+#[test]
+fn new_epoch_flushes_the_peer_state() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    protocol
+        .process_raw_packet(control_packet(1, 2, ControlFrame::EpochAnnounce(1)))
+        .unwrap();
+    assert_eq!(sent_controls(&mut protocol), [ControlFrame::EpochAck(1)]);
+
+    protocol
+        .process_raw_packet(data_packet(1, 2, 0, 3))
+        .unwrap();
+    protocol
+        .process_raw_packet(data_packet(4, 2, 0, 3))
+        .unwrap();
+    let mut message = Message::new(MessageId::new(5).unwrap(), id(1), id(2), &[9; 13]).unwrap();
+    message.mark_ack_as_received(SeqId::new(2).unwrap());
+    protocol.add_message_to_send_buff(message).unwrap();
+
+    // same epoch again, the announce was only repeated
+    protocol
+        .process_raw_packet(control_packet(1, 2, ControlFrame::EpochAnnounce(1)))
+        .unwrap();
+    assert_eq!(protocol.received.in_progress().count(), 2);
+
+    protocol
+        .process_raw_packet(control_packet(1, 2, ControlFrame::EpochAnnounce(2)))
+        .unwrap();
+    assert_eq!(
+        protocol
+            .received
+            .in_progress()
+            .collect::<heapless::Vec<_, 8>>(),
+        [(id(4), MessageId::new(0).unwrap())]
+    );
+    // the pending ACKs for node 1 are gone, only the answer to the announce and the ACK
+    // for node 4 are left
+    assert_eq!(
+        sent_controls(&mut protocol),
+        [ControlFrame::EpochAck(2), ControlFrame::Ack]
+    );
+    // the message to node 1 is sent again from its first packet
+    let first = Packet::try_from(&protocol.get_next_packet_to_send().unwrap().unwrap()).unwrap();
+    assert_eq!(first.header.seq_number, SeqId::new(2).unwrap());
+}
+
+#[test]
+fn data_is_dropped_until_the_peer_acks_our_epoch() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    protocol.announce_epoch(3, &[id(1)]).unwrap();
+    sent_controls(&mut protocol);
+
+    protocol
+        .process_raw_packet(data_packet(1, 2, 0, 0))
+        .unwrap();
+    assert!(protocol.received.is_empty());
+    assert!(protocol.acks_to_send.is_empty());
+
+    protocol
+        .process_raw_packet(control_packet(1, 2, ControlFrame::EpochAck(3)))
+        .unwrap();
+    protocol
+        .process_raw_packet(data_packet(1, 2, 0, 0))
+        .unwrap();
+    assert_eq!(protocol.received.len(), 1);
+}
+
+fn nack_packet(src: usize, dest: usize, id_message: usize, reason: NackReason) -> [u8; 8] {
+    let header = Header::new(
+        id(dest),
+        id(src),
+        true,
+        MessageId::new(id_message).unwrap(),
+        SeqId::new(0).unwrap(),
+    )
+    .unwrap();
+    Packet::new(header, ControlFrame::Nack(reason).into())
+        .try_into()
+        .unwrap()
+}
+
+#[test]
+fn rejected_message_is_nacked() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    protocol.message_filter = Some(|_| Err(NackReason::UnknownCommand));
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 1))
+        .unwrap();
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 0))
+        .unwrap();
+    assert!(protocol.received.is_empty());
+    assert!(protocol.received.in_progress().next().is_none());
+    let nack = Packet::try_from(&protocol.get_next_packet_to_send().unwrap().unwrap()).unwrap();
+    assert_eq!(nack.header.id_dest, id(1));
+    assert_eq!(nack.header.id_message, MessageId::new(3).unwrap());
+    assert_eq!(
+        ControlFrame::try_from(&nack),
+        Ok(ControlFrame::Nack(NackReason::UnknownCommand))
+    );
+    assert_eq!(sent_controls(&mut protocol), [ControlFrame::Ack]);
+}
+
+#[test]
+fn full_receive_pool_nacks_new_messages() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    for id_message in 0..7 {
+        protocol
+            .process_raw_packet(data_packet(1, 2, id_message, 0))
+            .unwrap();
+    }
+    // a message in progress takes a slot too
+    protocol
+        .process_raw_packet(data_packet(3, 2, 0, 1))
+        .unwrap();
+    sent_controls(&mut protocol);
+    assert!(protocol.received.is_full());
+
+    protocol
+        .process_raw_packet(data_packet(4, 2, 0, 1))
+        .unwrap();
+    assert_eq!(
+        sent_controls(&mut protocol),
+        [ControlFrame::Nack(NackReason::BufferFull)]
+    );
+    // the message in progress can still be completed
+    protocol
+        .process_raw_packet(data_packet(3, 2, 0, 0))
+        .unwrap();
+    assert_eq!(sent_controls(&mut protocol), [ControlFrame::Ack]);
+    assert_eq!(protocol.received.len(), 8);
+
+    protocol.received.pop_front();
+    protocol
+        .process_raw_packet(data_packet(4, 2, 0, 1))
+        .unwrap();
+    protocol
+        .process_raw_packet(data_packet(4, 2, 0, 0))
+        .unwrap();
+    assert_eq!(
+        sent_controls(&mut protocol),
+        [ControlFrame::Ack, ControlFrame::Ack]
+    );
+    assert_eq!(protocol.received.len(), 8);
+    assert_eq!(protocol.received[7], [1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn permanent_nack_stops_the_retries() {
+    let mut protocol = Protocol::new(id(1)).unwrap();
+    protocol
+        .add_message_to_send_buff(
+            Message::new(MessageId::new(5).unwrap(), id(2), id(1), &[9; 12]).unwrap(),
+        )
+        .unwrap();
+
+    protocol
+        .process_raw_packet(nack_packet(2, 1, 5, NackReason::BufferFull))
+        .unwrap();
+    assert_eq!(protocol.send_buff.len(), 1);
+    assert!(protocol.send_errors.is_empty());
+
+    protocol
+        .process_raw_packet(nack_packet(2, 1, 5, NackReason::MalformedPayload))
+        .unwrap();
+    assert!(protocol.send_buff.is_empty());
+    assert_eq!(
+        protocol.send_errors,
+        [SendFailure {
+            id_dest: id(2),
+            id: MessageId::new(5).unwrap(),
+            error: SendError::Rejected(NackReason::MalformedPayload),
+        }]
+    );
+    assert_eq!(protocol.get_next_packet_to_send().unwrap(), None);
+}
+
+fn next_data_packet(protocol: &mut Protocol) -> Option<Packet> {
+    protocol
+        .get_next_packet_to_send()
+        .unwrap()
+        .map(|raw| Packet::try_from(&raw).unwrap())
+}
+
+#[test]
+fn destinations_take_turns() {
+    // Retransmits as soon as there is nothing new to send
+    let mut protocol: Protocol = Protocol::builder(id(1)).ack_timeout(0).build().unwrap();
+    for dest in [2, 3] {
+        protocol
+            .add_message_to_send_buff(
+                Message::new(MessageId::new(0).unwrap(), id(dest), id(1), &[0; 12]).unwrap(),
+            )
+            .unwrap();
+    }
+    let dests: heapless::Vec<CanId, 6> = (0..6)
+        .map(|_| next_data_packet(&mut protocol).unwrap().header.id_dest)
+        .collect();
+    assert_eq!(dests, [id(2), id(3), id(2), id(3), id(2), id(3)]);
+}
+
+#[test]
+fn in_flight_packets_are_bounded() {
+    let mut protocol: Protocol = Protocol::builder(id(1)).ack_timeout(0).build().unwrap();
+    for id_message in 0..3 {
+        protocol
+            .add_message_to_send_buff(
+                Message::new(MessageId::new(id_message).unwrap(), id(2), id(1), &[0; 10]).unwrap(),
+            )
+            .unwrap();
+    }
+    // one packet per message until the window is full
+    for id_message in 0..2 {
+        let packet = next_data_packet(&mut protocol).unwrap();
+        assert_eq!(
+            packet.header.id_message,
+            MessageId::new(id_message).unwrap()
+        );
+        assert_eq!(packet.header.seq_number, SeqId::new(1).unwrap());
+    }
+    // then the oldest packet is sent again
+    let packet = next_data_packet(&mut protocol).unwrap();
+    assert_eq!(packet.header.id_message, MessageId::new(0).unwrap());
+    assert_eq!(packet.header.seq_number, SeqId::new(1).unwrap());
+
+    let ack = Header::new(
+        id(1),
+        id(2),
+        true,
+        MessageId::new(0).unwrap(),
+        SeqId::new(1).unwrap(),
+    )
+    .unwrap();
+    protocol
+        .process_raw_packet(
+            Packet::new(ack, ControlFrame::Ack.into())
+                .try_into()
+                .unwrap(),
+        )
+        .unwrap();
+    let packet = next_data_packet(&mut protocol).unwrap();
+    assert_eq!(packet.header.id_message, MessageId::new(0).unwrap());
+    assert_eq!(packet.header.seq_number, SeqId::new(0).unwrap());
+}
+
+#[test]
+fn queue_of_a_destination_is_bounded() {
+    let mut protocol = Protocol::new(id(1)).unwrap();
+    let message = |dest| Message::new(MessageId::new(0).unwrap(), id(dest), id(1), &[0]).unwrap();
+    for _ in 0..4 {
+        protocol.add_message_to_send_buff(message(2)).unwrap();
+    }
+    assert_eq!(
+        protocol.add_message_to_send_buff(message(2)),
+        Err(ProtocolError::SendFailed(SendError::QueueFull))
+    );
+    protocol.add_message_to_send_buff(message(3)).unwrap();
+}
+
+#[test]
+fn next_message_id_skips_the_ids_in_use() {
+    let mut protocol = Protocol::new(id(1)).unwrap();
+    let message =
+        |id_message| Message::new(MessageId::new(id_message).unwrap(), id(2), id(1), &[0]).unwrap();
+    assert_eq!(protocol.next_message_id(id(2)), MessageId::new(0));
+    protocol.add_message_to_send_buff(message(5)).unwrap();
+    assert_eq!(protocol.next_message_id(id(2)), MessageId::new(6));
+    protocol.add_message_to_send_buff(message(6)).unwrap();
+    protocol.add_message_to_send_buff(message(0)).unwrap();
+    // After the last id queued, the datagram id is never given
+    assert_eq!(protocol.next_message_id(id(2)), MessageId::new(1));
+    assert_eq!(protocol.next_message_id(id(3)), MessageId::new(0));
+}
+
+#[test]
+fn wide_message_ids_only_go_to_extended_peers() {
+    let mut protocol = Protocol::new(id(1)).unwrap();
+    let message = |id_message, dest| {
+        Message::new(MessageId::new(id_message).unwrap(), id(dest), id(1), &[0]).unwrap()
+    };
+    assert_eq!(
+        protocol.add_message_to_send_buff(message(12, 2)),
+        Err(ProtocolError::InvalidId(12))
+    );
+    protocol.add_message_to_send_buff(message(12, 40)).unwrap();
+    assert_eq!(protocol.next_message_id(id(40)), MessageId::new(13));
+    protocol.add_message_to_send_buff(message(31, 40)).unwrap();
+    // Wraps around after the last 5-bit id
+    assert_eq!(protocol.next_message_id(id(40)), MessageId::new(0));
+}
+
+#[test]
+fn next_ids_of_the_latest_destinations_are_remembered() {
+    let mut protocol: Protocol<64> = Protocol::builder(id(1)).build().unwrap();
+    let dests = (16..).take(MAX_PEERS + 1);
+    for dest in dests.clone() {
+        let message = Message::new(MessageId::new(3).unwrap(), id(dest), id(1), &[0]).unwrap();
+        protocol.add_message_to_send_buff(message).unwrap();
+    }
+    protocol.send_buff.clear();
+    // The first destination was forgotten to make room for the last one
+    assert_eq!(protocol.next_message_id(id(16)), MessageId::new(0));
+    for dest in dests.skip(1) {
+        assert_eq!(protocol.next_message_id(id(dest)), MessageId::new(4));
+    }
+}
+
+#[test]
+fn datagram_id_is_reserved() {
+    assert_eq!(
+        Message::new(MessageId::new(7).unwrap(), id(2), id(1), &[0]).unwrap_err(),
+        ProtocolError::InvalidId(7)
+    );
+}
+
+#[test]
+fn datagram_is_sent_once_and_not_acked() {
+    let mut sender = Protocol::new(id(1)).unwrap();
+    let mut receiver = Protocol::new(id(2)).unwrap();
+    sender
+        .add_message_to_send_buff(Message::datagram(id(2), id(1), &[5; 6]).unwrap())
+        .unwrap();
+    while let Some(raw) = sender.get_next_packet_to_send().unwrap() {
+        receiver.process_raw_packet(raw).unwrap();
+    }
+    assert!(sender.send_buff.is_empty());
+    assert!(receiver.acks_to_send.is_empty());
+    assert_eq!(receiver.received.len(), 1);
+    assert_eq!(receiver.received[0], [5; 6]);
+}
+
+#[test]
+fn datagram_fits_in_one_packet() {
+    assert_eq!(
+        Message::datagram(id(2), id(1), &[0; 7]).unwrap_err(),
+        ProtocolError::MessageTooLong
+    );
+}
+
+#[test]
+fn pending_message_with_the_same_key_is_replaced() {
+    let mut protocol = Protocol::new(id(1)).unwrap();
+    let setpoint = |id_message, value| {
+        Message::new(MessageId::new(id_message).unwrap(), id(2), id(1), &[value])
+            .unwrap()
+            .with_replace_key(1)
+    };
+    protocol.add_message_to_send_buff(setpoint(0, 10)).unwrap();
+    // already on the bus, it is not replaced
+    next_data_packet(&mut protocol).unwrap();
+    protocol.add_message_to_send_buff(setpoint(1, 20)).unwrap();
+    protocol.add_message_to_send_buff(setpoint(2, 30)).unwrap();
+    // an other key
+    protocol
+        .add_message_to_send_buff(
+            Message::new(MessageId::new(3).unwrap(), id(2), id(1), &[40])
+                .unwrap()
+                .with_replace_key(2),
+        )
+        .unwrap();
+
+    assert_eq!(protocol.send_buff.len(), 3);
+    assert_eq!(protocol.send_buff[1].id, MessageId::new(2).unwrap());
+    assert_eq!(protocol.send_buff[2].id, MessageId::new(3).unwrap());
+}
+
+#[test]
+fn expired_messages_are_dropped() {
+    let mut protocol = Protocol::new(id(1)).unwrap();
+    protocol
+        .add_message_to_send_buff(
+            Message::new(MessageId::new(0).unwrap(), id(2), id(1), &[0])
+                .unwrap()
+                .with_deadline(1_000),
+        )
+        .unwrap();
+    protocol
+        .add_message_to_send_buff(
+            Message::datagram(id(3), id(1), &[0])
+                .unwrap()
+                .with_deadline(500),
+        )
+        .unwrap();
+    protocol
+        .add_message_to_send_buff(
+            Message::new(MessageId::new(1).unwrap(), id(2), id(1), &[0]).unwrap(),
+        )
+        .unwrap();
+
+    protocol.drop_expired_messages(999);
+    assert_eq!(protocol.send_buff.len(), 2);
+    assert!(protocol.send_errors.is_empty());
+
+    protocol.drop_expired_messages(1_000);
+    assert_eq!(protocol.send_buff.len(), 1);
+    assert_eq!(protocol.send_buff[0].id, MessageId::new(1).unwrap());
+    assert_eq!(
+        protocol.send_errors,
+        [SendFailure {
+            id_dest: id(2),
+            id: MessageId::new(0).unwrap(),
+            error: SendError::Expired,
+        }]
+    );
+}
+
+fn multicast_packet(src: usize, dest: usize, value: u8) -> [u8; 8] {
+    let header = Header::new(
+        id(dest),
+        id(src),
+        false,
+        MessageId::new(DATAGRAM_MESSAGE_ID).unwrap(),
+        SeqId::new(0).unwrap(),
+    )
+    .unwrap();
+    Packet::new(header, [value; 6]).try_into().unwrap()
+}
+
+#[test]
+fn broadcast_is_received_without_ack() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    protocol
+        .process_raw_packet(multicast_packet(1, BROADCAST_ID, 1))
+        .unwrap();
+    assert_eq!(protocol.received.len(), 1);
+    assert!(protocol.acks_to_send.is_empty());
+}
+
+#[test]
+fn group_is_received_by_its_members_only() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    protocol
+        .process_raw_packet(multicast_packet(1, 12, 1))
+        .unwrap();
+    assert!(protocol.received.is_empty());
+
+    protocol.join_group(id(12)).unwrap();
+    protocol
+        .process_raw_packet(multicast_packet(1, 12, 2))
+        .unwrap();
+    protocol
+        .process_raw_packet(multicast_packet(1, 13, 3))
+        .unwrap();
+    assert_eq!(protocol.received.len(), 1);
+    assert_eq!(protocol.received[0], [2; 6]);
+    assert!(protocol.acks_to_send.is_empty());
+
+    protocol.leave_group(id(12));
+    protocol
+        .process_raw_packet(multicast_packet(1, 12, 4))
+        .unwrap();
+    assert_eq!(protocol.received.len(), 1);
+}
+
+#[test]
+fn only_group_ids_can_be_joined() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    assert_eq!(protocol.join_group(id(3)), Err(ProtocolError::InvalidId(3)));
+    assert_eq!(
+        protocol.join_group(id(BROADCAST_ID)),
+        Err(ProtocolError::InvalidId(BROADCAST_ID))
+    );
+}
+
+#[test]
+fn reliable_message_can_not_be_multicast() {
+    assert_eq!(
+        Message::new(MessageId::new(0).unwrap(), id(BROADCAST_ID), id(1), &[0]).unwrap_err(),
+        ProtocolError::MulticastMustBeDatagram
+    );
+}
+
+#[test]
+fn message_with_a_bad_crc_is_nacked_and_counted() {
+    let mut protocol = Protocol::new(id(2)).unwrap();
+    protocol.crc_peers.push(id(1)).unwrap();
+    // [1; 6] then [0; 6] can't end with their CRC
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 1))
+        .unwrap();
+    protocol
+        .process_raw_packet(data_packet(1, 2, 3, 0))
+        .unwrap();
+    assert!(protocol.received.is_empty());
+    assert_eq!(protocol.crc_errors, 1);
+    assert_eq!(
+        sent_controls(&mut protocol),
+        [ControlFrame::Nack(NackReason::Corrupted), ControlFrame::Ack]
+    );
+}
+
+#[test]
+fn corrupted_nack_restarts_the_message() {
+    let mut protocol = Protocol::new(id(1)).unwrap();
+    protocol
+        .add_message_to_send_buff(
+            Message::new(MessageId::new(5).unwrap(), id(2), id(1), &[9; 12]).unwrap(),
+        )
+        .unwrap();
+    let first = next_data_packet(&mut protocol).unwrap();
+    protocol
+        .process_raw_packet(control_packet_for(2, 1, &first, ControlFrame::Ack))
+        .unwrap();
+    let last = next_data_packet(&mut protocol).unwrap();
+    protocol
+        .process_raw_packet(control_packet_for(
+            2,
+            1,
+            &last,
+            ControlFrame::Nack(NackReason::Corrupted),
+        ))
+        .unwrap();
+    assert_eq!(
+        next_data_packet(&mut protocol).unwrap().header,
+        first.header
+    );
+    assert!(protocol.send_errors.is_empty());
+}
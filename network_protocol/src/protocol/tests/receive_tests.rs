@@ -0,0 +1,134 @@
+use crate::protocol::header::Header;
+use crate::protocol::packet::Packet;
+use crate::protocol::receive::{ReceivePool, Stored};
+use crate::protocol::{CanId, MessageId, SeqId, MAX_PEERS};
+
+fn packet(src: usize, seq: usize) -> Packet {
+    message_packet(src, 1, seq)
+}
+
+fn message_packet(src: usize, id: usize, seq: usize) -> Packet {
+    let header = Header::new(
+        CanId::new(2).unwrap(),
+        CanId::new(src).unwrap(),
+        false,
+        MessageId::new(id).unwrap(),
+        SeqId::new(seq).unwrap(),
+    )
+    .unwrap();
+    Packet::new(header, [seq as u8; 6])
+}
+
+#[test]
+fn packets_are_written_in_place() {
+    let mut pool: ReceivePool = ReceivePool::new();
+    assert_eq!(pool.store(&packet(1, 2)), Stored::Incomplete);
+    assert_eq!(pool.store(&packet(1, 2)), Stored::Duplicate);
+    assert_eq!(pool.store(&packet(1, 1)), Stored::Incomplete);
+    let slot = match pool.store(&packet(1, 0)) {
+        Stored::Complete(slot) => slot,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(pool.assembled(slot).len(), 18);
+    assert!(pool.is_empty());
+
+    pool.publish(slot, 14);
+    assert_eq!(pool.len(), 1);
+    assert_eq!(
+        pool.front(),
+        Some(&[2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 0, 0][..])
+    );
+    pool.pop_front();
+    assert!(pool.is_empty());
+    assert!(!pool.is_full());
+}
+
+#[test]
+fn longest_message_fills_a_slot() {
+    let mut pool: ReceivePool = ReceivePool::new();
+    for seq in (1..=15).rev() {
+        assert_eq!(pool.store(&packet(1, seq)), Stored::Incomplete);
+    }
+    assert_eq!(pool.store(&packet(1, 0)), Stored::Complete(0));
+    assert_eq!(pool.assembled(0).len(), 96);
+    assert_eq!(pool.assembled(0)[0], 15);
+}
+
+#[test]
+fn dropping_a_peer_keeps_its_complete_messages() {
+    let mut pool: ReceivePool = ReceivePool::new();
+    assert_eq!(pool.store(&packet(1, 0)), Stored::Complete(0));
+    pool.publish(0, 6);
+    assert_eq!(pool.store(&message_packet(1, 2, 1)), Stored::Incomplete);
+    assert_eq!(pool.store(&packet(3, 1)), Stored::Incomplete);
+
+    pool.drop_in_progress_from(CanId::new(1).unwrap());
+    assert_eq!(pool.len(), 1);
+    assert_eq!(
+        pool.in_progress().next(),
+        Some((CanId::new(3).unwrap(), MessageId::new(1).unwrap()))
+    );
+    assert_eq!(pool.in_progress().count(), 1);
+    // A rebooted peer starts its ids again
+    assert_eq!(pool.store(&packet(1, 0)), Stored::Complete(1));
+}
+
+#[test]
+fn last_message_sent_again() {
+    let mut pool: ReceivePool = ReceivePool::new();
+    assert_eq!(pool.store(&packet(1, 1)), Stored::Incomplete);
+    assert_eq!(pool.store(&packet(1, 0)), Stored::Complete(0));
+    pool.publish(0, 12);
+    pool.pop_front();
+    // Its ACKs were lost
+    assert_eq!(pool.store(&packet(1, 0)), Stored::Duplicate);
+    assert_eq!(pool.store(&packet(1, 1)), Stored::Duplicate);
+    assert!(pool.in_progress().next().is_none());
+    // Other sources and the next message are not affected
+    assert_eq!(pool.store(&packet(3, 0)), Stored::Complete(0));
+    assert_eq!(pool.store(&message_packet(1, 2, 0)), Stored::Complete(1));
+}
+
+#[test]
+fn last_messages_of_the_latest_sources_are_remembered() {
+    let mut pool: ReceivePool<1> = ReceivePool::new();
+    let sources = (16..).take(MAX_PEERS + 1);
+    for src in sources.clone() {
+        assert_eq!(pool.store(&packet(src, 0)), Stored::Complete(0));
+        pool.publish(0, 6);
+        pool.pop_front();
+    }
+    // The first source was forgotten to make room for the last one
+    assert_eq!(pool.store(&packet(16, 0)), Stored::Complete(0));
+    pool.release(0);
+    for src in sources.skip(2) {
+        assert_eq!(pool.store(&packet(src, 0)), Stored::Duplicate);
+    }
+}
+
+fn extended_packet(src: usize, seq: usize) -> Packet {
+    let header = message_packet(src, 20, seq).header;
+    Packet::extended(header, [seq as u8; 8])
+}
+
+#[test]
+fn extended_packets_are_written_in_place() {
+    let mut pool: ReceivePool = ReceivePool::new();
+    assert_eq!(pool.store(&extended_packet(40, 1)), Stored::Incomplete);
+    let slot = match pool.store(&extended_packet(40, 0)) {
+        Stored::Complete(slot) => slot,
+        other => panic!("{:?}", other),
+    };
+    pool.publish(slot, 10);
+    assert_eq!(pool.front(), Some(&[1, 1, 1, 1, 1, 1, 1, 1, 0, 0][..]));
+}
+
+#[test]
+fn packets_of_another_size_are_malformed() {
+    let mut pool: ReceivePool = ReceivePool::new();
+    assert_eq!(pool.store(&extended_packet(40, 1)), Stored::Incomplete);
+    assert_eq!(pool.store(&message_packet(40, 20, 0)), Stored::Malformed);
+    // 12 packets of 8 bytes fill a slot
+    assert_eq!(pool.store(&extended_packet(41, 12)), Stored::Malformed);
+    assert_eq!(pool.store(&extended_packet(41, 11)), Stored::Incomplete);
+}
@@ -0,0 +1,241 @@
+use crate::protocol::errors::{ProtocolError, RecordingError};
+use crate::protocol::extended::CanFrame;
+use crate::protocol::message::Message;
+use crate::protocol::protocol::Protocol;
+use crate::protocol::recording::{
+    Direction, FrameSink, NodeInfo, RecordedFrame, Recorder, Recording, ReplaySpeed, Replayer,
+};
+use crate::protocol::{CanId, MessageId};
+use crate::Write;
+use heapless::Vec;
+
+struct Buffer {
+    buff: Vec<u8, 256>,
+}
+
+impl Write for Buffer {
+    type Error = ();
+
+    fn write(&mut self, word: u8) -> Result<(), Self::Error> {
+        self.buff.push(word).map_err(|_| ())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn frame(timestamp: u32, first_byte: u8) -> RecordedFrame {
+    RecordedFrame {
+        timestamp,
+        node: CanId::new(1).unwrap(),
+        direction: Direction::Rx,
+        frame: CanFrame::Standard([first_byte, 0, 1, 2, 3, 4, 5, 6]),
+    }
+}
+
+/// Keeps the first byte of the frames fed
+#[derive(Default)]
+struct Sink {
+    fed: Vec<u8, 8>,
+}
+
+impl FrameSink for Sink {
+    fn feed_frame(&mut self, frame: &CanFrame) -> Result<(), ProtocolError> {
+        self.fed.push(frame.data()[0]).unwrap();
+        Ok(())
+    }
+}
+
+fn record(frames: &[RecordedFrame]) -> Vec<u8, 256> {
+    let nodes = [
+        NodeInfo::new(CanId::new(1).unwrap(), "gateway"),
+        NodeInfo::new(CanId::new(2).unwrap(), "herkulex"),
+    ];
+    let mut recorder = Recorder::new(Buffer { buff: Vec::new() }, &nodes).unwrap();
+    for f in frames {
+        recorder.record(f).unwrap();
+    }
+    recorder.into_inner().buff
+}
+
+#[test]
+fn recording_round_trip() {
+    let frames = [frame(0, 0x21), frame(1500, 0x12)];
+    let raw = record(&frames);
+    let recording = Recording::parse(&raw).unwrap();
+
+    let nodes: Vec<NodeInfo, 2> = recording.nodes().map(|n| n.unwrap()).collect();
+    assert_eq!(nodes[0].id, CanId::new(1).unwrap());
+    assert_eq!(nodes[0].name(), "gateway");
+    assert_eq!(nodes[1].name(), "herkulex");
+
+    assert_eq!(recording.frame_count(), 2);
+    for (read, written) in recording.frames().zip(frames.iter()) {
+        assert_eq!(&read.unwrap(), written);
+    }
+}
+
+#[test]
+fn extended_frames_round_trip() {
+    let mut extended = frame(0, 0);
+    extended.frame = CanFrame::Extended {
+        id: 0x0A23_4567,
+        data: [1, 2, 3, 4, 5, 6, 7, 8],
+    };
+    let frames = [extended, frame(10, 0x21)];
+    let raw = record(&frames);
+    let recording = Recording::parse(&raw).unwrap();
+
+    for (read, written) in recording.frames().zip(frames.iter()) {
+        assert_eq!(&read.unwrap(), written);
+    }
+}
+
+#[test]
+fn frames_of_an_unknown_kind_are_corrupted() {
+    let mut raw = record(&[frame(0, 0x21)]);
+    let kind = raw.len() - 8 - 4 - 1;
+    raw[kind] = 2;
+    let recording = Recording::parse(&raw).unwrap();
+    assert_eq!(
+        recording.frames().next().unwrap(),
+        Err(RecordingError::Corrupted)
+    );
+}
+
+#[test]
+fn node_name_is_truncated() {
+    let node = NodeInfo::new(CanId::new(3).unwrap(), "a_very_long_node_name");
+    assert_eq!(node.name(), "a_very_long_");
+}
+
+#[test]
+fn parse_rejects_bad_recordings() {
+    let raw = record(&[frame(0, 0x21)]);
+
+    let mut bad_magic = raw.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(
+        Recording::parse(&bad_magic).unwrap_err(),
+        RecordingError::BadMagic
+    );
+
+    let mut bad_version = raw.clone();
+    bad_version[4] = 42;
+    assert_eq!(
+        Recording::parse(&bad_version).unwrap_err(),
+        RecordingError::UnsupportedVersion(42)
+    );
+
+    assert_eq!(
+        Recording::parse(&raw[..raw.len() - 1]).unwrap_err(),
+        RecordingError::Truncated
+    );
+}
+
+#[test]
+fn replay_at_original_speed() {
+    let raw = record(&[frame(100, 1), frame(1100, 2), frame(3100, 3)]);
+    let recording = Recording::parse(&raw).unwrap();
+    let mut replayer = Replayer::new(&recording, ReplaySpeed::Original);
+
+    // the first poll anchors the recording on the current time
+    assert_eq!(replayer.poll(50_000).unwrap().unwrap().frame.data()[0], 1);
+    assert_eq!(replayer.poll(50_999).unwrap(), None);
+    assert_eq!(replayer.poll(51_000).unwrap().unwrap().frame.data()[0], 2);
+    assert_eq!(replayer.poll(52_000).unwrap(), None);
+    assert!(!replayer.is_finished());
+    assert_eq!(replayer.poll(53_000).unwrap().unwrap().frame.data()[0], 3);
+    assert_eq!(replayer.poll(60_000).unwrap(), None);
+    assert!(replayer.is_finished());
+}
+
+#[test]
+fn replay_accelerated() {
+    let raw = record(&[frame(0, 1), frame(1000, 2), frame(10_000, 3)]);
+    let recording = Recording::parse(&raw).unwrap();
+    let mut replayer = Replayer::new(&recording, ReplaySpeed::Accelerated(10));
+
+    assert_eq!(replayer.poll(0).unwrap().unwrap().frame.data()[0], 1);
+    assert_eq!(replayer.poll(99).unwrap(), None);
+    assert_eq!(replayer.poll(100).unwrap().unwrap().frame.data()[0], 2);
+    assert_eq!(replayer.poll(999).unwrap(), None);
+    assert_eq!(replayer.poll(1000).unwrap().unwrap().frame.data()[0], 3);
+}
+
+#[test]
+fn replay_instant() {
+    let raw = record(&[frame(0, 1), frame(1_000_000, 2)]);
+    let recording = Recording::parse(&raw).unwrap();
+    let mut replayer = Replayer::new(&recording, ReplaySpeed::Instant);
+
+    assert!(replayer.poll(0).unwrap().is_some());
+    assert!(replayer.poll(0).unwrap().is_some());
+    assert!(replayer.is_finished());
+}
+
+#[test]
+fn replay_feeds_what_the_node_received() {
+    let herkulex = CanId::new(2).unwrap();
+    let mut sent = frame(0, 1);
+    sent.direction = Direction::Tx;
+    let mut seen_by_herkulex = frame(0, 2);
+    seen_by_herkulex.node = herkulex;
+    let raw = record(&[frame(0, 0), sent, seen_by_herkulex, frame(10, 3)]);
+    let recording = Recording::parse(&raw).unwrap();
+    let mut replayer = Replayer::new(&recording, ReplaySpeed::Instant);
+    let mut sink = Sink::default();
+
+    let fed = replayer
+        .replay_node_into(0, CanId::new(1).unwrap(), &mut sink)
+        .unwrap();
+    assert_eq!(fed, 2);
+    assert_eq!(sink.fed, [0, 3]);
+    assert!(replayer.is_finished());
+}
+
+#[test]
+fn replay_feeds_every_received_frame() {
+    let mut sent = frame(0, 1);
+    sent.direction = Direction::Tx;
+    let mut seen_by_herkulex = frame(0, 2);
+    seen_by_herkulex.node = CanId::new(2).unwrap();
+    let raw = record(&[frame(0, 0), sent, seen_by_herkulex, frame(10, 3)]);
+    let recording = Recording::parse(&raw).unwrap();
+    let mut replayer = Replayer::new(&recording, ReplaySpeed::Instant);
+    let mut sink = Sink::default();
+
+    assert_eq!(replayer.replay_into(0, &mut sink).unwrap(), 3);
+    assert_eq!(sink.fed, [0, 2, 3]);
+}
+
+#[test]
+fn extended_frames_are_replayed() {
+    // Node ids above the standard limit only go in extended frames
+    let receiver = CanId::new(40).unwrap();
+    let mut sender = Protocol::new(CanId::new(1).unwrap()).unwrap();
+    sender
+        .add_message_to_send_buff(
+            Message::new(
+                MessageId::new(12).unwrap(),
+                receiver,
+                CanId::new(1).unwrap(),
+                &[1, 2, 3],
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    let sent = sender.get_next_frame_to_send().unwrap().unwrap();
+    assert!(matches!(sent, CanFrame::Extended { .. }));
+    let mut recorded = frame(0, 0);
+    recorded.frame = sent;
+    let raw = record(&[recorded]);
+
+    let recording = Recording::parse(&raw).unwrap();
+    let mut replayer = Replayer::new(&recording, ReplaySpeed::Instant);
+    let mut node = Protocol::new(receiver).unwrap();
+    assert_eq!(replayer.replay_into(0, &mut node).unwrap(), 1);
+    assert_eq!(node.received.len(), 1);
+    assert_eq!(&node.received[0][..3], &[1, 2, 3]);
+}
@@ -0,0 +1,151 @@
+use crate::protocol::errors::{ProtocolError, RoutingError};
+use crate::protocol::extended::CanFrame;
+use crate::protocol::header::Header;
+use crate::protocol::packet::Packet;
+use crate::protocol::router::{Interfaces, Router};
+use crate::protocol::{CanId, MessageId, SeqId, BROADCAST_ID, DATAGRAM_MESSAGE_ID};
+
+fn id(v: usize) -> CanId {
+    CanId::new(v).unwrap()
+}
+
+/// Gateway 5 between the bus 0 with the nodes 1 and 2 and the bus 1 with the nodes 3 and 4
+fn router() -> Router {
+    let mut router = Router::new(id(5), 2).unwrap();
+    router.add_route(id(1), 0).unwrap();
+    router.add_route(id(2), 0).unwrap();
+    router.add_route(id(3), 1).unwrap();
+    router.add_route(id(4), 1).unwrap();
+    router
+}
+
+fn frame(dest: usize, src: usize, is_ack: bool, id_message: usize) -> CanFrame {
+    let header = Header::new(
+        id(dest),
+        id(src),
+        is_ack,
+        MessageId::new(id_message).unwrap(),
+        SeqId::new(0).unwrap(),
+    )
+    .unwrap();
+    CanFrame::Standard((&Packet::new(header, [0; 6])).try_into().unwrap())
+}
+
+#[test]
+fn frame_is_forwarded_to_the_bus_of_its_destination() {
+    let router = router();
+    assert_eq!(
+        router.route(&frame(3, 1, false, 0), 0),
+        Ok(Interfaces::one(1))
+    );
+    // The ACK goes back the same way
+    assert_eq!(
+        router.route(&frame(1, 3, true, 0), 1),
+        Ok(Interfaces::one(0))
+    );
+}
+
+#[test]
+fn local_traffic_is_not_forwarded() {
+    let router = router();
+    assert_eq!(
+        router.route(&frame(2, 1, false, 0), 0),
+        Ok(Interfaces::none())
+    );
+    assert_eq!(
+        router.route(&frame(5, 1, false, 0), 0),
+        Ok(Interfaces::none())
+    );
+}
+
+#[test]
+fn frame_from_the_wrong_side_is_dropped() {
+    let router = router();
+    assert_eq!(
+        router.route(&frame(3, 1, false, 0), 1),
+        Err(RoutingError::WrongInterface {
+            src: id(1),
+            interface: 1
+        })
+    );
+    // The gateway's own frames never come back
+    assert_eq!(
+        router.route(&frame(1, 5, false, 0), 1),
+        Err(RoutingError::UnknownSource(id(5)))
+    );
+    assert_eq!(
+        router.route(&frame(3, 9, false, 0), 0),
+        Err(RoutingError::UnknownSource(id(9)))
+    );
+    assert_eq!(
+        router.route(&frame(9, 1, false, 0), 0),
+        Err(RoutingError::NoRoute(id(9)))
+    );
+}
+
+#[test]
+fn datagrams_to_multicast_ids_go_everywhere_else() {
+    let mut router = Router::new(id(5), 3).unwrap();
+    router.add_route(id(1), 0).unwrap();
+    let datagram = frame(BROADCAST_ID, 1, false, DATAGRAM_MESSAGE_ID);
+    let interfaces = router.route(&datagram, 0).unwrap();
+    assert_eq!(interfaces.iter().collect::<heapless::Vec<u8, 8>>(), [1, 2]);
+    assert_eq!(
+        router.route(&frame(12, 1, false, DATAGRAM_MESSAGE_ID), 0),
+        Ok(interfaces)
+    );
+    assert_eq!(
+        router
+            .interfaces_for(id(BROADCAST_ID))
+            .unwrap()
+            .iter()
+            .count(),
+        3
+    );
+}
+
+#[test]
+fn invalid_multicast_frame_is_not_forwarded() {
+    let router = router();
+    // Built by hand, `Header::new` refuses it
+    let raw = [(BROADCAST_ID << 4 | 1) as u8, 0, 0, 0, 0, 0, 0, 0];
+    assert_eq!(
+        router.route(&CanFrame::Standard(raw), 0),
+        Ok(Interfaces::none())
+    );
+}
+
+#[test]
+fn table_is_checked() {
+    let mut router = router();
+    assert_eq!(
+        router.add_route(id(6), 2),
+        Err(RoutingError::UnknownInterface(2))
+    );
+    assert_eq!(
+        router.add_route(id(BROADCAST_ID), 0),
+        Err(RoutingError::NotRoutable(id(BROADCAST_ID)))
+    );
+    assert_eq!(
+        router.add_route(id(5), 0),
+        Err(RoutingError::NotRoutable(id(5)))
+    );
+    assert_eq!(
+        router.interfaces_for(id(6)),
+        Err(RoutingError::NoRoute(id(6)))
+    );
+    assert_eq!(
+        Router::new(id(5), 9).unwrap_err(),
+        RoutingError::UnknownInterface(9)
+    );
+    let extended = CanFrame::Extended {
+        id: 0x1FFF_FFFF,
+        data: [0; 8],
+    };
+    assert_eq!(
+        router.route(&extended, 0),
+        Err(RoutingError::InvalidFrame(
+            ProtocolError::UnsupportedVersion(3)
+        ))
+    );
+}
@@ -0,0 +1,71 @@
+//! Messages of `robot.schema`, generated by the build script.
+//! The firmwares and the host tools encode and decode them with the same code.
+
+use crate::protocol::errors::ProtocolError;
+use crate::protocol::message::Message;
+use crate::protocol::{CanId, BROADCAST_ID};
+
+include!(concat!(env!("OUT_DIR"), "/schema.rs"));
+
+#[cfg(test)]
+mod tests;
+
+/// Table of the nodes and messages, the same as `PROTOCOL.md`
+pub const PROTOCOL_TABLE: &str = include_str!(concat!(env!("OUT_DIR"), "/PROTOCOL.md"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaError {
+    Empty,
+    UnknownCommand(u8),
+    /// The payload of this command is shorter than its fields
+    TooShort(u8),
+    /// The sender was built with another version of the schema
+    Mismatch { ours: u32, theirs: u32 },
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::Empty => write!(f, "empty message"),
+            SchemaError::UnknownCommand(command) => write!(f, "unknown command {:#04x}", command),
+            SchemaError::TooShort(command) => {
+                write!(f, "payload of command {:#04x} too short", command)
+            }
+            SchemaError::Mismatch { ours, theirs } => write!(
+                f,
+                "schema {:#010x} doesn't match ours {:#010x}",
+                theirs, ours
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SchemaError {}
+
+impl Heartbeat {
+    /// The heartbeat of a node built with this schema
+    pub fn current() -> Self {
+        Heartbeat {
+            schema_hash: SCHEMA_HASH,
+        }
+    }
+
+    /// Broadcast to every node, to be sent periodically
+    pub fn datagram(src: CanId) -> Result<Message, ProtocolError> {
+        Message::datagram(CanId::new(BROADCAST_ID)?, src, &Self::current().encode())
+    }
+
+    /// A node built with another schema can't be trusted to understand our messages
+    pub fn check(&self) -> Result<(), SchemaError> {
+        if self.schema_hash == SCHEMA_HASH {
+            Ok(())
+        } else {
+            Err(SchemaError::Mismatch {
+                ours: SCHEMA_HASH,
+                theirs: self.schema_hash,
+            })
+        }
+    }
+}
@@ -0,0 +1 @@
+mod schema_tests;
@@ -0,0 +1,74 @@
+use crate::protocol::dispatcher::REPLY_FLAG;
+use crate::protocol::BROADCAST_ID;
+use crate::schema::{
+    nodes, GetOdometry, Heartbeat, Odometry, SchemaError, SchemaMessage, SetServoTorque, SetSpeed,
+    Stop, PROTOCOL_TABLE, SCHEMA_HASH,
+};
+
+#[test]
+fn fields_are_little_endian_after_the_command() {
+    let message = SetSpeed {
+        left: -2,
+        right: 0x0102,
+    };
+    assert_eq!(
+        message.encode(),
+        [SetSpeed::COMMAND, 0xFE, 0xFF, 0x02, 0x01]
+    );
+    assert_eq!(SetSpeed::decode(&message.encode()[1..]), Ok(message));
+    assert_eq!(Stop.encode(), [Stop::COMMAND]);
+}
+
+#[test]
+fn padding_is_ignored_and_short_payloads_refused() {
+    let torque = SetServoTorque {
+        servo: 4,
+        enabled: true,
+    };
+    assert_eq!(SetServoTorque::decode(&[4, 1, 0, 0, 0]), Ok(torque));
+    assert_eq!(
+        SetServoTorque::decode(&[4]),
+        Err(SchemaError::TooShort(SetServoTorque::COMMAND))
+    );
+}
+
+#[test]
+fn messages_are_decoded_by_command_id() {
+    let odometry = Odometry {
+        x: 1500,
+        y: -20,
+        theta: 1.5,
+    };
+    assert_eq!(Odometry::COMMAND, GetOdometry::COMMAND | REPLY_FLAG);
+    let decoded = SchemaMessage::decode(&odometry.encode()).unwrap();
+    assert_eq!(decoded, SchemaMessage::Odometry(odometry));
+    assert_eq!(decoded.name(), "odometry");
+    assert_eq!(
+        SchemaMessage::decode(&[0x7E]),
+        Err(SchemaError::UnknownCommand(0x7E))
+    );
+    assert_eq!(SchemaMessage::decode(&[]), Err(SchemaError::Empty));
+}
+
+#[test]
+fn heartbeat_carries_the_schema_hash() {
+    assert!(Heartbeat::current().check().is_ok());
+    assert_eq!(
+        Heartbeat { schema_hash: 1 }.check(),
+        Err(SchemaError::Mismatch {
+            ours: SCHEMA_HASH,
+            theirs: 1
+        })
+    );
+    let src = crate::protocol::CanId::new(nodes::BRAIN).unwrap();
+    let mut datagram = Heartbeat::datagram(src).unwrap();
+    let packet = datagram.get_next_new_packet().unwrap().unwrap();
+    assert_eq!(usize::from(packet.header.id_dest), BROADCAST_ID);
+    assert_eq!(packet.payload[..5], Heartbeat::current().encode());
+}
+
+#[test]
+fn protocol_table_is_up_to_date() {
+    // PROTOCOL.md has to be copied from the build directory when the schema changes
+    assert_eq!(include_str!("../../../PROTOCOL.md"), PROTOCOL_TABLE);
+}
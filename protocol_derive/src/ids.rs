@@ -0,0 +1,29 @@
+//! Rules for the command ids, shared by the derive and `network_protocol/build.rs` which
+//! includes this file: a command declared with either of them is checked the same way
+
+/// `network_protocol::protocol::dispatcher::REPLY_FLAG`
+pub const REPLY_FLAG: u8 = 0x80;
+/// `network_protocol::protocol::dispatcher::ERROR_REPLY`
+pub const ERROR_REPLY: u8 = 0xFF;
+/// `network_protocol::log::LOG_COMMAND`
+pub const LOG_COMMAND: u8 = 0x7E;
+
+/// `id` if it can be the id of a command
+pub fn command_id(id: u8) -> Result<u8, &'static str> {
+    if id >= REPLY_FLAG {
+        return Err("command ids go up to 0x7F, the ids above are their replies");
+    }
+    if id == LOG_COMMAND {
+        return Err("0x7E is reserved for the logs");
+    }
+    Ok(id)
+}
+
+/// Id of the reply to the command `command`
+pub fn reply_id(command: u8) -> Result<u8, &'static str> {
+    let id = command_id(command)? | REPLY_FLAG;
+    if id == ERROR_REPLY {
+        return Err("0xFF is the id of the error replies");
+    }
+    Ok(id)
+}
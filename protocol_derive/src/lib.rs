@@ -0,0 +1,410 @@
+//! `#[derive(ProtocolMessage)]`, see `network_protocol::protocol::codec`
+
+mod ids;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Expr, Fields, Ident, LitInt, Type,
+};
+
+/// Bits which can be packed in one run, they are read through a u64
+const MAX_BIT_RUN: u32 = 64;
+
+/// Implements `WireFormat` for a struct or an enum, and `ProtocolMessage` when the type has a
+/// `#[protocol(command = ..)]` attribute, or `#[protocol(reply = ..)]` with the id of the command
+/// it answers. The ids follow the rules of `robot.schema`. A field with
+/// `#[protocol(bits = ..)]` is packed with the next bit fields, most significant bits first, a
+/// run of them must fill whole bytes.
+#[proc_macro_derive(ProtocolMessage, attributes(protocol))]
+pub fn derive_protocol_message(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Key and value of `#[protocol(<key> = ..)]` for one of `keys`, the other keys are refused
+fn protocol_attr(attrs: &[Attribute], keys: &[&str]) -> syn::Result<Option<(String, LitInt)>> {
+    let names: Vec<String> = keys.iter().map(|k| format!("`{}`", k)).collect();
+    let names = names.join(" or ");
+    let mut value = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("protocol")) {
+        attr.parse_nested_meta(|meta| match keys.iter().find(|k| meta.path.is_ident(k)) {
+            Some(_) if value.is_some() => Err(meta.error(format!("only one {} is given", names))),
+            Some(key) => {
+                value = Some((key.to_string(), meta.value()?.parse::<LitInt>()?));
+                Ok(())
+            }
+            None => Err(meta.error(format!("expected {}", names))),
+        })?;
+    }
+    Ok(value)
+}
+
+struct Field {
+    /// The name of a named field
+    member: Option<Ident>,
+    /// The variable holding the value while encoding and decoding, prefixed to not shadow the
+    /// variables of the generated code
+    binding: Ident,
+    ty: Type,
+    bits: Option<u32>,
+}
+
+enum Segment {
+    Whole(Box<Field>),
+    Bits(Vec<(Field, u32)>),
+}
+
+fn fields(fields: &Fields) -> syn::Result<Vec<Field>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let bits = match protocol_attr(&field.attrs, &["bits"])? {
+                Some((_, bits)) => {
+                    let value: u32 = bits.base10_parse()?;
+                    if value == 0 || value > 32 {
+                        return Err(Error::new(bits.span(), "a bit field is 1 to 32 bits wide"));
+                    }
+                    Some(value)
+                }
+                None => None,
+            };
+            Ok(Field {
+                member: field.ident.clone(),
+                binding: match &field.ident {
+                    Some(ident) => format_ident!("field_{}", ident),
+                    None => format_ident!("field_{}", i),
+                },
+                ty: field.ty.clone(),
+                bits,
+            })
+        })
+        .collect()
+}
+
+fn segments(fields: Vec<Field>, span: proc_macro2::Span) -> syn::Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut run: Vec<(Field, u32)> = Vec::new();
+    let mut run_bits = 0;
+    for field in fields {
+        match field.bits {
+            Some(bits) => {
+                run_bits += bits;
+                run.push((field, bits));
+                if run_bits % 8 == 0 {
+                    if run_bits > MAX_BIT_RUN {
+                        return Err(Error::new(span, "a run of bit fields can't exceed 64 bits"));
+                    }
+                    segments.push(Segment::Bits(std::mem::take(&mut run)));
+                    run_bits = 0;
+                }
+            }
+            None if !run.is_empty() => {
+                return Err(Error::new(
+                    field.ty.span(),
+                    "the bit fields before this one don't fill whole bytes",
+                ))
+            }
+            None => segments.push(Segment::Whole(Box::new(field))),
+        }
+    }
+    if !run.is_empty() {
+        return Err(Error::new(
+            span,
+            "the last bit fields don't fill whole bytes",
+        ));
+    }
+    Ok(segments)
+}
+
+fn size(segments: &[Segment]) -> TokenStream2 {
+    let parts = segments.iter().map(|segment| match segment {
+        Segment::Whole(field) => {
+            let ty = &field.ty;
+            quote!(<#ty as ::network_protocol::protocol::codec::WireFormat>::SIZE)
+        }
+        Segment::Bits(run) => {
+            let bytes = (run.iter().map(|(_, bits)| bits).sum::<u32>() / 8) as usize;
+            quote!(#bytes)
+        }
+    });
+    quote!(0usize #(+ #parts)*)
+}
+
+/// Writes the bindings at `offset` in `buf`
+fn encode(segments: &[Segment]) -> TokenStream2 {
+    let codec = quote!(::network_protocol::protocol::codec);
+    let parts = segments.iter().map(|segment| match segment {
+        Segment::Whole(field) => {
+            let (binding, ty) = (&field.binding, &field.ty);
+            quote! {
+                let size = <#ty as #codec::WireFormat>::SIZE;
+                #codec::WireFormat::write(#binding, &mut buf[offset..offset + size])?;
+                offset += size;
+            }
+        }
+        Segment::Bits(run) => {
+            let bytes = (run.iter().map(|(_, bits)| bits).sum::<u32>() / 8) as usize;
+            let packs = run.iter().map(|(field, bits)| {
+                let (binding, ty) = (&field.binding, &field.ty);
+                quote! {
+                    let value = u64::from(<#ty as #codec::BitField>::to_bits(#binding));
+                    if value >> #bits != 0 {
+                        return Err(#codec::CodecError::ValueTooLarge);
+                    }
+                    bits = (bits << #bits) | value;
+                }
+            });
+            quote! {
+                let mut bits: u64 = 0;
+                #(#packs)*
+                buf[offset..offset + #bytes].copy_from_slice(&bits.to_be_bytes()[8 - #bytes..]);
+                offset += #bytes;
+            }
+        }
+    });
+    quote! {
+        #(#parts)*
+        let _ = offset;
+    }
+}
+
+/// Reads the bindings from `offset` in `buf`
+fn decode(segments: &[Segment]) -> TokenStream2 {
+    let codec = quote!(::network_protocol::protocol::codec);
+    let parts = segments.iter().map(|segment| match segment {
+        Segment::Whole(field) => {
+            let (binding, ty) = (&field.binding, &field.ty);
+            quote! {
+                let size = <#ty as #codec::WireFormat>::SIZE;
+                let #binding = <#ty as #codec::WireFormat>::read(&buf[offset..offset + size])?;
+                offset += size;
+            }
+        }
+        Segment::Bits(run) => {
+            let total: u32 = run.iter().map(|(_, bits)| bits).sum();
+            let bytes = (total / 8) as usize;
+            let mut remaining = total;
+            let unpacks = run.iter().map(|(field, bits)| {
+                let (binding, ty) = (&field.binding, &field.ty);
+                remaining -= bits;
+                let mask = (1u64 << bits) - 1;
+                quote! {
+                    let #binding = <#ty as #codec::BitField>::from_bits(
+                        ((bits >> #remaining) & #mask) as u32,
+                    )?;
+                }
+            });
+            quote! {
+                let mut raw = [0u8; 8];
+                raw[8 - #bytes..].copy_from_slice(&buf[offset..offset + #bytes]);
+                let bits = u64::from_be_bytes(raw);
+                #(#unpacks)*
+                offset += #bytes;
+            }
+        }
+    });
+    quote! {
+        #(#parts)*
+        let _ = offset;
+    }
+}
+
+/// `Self { a: field_a }`, `Self(field_0, field_1)` or `Self` with the bindings
+fn construct(path: TokenStream2, fields: &[Field], kind: &Fields) -> TokenStream2 {
+    let bindings = fields.iter().map(|f| &f.binding);
+    match kind {
+        Fields::Named(_) => {
+            let members = fields.iter().map(|f| &f.member);
+            quote!(#path { #(#members: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(#path ( #(#bindings),* )),
+        Fields::Unit => path,
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "ProtocolMessage can't be derived for a generic type",
+        ));
+    }
+    let name = &input.ident;
+    let codec = quote!(::network_protocol::protocol::codec);
+
+    let (size, write, read, bit_field) = match &input.data {
+        Data::Struct(data) => {
+            let fields_list = fields(&data.fields)?;
+            let pattern = construct(quote!(Self), &fields_list, &data.fields);
+            let segments = segments(fields_list, name.span())?;
+            let size = size(&segments);
+            let encode = encode(&segments);
+            let decode = decode(&segments);
+            let write = quote! {
+                let #pattern = self;
+                let mut offset = 0usize;
+                #encode
+                Ok(())
+            };
+            let read = quote! {
+                let mut offset = 0usize;
+                #decode
+                Ok(#pattern)
+            };
+            (size, write, read, None)
+        }
+        Data::Enum(data) => {
+            let mut tag: u8 = 0;
+            let mut sizes = Vec::new();
+            let mut write_arms = Vec::new();
+            let mut read_arms = Vec::new();
+            let mut bit_arms = Vec::new();
+            let mut from_bit_arms = Vec::new();
+            let unit_only = data.variants.iter().all(|v| v.fields.is_empty());
+            for variant in &data.variants {
+                if let Some((_, discriminant)) = &variant.discriminant {
+                    tag = match discriminant {
+                        Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Int(value),
+                            ..
+                        }) => value.base10_parse()?,
+                        other => {
+                            return Err(Error::new(other.span(), "expected an integer tag"));
+                        }
+                    };
+                }
+                let ident = &variant.ident;
+                let fields_list = fields(&variant.fields)?;
+                let pattern = construct(quote!(Self::#ident), &fields_list, &variant.fields);
+                let segments = segments(fields_list, variant.ident.span())?;
+                sizes.push(size(&segments));
+                let encode = encode(&segments);
+                let decode = decode(&segments);
+                write_arms.push(quote! {
+                    #pattern => {
+                        buf[0] = #tag;
+                        let mut offset = 1usize;
+                        #encode
+                    }
+                });
+                read_arms.push(quote! {
+                    #tag => {
+                        let mut offset = 1usize;
+                        #decode
+                        Ok(#pattern)
+                    }
+                });
+                bit_arms.push(quote!(Self::#ident => #tag as u32));
+                from_bit_arms.push(quote!(#tag => Ok(Self::#ident)));
+                tag = tag.checked_add(1).ok_or_else(|| {
+                    Error::new(variant.span(), "an enum has at most 256 variants")
+                })?;
+            }
+            let size = quote! {
+                1 + {
+                    let sizes = [#(#sizes),*];
+                    let mut max = 0;
+                    let mut i = 0;
+                    while i < sizes.len() {
+                        if sizes[i] > max {
+                            max = sizes[i];
+                        }
+                        i += 1;
+                    }
+                    max
+                }
+            };
+            let write = quote! {
+                // The shorter variants are padded with zeros
+                buf[..<Self as #codec::WireFormat>::SIZE].fill(0);
+                match self {
+                    #(#write_arms)*
+                }
+                Ok(())
+            };
+            let read = quote! {
+                match buf[0] {
+                    #(#read_arms)*
+                    other => Err(#codec::CodecError::InvalidTag(other)),
+                }
+            };
+            // A fieldless enum can be a bit field too
+            let bit_field = unit_only.then(|| {
+                quote! {
+                    impl #codec::BitField for #name {
+                        fn to_bits(&self) -> u32 {
+                            match self {
+                                #(#bit_arms,)*
+                            }
+                        }
+
+                        fn from_bits(bits: u32) -> Result<Self, #codec::CodecError> {
+                            let tag = u8::try_from(bits)
+                                .map_err(|_| #codec::CodecError::ValueTooLarge)?;
+                            match tag {
+                                #(#from_bit_arms,)*
+                                other => Err(#codec::CodecError::InvalidTag(other)),
+                            }
+                        }
+                    }
+                }
+            });
+            (size, write, read, bit_field)
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                name.span(),
+                "ProtocolMessage can't be derived for a union",
+            ))
+        }
+    };
+
+    let message = match protocol_attr(&input.attrs, &["command", "reply"])? {
+        Some((key, id)) => {
+            let id_value: u8 = id.base10_parse()?;
+            let checked = match key.as_str() {
+                "command" => ids::command_id(id_value),
+                _ => ids::reply_id(id_value),
+            };
+            let value = checked.map_err(|error| Error::new(id.span(), error))?;
+            let too_long = format!("{} doesn't fit in a message", name);
+            quote! {
+                impl #codec::ProtocolMessage for #name {
+                    const COMMAND: u8 = #value;
+                }
+
+                const _: () = assert!(
+                    <#name as #codec::WireFormat>::SIZE
+                        < ::network_protocol::protocol::MAX_MESSAGE_LEN,
+                    #too_long
+                );
+            }
+        }
+        None => quote!(),
+    };
+
+    Ok(quote! {
+        impl #codec::WireFormat for #name {
+            const SIZE: usize = #size;
+
+            #[allow(unused_variables, unused_mut)]
+            fn write(&self, buf: &mut [u8]) -> Result<(), #codec::CodecError> {
+                #write
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn read(buf: &[u8]) -> Result<Self, #codec::CodecError> {
+                #read
+            }
+        }
+
+        #bit_field
+        #message
+    })
+}
@@ -0,0 +1,92 @@
+#![allow(dead_code)]
+
+use network_protocol::protocol::protocol::Protocol;
+use network_protocol::protocol::CAN_PACKET_SIZE;
+use network_protocol::Write;
+
+/// Time needed to send one frame on a 125kbit/s bus, in microseconds
+pub const FRAME_TIME_US: u32 = 1000;
+
+/// A CAN bus shared by several `Protocol`, every frame sent by a node is seen by all the others
+pub struct VirtualBus {
+    pub time: u32,
+    /// Every frame sent on the bus with the time and the index of the sender
+    pub log: Vec<(u32, usize, [u8; CAN_PACKET_SIZE])>,
+}
+
+impl VirtualBus {
+    pub fn new() -> Self {
+        VirtualBus {
+            time: 0,
+            log: Vec::new(),
+        }
+    }
+
+    /// Gives each node the opportunity to send one frame, returns the number of frames sent
+    pub fn step(&mut self, nodes: &mut [Protocol]) -> usize {
+        self.step_with(nodes, |_, _| true)
+    }
+
+    /// Same as `step` but `filter` can modify a frame or drop it by returning false.
+    /// The nodes are polled with the bus time first, an idle bus lasts one frame time.
+    pub fn step_with<F>(&mut self, nodes: &mut [Protocol], mut filter: F) -> usize
+    where
+        F: FnMut(usize, &mut [u8; CAN_PACKET_SIZE]) -> bool,
+    {
+        for node in nodes.iter_mut() {
+            node.poll(self.time);
+        }
+        let mut sent = 0;
+        for sender in 0..nodes.len() {
+            let mut frame = match nodes[sender].get_next_packet_to_send().unwrap() {
+                Some(frame) => frame,
+                None => continue,
+            };
+            sent += 1;
+            self.time += FRAME_TIME_US;
+            if !filter(sender, &mut frame) {
+                continue;
+            }
+            self.log.push((self.time, sender, frame));
+            for (receiver, node) in nodes.iter_mut().enumerate() {
+                if receiver != sender {
+                    node.process_raw_packet(frame).unwrap();
+                }
+            }
+        }
+        if sent == 0 {
+            self.time += FRAME_TIME_US;
+        }
+        sent
+    }
+
+    /// Steps until no node has anything left to send, the messages waiting for an ACK
+    /// included
+    pub fn run_until_idle(&mut self, nodes: &mut [Protocol], max_steps: usize) {
+        for _ in 0..max_steps {
+            if self.step(nodes) == 0 && nodes.iter().all(|node| node.send_buff.is_empty()) {
+                return;
+            }
+        }
+        panic!("the bus is still busy after {} steps", max_steps);
+    }
+}
+
+/// Byte sink backed by a std Vec
+#[derive(Default)]
+pub struct VecWriter {
+    pub buff: Vec<u8>,
+}
+
+impl Write for VecWriter {
+    type Error = ();
+
+    fn write(&mut self, word: u8) -> Result<(), Self::Error> {
+        self.buff.push(word);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+mod common;
+
+#[cfg(test)]
+mod crc_tests {
+    use crate::common::VirtualBus;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::packet::Packet;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{CanId, MessageId};
+
+    const PAYLOAD: [u8; 20] = [0x5A; 20];
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    fn nodes(crc: bool) -> [Protocol; 2] {
+        let mut nodes = [Protocol::new(id(1)).unwrap(), Protocol::new(id(2)).unwrap()];
+        if crc {
+            nodes[0].crc_peers.push(id(2)).unwrap();
+            nodes[1].crc_peers.push(id(1)).unwrap();
+        }
+        nodes[0]
+            .add_message_to_send_buff(
+                Message::new(MessageId::new(0).unwrap(), id(2), id(1), &PAYLOAD).unwrap(),
+            )
+            .unwrap();
+        nodes
+    }
+
+    /// Flips a bit in the second data packet, as a stale packet or a reassembly bug would
+    fn corrupt_once(nodes: &mut [Protocol; 2], bus: &mut VirtualBus) {
+        let mut data_packets = 0;
+        bus.step_with(nodes, |_, _| true);
+        while !nodes[0].send_buff.is_empty() {
+            bus.step_with(nodes, |sender, frame| {
+                let packet = Packet::try_from(&*frame).unwrap();
+                if sender == 0 && !packet.header.is_ack {
+                    data_packets += 1;
+                    if data_packets == 1 {
+                        frame[4] ^= 0x01;
+                    }
+                }
+                true
+            });
+        }
+    }
+
+    #[test]
+    fn corrupted_message_is_sent_again() {
+        let mut nodes = nodes(true);
+        let mut bus = VirtualBus::new();
+        corrupt_once(&mut nodes, &mut bus);
+
+        assert_eq!(nodes[1].crc_errors, 1);
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_eq!(&nodes[1].received[0][..PAYLOAD.len()], &PAYLOAD);
+        assert!(nodes[0].send_errors.is_empty());
+    }
+
+    #[test]
+    fn without_crc_the_corruption_goes_unnoticed() {
+        let mut nodes = nodes(false);
+        let mut bus = VirtualBus::new();
+        corrupt_once(&mut nodes, &mut bus);
+
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_ne!(&nodes[1].received[0][..PAYLOAD.len()], &PAYLOAD);
+    }
+}
@@ -0,0 +1,86 @@
+mod common;
+
+#[cfg(test)]
+mod datagrams_tests {
+    use crate::common::VirtualBus;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{CanId, MessageId};
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    fn nodes() -> [Protocol; 2] {
+        [Protocol::new(id(1)).unwrap(), Protocol::new(id(2)).unwrap()]
+    }
+
+    #[test]
+    fn lost_datagram_is_not_sent_again() {
+        let mut nodes = nodes();
+        nodes[0]
+            .add_message_to_send_buff(Message::datagram(id(2), id(1), &[1; 6]).unwrap())
+            .unwrap();
+        let mut bus = VirtualBus::new();
+        bus.step_with(&mut nodes, |_, _| false);
+        assert!(nodes[0].send_buff.is_empty());
+
+        nodes[0]
+            .add_message_to_send_buff(Message::datagram(id(2), id(1), &[2; 6]).unwrap())
+            .unwrap();
+        bus.run_until_idle(&mut nodes, 10);
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_eq!(nodes[1].received[0], [2; 6]);
+        // no ACK
+        assert_eq!(bus.log.len(), 1);
+    }
+
+    #[test]
+    fn only_the_latest_setpoint_is_delivered() {
+        let mut nodes = nodes();
+        let mut bus = VirtualBus::new();
+        // nothing reaches the receiver for a while
+        for value in 0..5u8 {
+            nodes[0]
+                .add_message_to_send_buff(
+                    Message::new(
+                        MessageId::new(value as usize).unwrap(),
+                        id(2),
+                        id(1),
+                        &[value],
+                    )
+                    .unwrap()
+                    .with_replace_key(0),
+                )
+                .unwrap();
+            bus.step_with(&mut nodes, |_, _| false);
+        }
+        // the first setpoint is already on the bus, the next ones overwrote each other
+        assert_eq!(nodes[0].send_buff.len(), 2);
+
+        bus.run_until_idle(&mut nodes, 20);
+        let values: Vec<u8> = nodes[1].received.iter().map(|m| m[0]).collect();
+        assert_eq!(values, [0, 4]);
+    }
+
+    #[test]
+    fn stale_setpoint_is_dropped() {
+        let mut nodes = nodes();
+        nodes[0]
+            .add_message_to_send_buff(
+                Message::new(MessageId::new(0).unwrap(), id(2), id(1), &[1])
+                    .unwrap()
+                    .with_deadline(2_000),
+            )
+            .unwrap();
+        let mut bus = VirtualBus::new();
+        while bus.time < 2_000 {
+            bus.step_with(&mut nodes, |_, _| false);
+            nodes[0].drop_expired_messages(bus.time);
+        }
+        assert!(nodes[0].send_buff.is_empty());
+        assert_eq!(nodes[0].send_errors.len(), 1);
+        bus.run_until_idle(&mut nodes, 10);
+        assert!(nodes[1].received.is_empty());
+    }
+}
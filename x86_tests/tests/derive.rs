@@ -0,0 +1,113 @@
+mod common;
+
+#[cfg(test)]
+mod derive_tests {
+    use crate::common::VirtualBus;
+    use network_protocol::protocol::codec::ProtocolMessage;
+    use network_protocol::protocol::control::NackReason;
+    use network_protocol::protocol::dispatcher::{Dispatcher, Reply, REPLY_FLAG};
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{CanId, MessageId};
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    #[derive(ProtocolMessage, Debug, PartialEq, Clone, Copy, Default)]
+    enum Gripper {
+        #[default]
+        Open,
+        Closed,
+    }
+
+    #[derive(ProtocolMessage, Debug, PartialEq, Clone, Copy, Default)]
+    #[protocol(command = 0x01)]
+    struct SetArm {
+        #[protocol(bits = 3)]
+        joint: u8,
+        #[protocol(bits = 1)]
+        gripper: Gripper,
+        #[protocol(bits = 12)]
+        angle: u16,
+        speed: f32,
+    }
+
+    #[derive(ProtocolMessage, Debug, PartialEq)]
+    #[protocol(command = 0x02)]
+    struct GetArm;
+
+    #[derive(ProtocolMessage, Debug, PartialEq)]
+    #[protocol(reply = 0x02)]
+    struct ArmState(SetArm, [i16; 3]);
+
+    #[derive(Default)]
+    struct Arm {
+        last: SetArm,
+        moves: i16,
+    }
+
+    fn set_arm(arm: &mut Arm, _: CanId, payload: &[u8], _: &mut Reply) -> Result<(), NackReason> {
+        arm.last = SetArm::decode(payload).map_err(|_| NackReason::MalformedPayload)?;
+        arm.moves += 1;
+        Ok(())
+    }
+
+    fn get_arm(arm: &mut Arm, _: CanId, _: &[u8], reply: &mut Reply) -> Result<(), NackReason> {
+        let state = ArmState(arm.last, [arm.moves, -1, 0]);
+        let data = state.encode().map_err(|_| NackReason::MalformedPayload)?;
+        // The dispatcher writes the command id of the reply
+        reply
+            .extend_from_slice(&data[1..])
+            .map_err(|_| NackReason::BufferFull)
+    }
+
+    fn arm_state(
+        states: &mut Vec<ArmState>,
+        _: CanId,
+        payload: &[u8],
+        _: &mut Reply,
+    ) -> Result<(), NackReason> {
+        states.push(ArmState::decode(payload).map_err(|_| NackReason::MalformedPayload)?);
+        Ok(())
+    }
+
+    fn send<M: ProtocolMessage>(node: &mut Protocol, id_message: usize, message: &M) {
+        let data = message.encode().unwrap();
+        let message =
+            Message::new(MessageId::new(id_message).unwrap(), id(2), id(1), &data).unwrap();
+        node.add_message_to_send_buff(message).unwrap();
+    }
+
+    #[test]
+    fn derived_messages_go_through_the_dispatcher() {
+        let mut nodes = [Protocol::new(id(1)).unwrap(), Protocol::new(id(2)).unwrap()];
+        let mut bus = VirtualBus::new();
+        let mut arm_commands: Dispatcher<Arm, 2> = Dispatcher::new();
+        arm_commands.register(SetArm::COMMAND, set_arm).unwrap();
+        arm_commands.register(GetArm::COMMAND, get_arm).unwrap();
+        let mut brain_replies: Dispatcher<Vec<ArmState>, 1> = Dispatcher::new();
+        brain_replies
+            .register(GetArm::COMMAND | REPLY_FLAG, arm_state)
+            .unwrap();
+        let mut arm = Arm::default();
+        let mut states = Vec::new();
+
+        let order = SetArm {
+            joint: 5,
+            gripper: Gripper::Closed,
+            angle: 0xABC,
+            speed: 0.25,
+        };
+        send(&mut nodes[0], 0, &order);
+        send(&mut nodes[0], 1, &GetArm);
+        for _ in 0..3 {
+            bus.run_until_idle(&mut nodes, 100);
+            arm_commands.dispatch(&mut nodes[1], &mut arm).unwrap();
+            brain_replies.dispatch(&mut nodes[0], &mut states).unwrap();
+        }
+
+        assert_eq!(arm.last, order);
+        assert_eq!(states, [ArmState(order, [1, -1, 0])]);
+    }
+}
@@ -0,0 +1,9 @@
+#[cfg(test)]
+mod derive_errors_tests {
+    /// The ids refused by `robot.schema` are refused by the derive too
+    #[test]
+    fn reserved_ids_dont_compile() {
+        let cases = trybuild::TestCases::new();
+        cases.compile_fail("tests/derive_errors/*.rs");
+    }
+}
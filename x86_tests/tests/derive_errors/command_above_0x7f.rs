@@ -0,0 +1,7 @@
+use network_protocol::protocol::codec::ProtocolMessage;
+
+#[derive(ProtocolMessage)]
+#[protocol(command = 0x82)]
+struct Message;
+
+fn main() {}
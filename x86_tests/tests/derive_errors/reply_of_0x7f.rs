@@ -0,0 +1,7 @@
+use network_protocol::protocol::codec::ProtocolMessage;
+
+#[derive(ProtocolMessage)]
+#[protocol(reply = 0x7F)]
+struct Message;
+
+fn main() {}
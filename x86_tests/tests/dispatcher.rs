@@ -0,0 +1,114 @@
+mod common;
+
+#[cfg(test)]
+mod dispatcher_tests {
+    use crate::common::VirtualBus;
+    use network_protocol::protocol::control::NackReason;
+    use network_protocol::protocol::dispatcher::{Dispatcher, Reply, ERROR_REPLY, REPLY_FLAG};
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{CanId, MessageId};
+
+    const SET_SPEED: u8 = 1;
+    const GET_SPEED: u8 = 2;
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    #[derive(Default)]
+    struct Motor {
+        speed: i16,
+    }
+
+    fn set_speed(
+        motor: &mut Motor,
+        _: CanId,
+        payload: &[u8],
+        _: &mut Reply,
+    ) -> Result<(), NackReason> {
+        let bytes = payload.get(..2).ok_or(NackReason::MalformedPayload)?;
+        motor.speed = i16::from_le_bytes([bytes[0], bytes[1]]);
+        Ok(())
+    }
+
+    fn get_speed(
+        motor: &mut Motor,
+        _: CanId,
+        _: &[u8],
+        reply: &mut Reply,
+    ) -> Result<(), NackReason> {
+        reply
+            .extend_from_slice(&motor.speed.to_le_bytes())
+            .map_err(|_| NackReason::BufferFull)
+    }
+
+    /// The replies seen by the gateway
+    #[derive(Default)]
+    struct Gateway {
+        speeds: Vec<i16>,
+        errors: Vec<(u8, u8)>,
+    }
+
+    fn speed_reply(
+        gateway: &mut Gateway,
+        _: CanId,
+        payload: &[u8],
+        _: &mut Reply,
+    ) -> Result<(), NackReason> {
+        gateway
+            .speeds
+            .push(i16::from_le_bytes([payload[0], payload[1]]));
+        Ok(())
+    }
+
+    fn error_reply(
+        gateway: &mut Gateway,
+        _: CanId,
+        payload: &[u8],
+        _: &mut Reply,
+    ) -> Result<(), NackReason> {
+        gateway.errors.push((payload[0], payload[1]));
+        Ok(())
+    }
+
+    fn request(gateway: &mut Protocol, id_message: usize, data: &[u8]) {
+        let message =
+            Message::new(MessageId::new(id_message).unwrap(), id(2), id(1), data).unwrap();
+        gateway.add_message_to_send_buff(message).unwrap();
+    }
+
+    #[test]
+    fn commands_are_handled_and_answered_over_the_bus() {
+        let mut nodes = [Protocol::new(id(1)).unwrap(), Protocol::new(id(2)).unwrap()];
+        let mut bus = VirtualBus::new();
+        let mut motor_commands: Dispatcher<Motor, 4> = Dispatcher::new();
+        motor_commands.register(SET_SPEED, set_speed).unwrap();
+        motor_commands.register(GET_SPEED, get_speed).unwrap();
+        let mut gateway_replies: Dispatcher<Gateway, 4> = Dispatcher::new();
+        gateway_replies
+            .register(GET_SPEED | REPLY_FLAG, speed_reply)
+            .unwrap();
+        gateway_replies.register(ERROR_REPLY, error_reply).unwrap();
+        let mut motor = Motor::default();
+        let mut gateway = Gateway::default();
+
+        let [speed_lo, speed_hi] = (-300i16).to_le_bytes();
+        request(&mut nodes[0], 0, &[SET_SPEED, speed_lo, speed_hi]);
+        request(&mut nodes[0], 1, &[GET_SPEED]);
+        request(&mut nodes[0], 2, &[9]);
+        for _ in 0..3 {
+            bus.run_until_idle(&mut nodes, 100);
+            motor_commands.dispatch(&mut nodes[1], &mut motor).unwrap();
+            gateway_replies
+                .dispatch(&mut nodes[0], &mut gateway)
+                .unwrap();
+        }
+
+        assert_eq!(motor.speed, -300);
+        assert_eq!(gateway.speeds, [-300]);
+        assert_eq!(gateway.errors, [(9, NackReason::UnknownCommand.into())]);
+        // nobody answers the replies
+        assert_eq!(bus.step(&mut nodes), 0);
+    }
+}
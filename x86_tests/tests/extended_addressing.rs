@@ -0,0 +1,174 @@
+mod common;
+
+#[cfg(test)]
+mod extended_addressing_tests {
+    use network_protocol::protocol::extended::{CanFrame, PROTOCOL_VERSION};
+    use network_protocol::protocol::header::Header;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::packet::Packet;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{CanId, MessageId, SeqId, MAX_MES_ID};
+
+    const PAYLOAD: [u8; 20] = [9; 20];
+    /// Index of the node which runs a firmware older than the version negotiation
+    const LEGACY: usize = 2;
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    /// The bus as seen by the legacy node
+    #[derive(Default)]
+    struct LegacyView {
+        /// (message id, seq) of the data packets it sent
+        sent: Vec<(MessageId, SeqId)>,
+        /// (message id, seq) of the frames it took for an ACK
+        acks: Vec<(MessageId, SeqId)>,
+    }
+
+    /// Runs the bus until nobody has anything to send, returns every frame sent.
+    /// The legacy node behaves like the firmware before the version negotiation: its filter
+    /// only accepts standard frames, it drops the frames whose header isn't addressed to its
+    /// id, and it takes every frame with the ACK bit for the ACK of its message id and seq,
+    /// whatever the payload.
+    fn run(nodes: &mut [Protocol], legacy: &mut LegacyView) -> Vec<CanFrame> {
+        let mut log = Vec::new();
+        for _ in 0..400 {
+            let mut sent = false;
+            for sender in 0..nodes.len() {
+                if let Some(frame) = nodes[sender].get_next_frame_to_send().unwrap() {
+                    sent = true;
+                    log.push(frame);
+                    for receiver in 0..nodes.len() {
+                        if receiver == sender {
+                            continue;
+                        }
+                        if receiver != LEGACY {
+                            nodes[receiver].process_frame(&frame).unwrap();
+                            continue;
+                        }
+                        let raw = match frame {
+                            CanFrame::Standard(raw) => raw,
+                            CanFrame::Extended { .. } => continue,
+                        };
+                        let header = Header::try_from(&raw).unwrap();
+                        if header.id_dest != nodes[LEGACY].host_id {
+                            continue;
+                        }
+                        if header.is_ack {
+                            legacy.acks.push((header.id_message, header.seq_number));
+                        }
+                        nodes[LEGACY].process_frame(&frame).unwrap();
+                    }
+                    if sender == LEGACY {
+                        let packet = Packet::try_from(&frame).unwrap();
+                        if !packet.header.is_ack {
+                            legacy
+                                .sent
+                                .push((packet.header.id_message, packet.header.seq_number));
+                        }
+                    }
+                }
+            }
+            if !sent {
+                return log;
+            }
+        }
+        panic!("the bus is still busy");
+    }
+
+    fn send(nodes: &mut [Protocol], sender: usize, dest: CanId) {
+        let node = &mut nodes[sender];
+        let id_message = node.next_message_id(dest).unwrap();
+        let message = Message::new(id_message, dest, node.host_id, &PAYLOAD).unwrap();
+        node.add_message_to_send_buff(message).unwrap();
+    }
+
+    fn data_frames(log: &[CanFrame], src: CanId, dest: CanId) -> Vec<(CanFrame, Packet)> {
+        log.iter()
+            .map(|frame| (*frame, Packet::try_from(frame).unwrap()))
+            .filter(|(_, packet)| {
+                !packet.header.is_ack
+                    && packet.header.id_src == src
+                    && packet.header.id_dest == dest
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extended_frames_are_only_used_between_nodes_which_speak_them() {
+        let mut nodes = [1, 2, 3].map(|v| Protocol::new(id(v)).unwrap());
+        let mut legacy = LegacyView::default();
+        // The legacy node is sending its message 0 while the others negotiate
+        send(&mut nodes, LEGACY, id(1));
+        nodes[0].announce_version(&[id(2), id(3)]).unwrap();
+        run(&mut nodes, &mut legacy);
+        assert_eq!(nodes[0].version_with(id(2)), PROTOCOL_VERSION);
+        assert_eq!(nodes[0].version_with(id(3)), 0);
+        assert_eq!(nodes[1].version_with(id(1)), PROTOCOL_VERSION);
+        assert_eq!(nodes[0].received.len(), 1);
+
+        send(&mut nodes, 0, id(2));
+        send(&mut nodes, 0, id(3));
+        let log = run(&mut nodes, &mut legacy);
+
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_eq!(&nodes[1].received[0][..PAYLOAD.len()], &PAYLOAD);
+        assert_eq!(nodes[LEGACY].received.len(), 1);
+        assert_eq!(&nodes[LEGACY].received[0][..PAYLOAD.len()], &PAYLOAD);
+        for frame in log {
+            let packet = Packet::try_from(&frame).unwrap();
+            let involves_legacy = packet.header.id_dest == id(3) || packet.header.id_src == id(3);
+            assert_eq!(
+                matches!(frame, CanFrame::Standard(_)),
+                involves_legacy,
+                "{:?}",
+                frame
+            );
+        }
+        // Only the answers to its own packets looked like ACKs to the legacy node
+        assert!(!legacy.acks.is_empty());
+        assert!(legacy.acks.iter().all(|ack| legacy.sent.contains(ack)));
+    }
+
+    #[test]
+    fn data_packets_use_the_8_bytes_of_extended_frames() {
+        let mut nodes = [1, 2, 3].map(|v| Protocol::new(id(v)).unwrap());
+        let mut legacy = LegacyView::default();
+        nodes[0].announce_version(&[id(2)]).unwrap();
+        run(&mut nodes, &mut legacy);
+
+        send(&mut nodes, 0, id(2));
+        send(&mut nodes, 0, id(3));
+        let log = run(&mut nodes, &mut legacy);
+        assert_eq!(data_frames(&log, id(1), id(2)).len(), 3);
+        assert_eq!(data_frames(&log, id(1), id(3)).len(), 4);
+        assert_eq!(&nodes[1].received[0][..PAYLOAD.len()], &PAYLOAD);
+        assert_eq!(&nodes[LEGACY].received[0][..PAYLOAD.len()], &PAYLOAD);
+    }
+
+    #[test]
+    fn nodes_above_15_use_wide_message_ids() {
+        let mut nodes = [1, 40, 3].map(|v| Protocol::new(id(v)).unwrap());
+        let mut legacy = LegacyView::default();
+        let mut ids = Vec::new();
+        for _ in 0..12 {
+            send(&mut nodes, 1, id(1));
+            send(&mut nodes, 0, id(40));
+            let log = run(&mut nodes, &mut legacy);
+            for (frame, packet) in data_frames(&log, id(40), id(1)) {
+                assert!(matches!(frame, CanFrame::Extended { .. }));
+                ids.push(usize::from(packet.header.id_message));
+            }
+            assert_eq!(nodes[0].received.len(), 1);
+            assert_eq!(nodes[0].received.front_message().unwrap().src, id(40));
+            assert_eq!(nodes[1].received.len(), 1);
+            nodes[0].received.pop_front();
+            nodes[1].received.pop_front();
+        }
+        assert!(ids.iter().any(|id| *id > MAX_MES_ID));
+        // Nothing was sent to the legacy node, and it took nothing for an ACK
+        assert!(legacy.acks.is_empty());
+        assert!(nodes[LEGACY].received.is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+mod common;
+
+#[cfg(test)]
+mod flow_control_tests {
+    use crate::common::VirtualBus;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{
+        CanId, MessageId, MAX_IN_FLIGHT_PACKETS, MAX_QUEUED_MESSAGES,
+    };
+
+    const PAYLOAD: [u8; 40] = [3; 40];
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    fn message(id_message: usize, dest: usize) -> Message {
+        Message::new(
+            MessageId::new(id_message).unwrap(),
+            id(dest),
+            id(1),
+            &PAYLOAD,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn dead_node_does_not_block_a_live_one() {
+        // node 3 is not on the bus, nobody ACKs its packets
+        let mut nodes = [Protocol::new(id(1)).unwrap(), Protocol::new(id(2)).unwrap()];
+        for id_message in 0..4 {
+            nodes[0]
+                .add_message_to_send_buff(message(id_message, 3))
+                .unwrap();
+        }
+        for id_message in 0..4 {
+            nodes[0]
+                .add_message_to_send_buff(message(id_message, 2))
+                .unwrap();
+        }
+
+        let mut bus = VirtualBus::new();
+        for _ in 0..60 {
+            bus.step(&mut nodes);
+        }
+
+        assert_eq!(nodes[1].received.len(), 4);
+        for received in nodes[1].received.iter() {
+            assert_eq!(&received[..PAYLOAD.len()], &PAYLOAD);
+        }
+        // the messages to the dead node are still waiting
+        assert_eq!(nodes[0].send_buff.len(), 4);
+        assert!(nodes[0].send_buff.iter().all(|m| m.id_dest() == id(3)));
+    }
+
+    #[test]
+    fn dead_node_only_gets_its_window_while_others_have_traffic() {
+        let mut nodes = [Protocol::new(id(1)).unwrap(), Protocol::new(id(2)).unwrap()];
+        for id_message in 0..MAX_QUEUED_MESSAGES {
+            nodes[0]
+                .add_message_to_send_buff(message(id_message, 3))
+                .unwrap();
+        }
+        nodes[0].add_message_to_send_buff(message(0, 2)).unwrap();
+
+        let mut bus = VirtualBus::new();
+        while nodes[1].received.is_empty() {
+            bus.step(&mut nodes);
+        }
+        let to_dead_node = bus
+            .log
+            .iter()
+            .filter(|(_, sender, frame)| *sender == 0 && frame[0] >> 4 == 3)
+            .count();
+        assert_eq!(to_dead_node, MAX_IN_FLIGHT_PACKETS);
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod herkulex_tests {
+    use drs_0x01::builder::HerkulexMessage;
+    use herkulex_driver::communication::HerkulexCommunication;
+    use herkulex_driver::error::HerkulexError;
+    use herkulex_driver::receiver::Frame;
+    use herkulex_driver::registers::ram;
+    use herkulex_driver::response::{checksums, Status};
+    use herkulex_driver::Motors;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct Link {
+        sent: Vec<Vec<u8>>,
+        /// Given by each `read_message`, `None` is a timeout
+        replies: VecDeque<Option<Vec<u8>>>,
+    }
+
+    /// Serial link replaying the `replies`, shared with the test to check what was sent
+    #[derive(Clone, Default)]
+    struct MockLink(Rc<RefCell<Link>>);
+
+    impl MockLink {
+        fn reply(&self, reply: Option<Vec<u8>>) {
+            self.0.borrow_mut().replies.push_back(reply);
+        }
+
+        fn sent(&self) -> Vec<Vec<u8>> {
+            self.0.borrow().sent.clone()
+        }
+    }
+
+    impl HerkulexCommunication for MockLink {
+        fn send_message(&mut self, msg: HerkulexMessage) {
+            self.0.borrow_mut().sent.push(msg.to_vec());
+        }
+
+        fn read_message(&mut self) -> Result<Frame, HerkulexError> {
+            match self.0.borrow_mut().replies.pop_front().flatten() {
+                Some(reply) => Ok(Frame::from_slice(&reply).unwrap()),
+                None => Err(HerkulexError::Timeout),
+            }
+        }
+    }
+
+    fn packet(id: u8, command: u8, data: &[u8]) -> Vec<u8> {
+        let size = 7 + data.len() as u8;
+        let (checksum1, checksum2) = checksums(size, id, command, data);
+        let mut packet = vec![0xFF, 0xFF, size, id, command, checksum1, checksum2];
+        packet.extend_from_slice(data);
+        packet
+    }
+
+    /// Reply of the servo `id` to the read of a RAM register, without error
+    fn ram_reply(id: u8, address: u8, value: &[u8]) -> Vec<u8> {
+        let mut data = vec![address, value.len() as u8];
+        data.extend_from_slice(value);
+        data.extend_from_slice(&[0x00, 0x40]);
+        packet(id, 0x44, &data)
+    }
+
+    #[test]
+    fn read_temperature() {
+        let link = MockLink::default();
+        let motors = Motors::new(link.clone());
+        let motor = motors.new_motor(2);
+        link.reply(Some(ram_reply(2, 55, &[0x4A])));
+        assert_eq!(motor.get_temperature(), Ok(0x4A));
+        assert_eq!(link.sent(), vec![ram::TEMPERATURE.request(2).to_vec()]);
+    }
+
+    #[test]
+    fn read_position() {
+        let link = MockLink::default();
+        let motors = Motors::new(link.clone());
+        let motor = motors.new_motor(0);
+        link.reply(Some(ram_reply(0, 60, &[0xF3, 0x01])));
+        assert_eq!(motor.get_absolute_position(), Ok(0x01F3));
+        link.reply(Some(ram_reply(0, 64, &[0x00, 0xFE])));
+        assert_eq!(motor.get_pwm(), Ok(-512));
+    }
+
+    #[test]
+    fn status() {
+        let link = MockLink::default();
+        let motors = Motors::new(link.clone());
+        let motor = motors.new_motor(0xFD);
+        link.reply(Some(packet(0xFD, 0x47, &[0x01, 0x40])));
+        assert_eq!(
+            motor.stat(),
+            Ok(Status {
+                error: 0x01,
+                detail: 0x40
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_replies_are_retried() {
+        let link = MockLink::default();
+        let motors = Motors::new(link.clone());
+        let motor = motors.new_motor(2);
+        link.reply(None);
+        // Late reply of another servo
+        link.reply(Some(ram_reply(0, 55, &[0x30])));
+        link.reply(Some(ram_reply(2, 55, &[0x4A])));
+        assert_eq!(motor.get_temperature(), Ok(0x4A));
+        assert_eq!(link.sent().len(), 3);
+    }
+
+    #[test]
+    fn absent_servo() {
+        let link = MockLink::default();
+        let motors = Motors::new(link.clone());
+        let motor = motors.new_motor(2);
+        assert_eq!(motor.get_temperature(), Err(HerkulexError::Timeout));
+        assert_eq!(link.sent().len(), 3);
+    }
+
+    #[test]
+    fn last_error_is_returned() {
+        let link = MockLink::default();
+        let motors = Motors::new(link.clone());
+        let motor = motors.new_motor(2);
+        for _ in 0..3 {
+            link.reply(Some(ram_reply(2, 54, &[0x60])));
+        }
+        assert_eq!(
+            motor.get_temperature(),
+            Err(HerkulexError::WrongRegister {
+                expected: 55,
+                received: 54
+            })
+        );
+    }
+
+    #[test]
+    fn commands_dont_wait_for_a_reply() {
+        let link = MockLink::default();
+        let motors = Motors::new(link.clone());
+        let motor = motors.new_motor(2);
+        motor.enable_torque();
+        motor.set_position(512);
+        assert_eq!(link.sent().len(), 2);
+    }
+}
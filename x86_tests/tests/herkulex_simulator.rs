@@ -0,0 +1,353 @@
+#[cfg(test)]
+mod herkulex_simulator_tests {
+    use drs_0x01::builder::HerkulexMessage;
+    use drs_0x01::Rotation;
+    use herkulex_driver::communication::HerkulexCommunication;
+    use herkulex_driver::error::HerkulexError;
+    use herkulex_driver::jog::{IJog, Led, SJog};
+    use herkulex_driver::receiver::Frame;
+    use herkulex_driver::registers::ram;
+    use herkulex_driver::response::status_detail::{INPOSITION, MOTOR_ON, MOVING};
+    use herkulex_driver::response::status_error::{
+        EXCEED_POSITION_LIMIT, EXCEED_TEMPERATURE, OVERLOAD,
+    };
+    use herkulex_driver::response::BROADCAST_ID;
+    use herkulex_driver::sim::VirtualLine;
+    use herkulex_driver::units::{Angle, AngularVelocity};
+    use herkulex_driver::Motors;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Counts the requests sent on the line
+    struct CountingLine {
+        line: VirtualLine,
+        requests: Rc<Cell<usize>>,
+    }
+
+    impl HerkulexCommunication for CountingLine {
+        fn send_message(&mut self, msg: HerkulexMessage) {
+            self.requests.set(self.requests.get() + 1);
+            self.line.send_message(msg);
+        }
+
+        fn read_message(&mut self) -> Result<Frame, HerkulexError> {
+            self.line.read_message()
+        }
+    }
+
+    #[test]
+    fn servos_on_one_line() {
+        let line = VirtualLine::new(&[0x00, 0x02]);
+        line.with_servo(0x02, |servo| servo.temperature = 0x4A);
+        let motors = Motors::new(line.clone());
+        assert_eq!(motors.new_motor(0x00).get_temperature(), Ok(0x40));
+        assert_eq!(motors.new_motor(0x02).get_temperature(), Ok(0x4A));
+        assert_eq!(motors.new_motor(0x02).get_id(), Ok(0x02));
+        assert_eq!(
+            motors.new_motor(0x01).get_temperature(),
+            Err(HerkulexError::Timeout)
+        );
+    }
+
+    #[test]
+    fn scan_finds_the_servos_on_the_line() {
+        let line = VirtualLine::new(&[0x00, 0x02, 0x2A]);
+        let motors = Motors::new(line.clone());
+        let found = motors.scan::<8>(0..BROADCAST_ID);
+        assert_eq!(found, [0x00, 0x02, 0x2A]);
+
+        // A servo which never answers is not found, the scan stops once it is full
+        motors.new_motor(0x02).set_ack_policy(0);
+        assert_eq!(motors.scan::<8>(0..BROADCAST_ID), [0x00, 0x2A]);
+        assert_eq!(motors.scan::<1>(0..BROADCAST_ID), [0x00]);
+    }
+
+    #[test]
+    fn set_position() {
+        let line = VirtualLine::new(&[0x00, 0x02]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x02);
+        motor.enable_torque();
+        assert_eq!(
+            motor.stat().map(|status| status.detail & MOTOR_ON),
+            Ok(MOTOR_ON)
+        );
+
+        motor.set_position(700);
+        line.step(30);
+        let position = motor.get_absolute_position().unwrap();
+        assert!(512 < position && position < 700);
+        assert_eq!(
+            motor.stat().map(|status| status.detail & MOVING),
+            Ok(MOVING)
+        );
+
+        // The play time of `set_position` is 60 ticks
+        line.step(30);
+        assert_eq!(motor.get_absolute_position(), Ok(700));
+        assert_eq!(
+            motor.stat().map(|status| status.detail & INPOSITION),
+            Ok(INPOSITION)
+        );
+        // Only the servo 2 moved
+        assert_eq!(motors.new_motor(0x00).get_absolute_position(), Ok(512));
+    }
+
+    #[test]
+    fn set_speed() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        motor.set_speed(512, Rotation::CounterClockwise);
+        line.step(10);
+        assert!(motor.get_absolute_position().unwrap() > 512);
+        assert_eq!(motor.get_current_control_mode(), Ok(1));
+    }
+
+    #[test]
+    fn silent_servo() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.set_ack_policy(0);
+        assert_eq!(motor.get_temperature(), Err(HerkulexError::Timeout));
+        line.with_servo(0x00, |servo| assert_eq!(servo.register(ram::ACK_POLICY), 0));
+    }
+
+    #[test]
+    fn overheating() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        line.with_servo(0x00, |servo| servo.temperature = 0xE0);
+        line.step(1);
+        assert_eq!(motor.get_status_error(), Ok(EXCEED_TEMPERATURE));
+        assert_eq!(motor.get_torque_control(), Ok(0x00));
+
+        // Back to normal after a reboot
+        line.with_servo(0x00, |servo| servo.temperature = 0x40);
+        motor.reboot();
+        assert_eq!(motor.get_status_error(), Ok(0));
+    }
+
+    #[test]
+    fn synchronous_move() {
+        let line = VirtualLine::new(&[0x00, 0x01, 0x02]);
+        let motors = Motors::new(line.clone());
+        let ids = [0x00, 0x01, 0x02];
+        ids.iter()
+            .for_each(|id| motors.new_motor(*id).enable_torque());
+
+        let mut jog = SJog::new(50);
+        jog.position(0x00, 300, Led::GREEN)
+            .unwrap()
+            .position(0x01, 800, Led::BLUE)
+            .unwrap()
+            .position(0x02, 600, Led::RED)
+            .unwrap();
+        motors.s_jog(&jog);
+
+        // The servos arrive together, whatever their distance to the goal
+        line.step(49);
+        for id in ids {
+            let status = motors.new_motor(id).stat().unwrap();
+            assert_eq!(status.detail & MOVING, MOVING);
+        }
+        line.step(1);
+        let positions: Vec<_> = ids
+            .iter()
+            .map(|id| motors.new_motor(*id).get_absolute_position())
+            .collect();
+        assert_eq!(positions, [Ok(300), Ok(800), Ok(600)]);
+        assert_eq!(motors.new_motor(0x01).get_led_control(), Ok(0x02));
+    }
+
+    #[test]
+    fn individual_playtimes() {
+        let line = VirtualLine::new(&[0x00, 0x01]);
+        let motors = Motors::new(line.clone());
+        motors.new_motor(0x00).enable_torque();
+        motors.new_motor(0x01).enable_torque();
+
+        let mut jog = IJog::new();
+        jog.position(0x00, 612, Led::GREEN, 20)
+            .unwrap()
+            .position(0x01, 612, Led::GREEN, 40)
+            .unwrap();
+        motors.i_jog(&jog);
+
+        line.step(20);
+        assert_eq!(motors.new_motor(0x00).get_absolute_position(), Ok(612));
+        let position = motors.new_motor(0x01).get_absolute_position().unwrap();
+        assert!(512 < position && position < 612);
+        line.step(20);
+        assert_eq!(motors.new_motor(0x01).get_absolute_position(), Ok(612));
+    }
+
+    #[test]
+    fn chained_moves() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        for (position, led, control) in [
+            (600, Led::GREEN, 1),
+            (400, Led::BLUE, 2),
+            (650, Led::OFF, 0),
+        ] {
+            motor.move_to(position, 30, led).unwrap();
+            assert_eq!(motor.poll_move(), Ok(false));
+            let mut ticks = 0;
+            assert_eq!(
+                motor.wait_until_in_position(100, |delay| {
+                    line.step(delay);
+                    ticks += delay;
+                }),
+                Ok(())
+            );
+            assert_eq!(ticks, 30);
+            assert_eq!(motor.get_absolute_position(), Ok(position));
+            assert_eq!(motor.get_led_control(), Ok(control));
+        }
+    }
+
+    #[test]
+    fn a_poll_reads_the_status_and_the_position() {
+        let line = VirtualLine::new(&[0x00]);
+        let requests = Rc::new(Cell::new(0));
+        let motors = Motors::new(CountingLine {
+            line: line.clone(),
+            requests: requests.clone(),
+        });
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        motor.move_to(600, 30, Led::BLUE).unwrap();
+        line.step(30);
+
+        // The In Position Margin is only read by the first poll
+        for expected in [3, 2, 2] {
+            requests.set(0);
+            assert_eq!(motor.poll_move(), Ok(true));
+            assert_eq!(requests.get(), expected);
+        }
+    }
+
+    #[test]
+    fn move_timeout() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        motor.move_to(700, 100, Led::BLUE).unwrap();
+        let mut ticks = 0;
+        assert_eq!(
+            motor.wait_until_in_position(10, |delay| {
+                line.step(delay);
+                ticks += delay;
+            }),
+            Err(HerkulexError::MoveTimeout)
+        );
+        assert_eq!(ticks, 10);
+    }
+
+    #[test]
+    fn goal_beyond_the_limits() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        assert_eq!(
+            motor.move_to(1024, 30, Led::BLUE),
+            Err(HerkulexError::InvalidValue(1024))
+        );
+        // In range of the protocol, beyond the limits of the servo
+        motor.move_to(1010, 30, Led::BLUE).unwrap();
+        let result = motor.wait_until_in_position(100, |delay| line.step(delay));
+        assert!(
+            matches!(result, Err(HerkulexError::MoveFailed(status)) if status.error == EXCEED_POSITION_LIMIT)
+        );
+    }
+
+    #[test]
+    fn overload_during_a_move() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        motor.move_to(800, 60, Led::BLUE).unwrap();
+        line.step(20);
+        line.with_servo(0x00, |servo| servo.raise(OVERLOAD, 0));
+        let result = motor.wait_until_in_position(100, |delay| line.step(delay));
+        assert!(
+            matches!(result, Err(HerkulexError::MoveFailed(status)) if status.error == OVERLOAD)
+        );
+    }
+
+    #[test]
+    fn physical_units() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        assert_eq!(motor.get_angle(), Ok(Angle(0)));
+        assert_eq!(motor.get_voltage_millivolts(), Ok(7400));
+
+        // 90° at 180°/s, 0.5s
+        let velocity = AngularVelocity::from_degrees_per_second(180);
+        motor
+            .move_to_angle_at(Angle::from_degrees(90), velocity, Led::BLUE)
+            .unwrap();
+        line.step(20);
+        assert!(motor.get_angular_velocity().unwrap() > AngularVelocity(0));
+        line.step(25);
+        assert_eq!(motor.poll_move(), Ok(true));
+        assert_eq!(motor.get_angle(), Ok(Angle(90_025)));
+
+        motor
+            .move_to_angle(Angle::from_degrees(-45), 40, Led::BLUE)
+            .unwrap();
+        line.step(40);
+        assert_eq!(motor.get_angle(), Ok(Angle(-44_850)));
+    }
+
+    #[test]
+    fn turn_at_an_angular_velocity() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        motor
+            .set_angular_velocity(AngularVelocity::from_degrees_per_second(-180))
+            .unwrap();
+        line.step(5);
+        // The model doesn't turn exactly at the speed of the datasheet, and the velocity is
+        // measured in whole positions per tick, 29°/s
+        let velocity = motor.get_angular_velocity().unwrap().degrees_per_second();
+        assert!((-210.0..=-150.0).contains(&velocity), "{}", velocity);
+
+        let too_fast = AngularVelocity::from_degrees_per_second(400);
+        assert_eq!(
+            motor.set_angular_velocity(too_fast),
+            Err(HerkulexError::InvalidValue(1132))
+        );
+    }
+
+    #[test]
+    fn out_of_range_angles_are_not_sent() {
+        let line = VirtualLine::new(&[0x00]);
+        let motors = Motors::new(line.clone());
+        let motor = motors.new_motor(0x00);
+        motor.enable_torque();
+        assert!(motor
+            .move_to_angle(Angle::from_degrees(170), 30, Led::BLUE)
+            .is_err());
+        let slow = AngularVelocity::from_degrees_per_second(10);
+        assert!(motor
+            .move_to_angle_at(Angle::from_degrees(90), slow, Led::BLUE)
+            .is_err());
+        assert_eq!(motor.get_absolute_goal_position(), Ok(512));
+        assert_eq!(motor.get_status_error(), Ok(0));
+    }
+}
@@ -0,0 +1,65 @@
+mod common;
+
+#[cfg(test)]
+mod interrupt_queue_tests {
+    use network_protocol::protocol::header::Header;
+    use network_protocol::protocol::packet::Packet;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::receive::FrameQueue;
+    use network_protocol::protocol::{CanId, MessageId, SeqId, CAN_PACKET_SIZE};
+    use std::thread;
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    /// The frames of a message of `packets` packets, the payload is the sequence number
+    fn frames(src: usize, id_message: usize, packets: usize) -> Vec<[u8; CAN_PACKET_SIZE]> {
+        (0..packets)
+            .rev()
+            .map(|seq| {
+                let header = Header::new(
+                    id(2),
+                    id(src),
+                    false,
+                    MessageId::new(id_message).unwrap(),
+                    SeqId::new(seq).unwrap(),
+                )
+                .unwrap();
+                Packet::new(header, [seq as u8; 6]).try_into().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn frames_pushed_by_the_interrupt_are_reassembled_by_the_main_loop() {
+        let mut queue: FrameQueue<4> = FrameQueue::new();
+        let (mut producer, mut consumer) = queue.split();
+        let mut protocol = Protocol::new(id(2)).unwrap();
+        let mut to_send = frames(1, 0, 5);
+        to_send.extend(frames(3, 0, 2));
+        to_send.extend(frames(1, 1, 1));
+
+        thread::scope(|s| {
+            // stands for the CAN RX interrupt, it never touches the protocol
+            s.spawn(move || {
+                for frame in to_send {
+                    while producer.enqueue(frame).is_err() {
+                        thread::yield_now();
+                    }
+                }
+            });
+            while protocol.received.len() < 3 {
+                protocol.process_queued_frames(&mut consumer).unwrap();
+                // the answers are not needed here
+                while protocol.get_next_packet_to_send().unwrap().is_some() {}
+            }
+        });
+
+        assert_eq!(protocol.received.len(), 3);
+        assert_eq!(protocol.received[0].len(), 30);
+        assert_eq!(&protocol.received[0][..7], &[4, 4, 4, 4, 4, 4, 3]);
+        assert_eq!(protocol.received[1], [1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(protocol.received[2], [0; 6]);
+    }
+}
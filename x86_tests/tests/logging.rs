@@ -0,0 +1,170 @@
+mod common;
+
+#[cfg(test)]
+mod logging_tests {
+    use crate::common::{VecWriter, VirtualBus};
+    use log_viewer::{collect_logs, format_line, node_names};
+    use network_protocol::log::{BusLogger, Level};
+    use network_protocol::protocol::extended::CanFrame;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::recording::{
+        Direction, NodeInfo, RecordedFrame, Recorder, Recording,
+    };
+    use network_protocol::protocol::{CanId, MessageId};
+    use network_protocol::schema::nodes;
+    use network_protocol::{error, info, warn};
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    /// Runs the bus, the nodes flush their logs between the steps like in their main loop.
+    /// Returns the messages read by the brain, the first node.
+    fn run(
+        bus: &mut VirtualBus,
+        nodes: &mut [Protocol],
+        loggers: &mut [BusLogger<8>],
+    ) -> Vec<Vec<u8>> {
+        let mut received = Vec::new();
+        for _ in 0..500 {
+            for (logger, node) in loggers.iter_mut().zip(nodes[1..].iter_mut()) {
+                logger.flush(node).unwrap();
+            }
+            let sent = bus.step(nodes);
+            while let Some(message) = nodes[0].received.front() {
+                received.push(message.to_vec());
+                nodes[0].received.pop_front();
+            }
+            if sent == 0 && loggers.iter().all(|logger| logger.is_empty()) {
+                return received;
+            }
+        }
+        panic!("the bus is still busy");
+    }
+
+    /// Records the bus as seen by a sniffer with id 0
+    fn record(bus: &VirtualBus) -> Vec<u8> {
+        let mut recorder = Recorder::new(
+            VecWriter::default(),
+            &[
+                NodeInfo::new(id(nodes::BRAIN), "brain"),
+                NodeInfo::new(id(nodes::BASE_ROULANTE), "base"),
+            ],
+        )
+        .unwrap();
+        for (timestamp, _, data) in &bus.log {
+            recorder
+                .record(&RecordedFrame {
+                    timestamp: *timestamp,
+                    node: id(0),
+                    direction: Direction::Rx,
+                    frame: CanFrame::Standard(*data),
+                })
+                .unwrap();
+        }
+        recorder.into_inner().buff
+    }
+
+    #[test]
+    fn logs_of_every_node_reach_the_viewer() {
+        let brain = id(nodes::BRAIN);
+        let mut nodes = [
+            Protocol::new(brain).unwrap(),
+            Protocol::new(id(nodes::BASE_ROULANTE)).unwrap(),
+            Protocol::new(id(nodes::HERKULEX)).unwrap(),
+        ];
+        let mut loggers = [
+            BusLogger::new(brain, Level::Info),
+            BusLogger::new(brain, Level::Info),
+        ];
+        let mut bus = VirtualBus::new();
+
+        info!(loggers[0], "odometry ready");
+        warn!(loggers[1], "servo {} at {}°C", 2, 71);
+        info!(
+            loggers[0],
+            "a long message which takes several packets to be sent"
+        );
+        error!(loggers[1], "servo {} stopped", 2);
+        run(&mut bus, &mut nodes, &mut loggers);
+
+        let raw = record(&bus);
+        let recording = Recording::parse(&raw).unwrap();
+        let names = node_names(&recording).unwrap();
+        let lines: Vec<String> = collect_logs(&recording, brain)
+            .unwrap()
+            .iter()
+            .map(|line| format_line(line, &names))
+            .map(|line| line[12..].to_string())
+            .collect();
+        let tag = "logging::logging_tests";
+        let mut expected = vec![
+            format!("base         INFO  {}: odometry ready", tag),
+            format!("node 3       WARN  {}: servo 2 at 71°C", tag),
+            format!(
+                "base         INFO  {}: a long message which takes several packets to be sent",
+                tag
+            ),
+            format!("node 3       ERROR {}: servo 2 stopped", tag),
+        ];
+        // The nodes send at the same time, only the order of each node is kept
+        let mut sorted = lines.clone();
+        sorted.sort();
+        expected.sort();
+        assert_eq!(sorted, expected);
+        assert!(
+            lines.iter().position(|l| l.contains("odometry"))
+                < lines.iter().position(|l| l.contains("long"))
+        );
+    }
+
+    #[test]
+    fn logs_wait_for_the_other_messages() {
+        let brain = id(nodes::BRAIN);
+        let mut nodes = [
+            Protocol::new(brain).unwrap(),
+            Protocol::new(id(nodes::BASE_ROULANTE)).unwrap(),
+        ];
+        let mut loggers = [BusLogger::new(brain, Level::Info)];
+        let mut bus = VirtualBus::new();
+
+        info!(loggers[0], "starting");
+        nodes[1]
+            .add_message_to_send_buff(
+                Message::new(MessageId::new(0).unwrap(), brain, id(2), &[1; 12]).unwrap(),
+            )
+            .unwrap();
+        let received = run(&mut bus, &mut nodes, &mut loggers);
+
+        let commands: Vec<u8> = received.iter().map(|message| message[0]).collect();
+        assert_eq!(commands, [1, network_protocol::log::LOG_COMMAND]);
+    }
+
+    #[test]
+    fn full_logger_reports_the_lost_records() {
+        let brain = id(nodes::BRAIN);
+        let mut nodes = [
+            Protocol::new(brain).unwrap(),
+            Protocol::new(id(nodes::BASE_ROULANTE)).unwrap(),
+        ];
+        let mut loggers = [BusLogger::new(brain, Level::Info)];
+        let mut bus = VirtualBus::new();
+
+        for i in 0..10 {
+            info!(loggers[0], "record {}", i);
+        }
+        run(&mut bus, &mut nodes, &mut loggers);
+        info!(loggers[0], "back");
+        run(&mut bus, &mut nodes, &mut loggers);
+
+        let raw = record(&bus);
+        let recording = Recording::parse(&raw).unwrap();
+        let lines = collect_logs(&recording, brain).unwrap();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[8].text, "back");
+        assert_eq!(lines[8].dropped, 2);
+        assert!(format_line(&lines[8], &node_names(&recording).unwrap())
+            .ends_with("back (2 records lost before)"));
+    }
+}
@@ -0,0 +1,72 @@
+mod common;
+
+#[cfg(test)]
+mod multicast_tests {
+    use crate::common::VirtualBus;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{CanId, MessageId, BROADCAST_ID};
+
+    const EMERGENCY_STOP: [u8; 1] = [0xE5];
+    const ACTUATORS: usize = 12;
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    /// The brain (1) and three boards, 2 and 3 drive actuators
+    fn nodes() -> [Protocol; 4] {
+        let mut nodes = [1, 2, 3, 4].map(|v| Protocol::new(id(v)).unwrap());
+        nodes[1].join_group(id(ACTUATORS)).unwrap();
+        nodes[2].join_group(id(ACTUATORS)).unwrap();
+        nodes
+    }
+
+    #[test]
+    fn broadcast_reaches_every_node_in_one_frame() {
+        let mut nodes = nodes();
+        nodes[0]
+            .add_message_to_send_buff(
+                Message::datagram(id(BROADCAST_ID), id(1), &EMERGENCY_STOP).unwrap(),
+            )
+            .unwrap();
+        let mut bus = VirtualBus::new();
+        bus.run_until_idle(&mut nodes, 10);
+
+        for node in &nodes[1..] {
+            assert_eq!(node.received.len(), 1);
+            assert_eq!(node.received[0][0], EMERGENCY_STOP[0]);
+        }
+        assert_eq!(bus.log.len(), 1);
+    }
+
+    #[test]
+    fn group_reaches_its_members_only() {
+        let mut nodes = nodes();
+        nodes[0]
+            .add_message_to_send_buff(Message::datagram(id(ACTUATORS), id(1), &[1, 2]).unwrap())
+            .unwrap();
+        let mut bus = VirtualBus::new();
+        bus.run_until_idle(&mut nodes, 10);
+
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_eq!(nodes[2].received.len(), 1);
+        assert!(nodes[3].received.is_empty());
+        assert_eq!(bus.log.len(), 1);
+    }
+
+    #[test]
+    fn unicast_traffic_is_unchanged() {
+        let mut nodes = nodes();
+        nodes[0]
+            .add_message_to_send_buff(
+                Message::new(MessageId::new(0).unwrap(), id(3), id(1), &[7; 20]).unwrap(),
+            )
+            .unwrap();
+        let mut bus = VirtualBus::new();
+        bus.run_until_idle(&mut nodes, 50);
+        assert!(nodes[1].received.is_empty());
+        assert_eq!(nodes[2].received.len(), 1);
+        assert!(nodes[3].received.is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+mod common;
+
+#[cfg(test)]
+mod nack_tests {
+    use crate::common::VirtualBus;
+    use network_protocol::protocol::control::NackReason;
+    use network_protocol::protocol::errors::SendError;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{CanId, MessageId};
+
+    const PAYLOAD: [u8; 20] = [7; 20];
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    fn message(id_message: usize, data: &[u8]) -> Message {
+        Message::new(MessageId::new(id_message).unwrap(), id(2), id(1), data).unwrap()
+    }
+
+    fn nodes() -> [Protocol; 2] {
+        [Protocol::new(id(1)).unwrap(), Protocol::new(id(2)).unwrap()]
+    }
+
+    #[test]
+    fn rejected_message_is_not_sent_again() {
+        let mut nodes = nodes();
+        nodes[1].message_filter = Some(|data| match data[0] {
+            0xFF => Err(NackReason::UnknownCommand),
+            _ => Ok(()),
+        });
+        nodes[0]
+            .add_message_to_send_buff(message(0, &[0xFF, 1, 2]))
+            .unwrap();
+        nodes[0]
+            .add_message_to_send_buff(message(1, &PAYLOAD))
+            .unwrap();
+
+        let mut bus = VirtualBus::new();
+        bus.run_until_idle(&mut nodes, 100);
+
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_eq!(&nodes[1].received[0][..PAYLOAD.len()], &PAYLOAD);
+        assert_eq!(nodes[0].send_errors.len(), 1);
+        assert_eq!(nodes[0].send_errors[0].id, MessageId::new(0).unwrap());
+        assert_eq!(
+            nodes[0].send_errors[0].error,
+            SendError::Rejected(NackReason::UnknownCommand)
+        );
+        // the rejected message went once on the bus
+        let data_frames = bus.log.iter().filter(|(_, sender, _)| *sender == 0).count();
+        assert_eq!(data_frames, 1 + 4);
+    }
+
+    #[test]
+    fn message_is_sent_again_once_the_receiver_has_room() {
+        let mut nodes = nodes();
+        for id_message in 0..8 {
+            nodes[0]
+                .add_message_to_send_buff(message(id_message % 7, &[id_message as u8]))
+                .unwrap();
+            let mut bus = VirtualBus::new();
+            bus.run_until_idle(&mut nodes, 10);
+        }
+        assert!(nodes[1].received.is_full());
+
+        let next = usize::from(nodes[0].next_message_id(id(2)).unwrap());
+        nodes[0]
+            .add_message_to_send_buff(message(next, &PAYLOAD))
+            .unwrap();
+        let mut bus = VirtualBus::new();
+        for _ in 0..20 {
+            bus.step(&mut nodes);
+        }
+        assert_eq!(nodes[0].send_buff.len(), 1);
+        assert!(nodes[0].send_errors.is_empty());
+
+        nodes[1].received.clear();
+        bus.run_until_idle(&mut nodes, 100);
+        assert!(nodes[0].send_buff.is_empty());
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_eq!(&nodes[1].received[0][..PAYLOAD.len()], &PAYLOAD);
+    }
+}
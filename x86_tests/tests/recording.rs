@@ -0,0 +1,177 @@
+mod common;
+
+#[cfg(test)]
+mod recording_tests {
+    use crate::common::{VecWriter, VirtualBus};
+    use network_protocol::protocol::extended::CanFrame;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::recording::{
+        Direction, NodeInfo, RecordedFrame, Recorder, Recording, ReplaySpeed, Replayer,
+    };
+    use network_protocol::protocol::{CanId, MessageId};
+
+    const PAYLOAD: [u8; 20] = [
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20,
+    ];
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    /// Runs a transfer from node 1 to node 2, returns the nodes and the bus with its log
+    fn run_session() -> ([Protocol; 2], VirtualBus) {
+        let mut nodes = [Protocol::new(id(1)).unwrap(), Protocol::new(id(2)).unwrap()];
+        nodes[0]
+            .add_message_to_send_buff(
+                Message::new(MessageId::new(3).unwrap(), id(2), id(1), &PAYLOAD).unwrap(),
+            )
+            .unwrap();
+        let mut bus = VirtualBus::new();
+        bus.run_until_idle(&mut nodes, 100);
+        (nodes, bus)
+    }
+
+    /// Records the transfer as seen by a gateway with id 0, which receives every frame.
+    /// Returns the recording and the message received by node 2.
+    fn record_gateway_session() -> (Vec<u8>, Vec<u8>) {
+        let (nodes, bus) = run_session();
+        let mut recorder = Recorder::new(
+            VecWriter::default(),
+            &[
+                NodeInfo::new(id(0), "gateway"),
+                NodeInfo::new(id(1), "brain"),
+                NodeInfo::new(id(2), "herkulex"),
+            ],
+        )
+        .unwrap();
+        for (timestamp, _, data) in &bus.log {
+            recorder
+                .record(&RecordedFrame {
+                    timestamp: *timestamp,
+                    node: id(0),
+                    direction: Direction::Rx,
+                    frame: CanFrame::Standard(*data),
+                })
+                .unwrap();
+        }
+        let received = nodes[1].received.front().unwrap().to_vec();
+        (recorder.into_inner().buff, received)
+    }
+
+    /// Records the transfer, each node records the frames it sent and the frames it received.
+    /// Returns the recording, the message received by node 2 and the number of frames it
+    /// received.
+    fn record_session() -> (Vec<u8>, Vec<u8>, usize) {
+        let (nodes, bus) = run_session();
+        let mut recorder = Recorder::new(
+            VecWriter::default(),
+            &[
+                NodeInfo::new(id(1), "brain"),
+                NodeInfo::new(id(2), "herkulex"),
+            ],
+        )
+        .unwrap();
+        let mut received_by_2 = 0;
+        for (timestamp, sender, data) in &bus.log {
+            for (i, node) in nodes.iter().enumerate() {
+                let direction = if i == *sender {
+                    Direction::Tx
+                } else {
+                    Direction::Rx
+                };
+                if node.host_id == id(2) && direction == Direction::Rx {
+                    received_by_2 += 1;
+                }
+                recorder
+                    .record(&RecordedFrame {
+                        timestamp: *timestamp,
+                        node: node.host_id,
+                        direction,
+                        frame: CanFrame::Standard(*data),
+                    })
+                    .unwrap();
+            }
+        }
+        let received = nodes[1].received.front().unwrap().to_vec();
+        (recorder.into_inner().buff, received, received_by_2)
+    }
+
+    #[test]
+    fn gateway_recording_replays_into_a_node() {
+        let (raw, received_live) = record_gateway_session();
+        assert_eq!(&received_live[..PAYLOAD.len()], &PAYLOAD);
+
+        let recording = Recording::parse(&raw).unwrap();
+        assert_eq!(recording.nodes().count(), 3);
+
+        let mut node = Protocol::new(id(2)).unwrap();
+        let mut replayer = Replayer::new(&recording, ReplaySpeed::Instant);
+        let fed = replayer.replay_into(0, &mut node).unwrap();
+
+        // The gateway received every frame of the bus, all of them are fed
+        assert_eq!(fed, recording.frame_count());
+        assert!(replayer.is_finished());
+        assert_eq!(node.received.len(), 1);
+        assert_eq!(node.received[0].to_vec(), received_live);
+    }
+
+    #[test]
+    fn replays_into_a_node_with_its_own_capacities() {
+        let (raw, received_live) = record_gateway_session();
+        let recording = Recording::parse(&raw).unwrap();
+
+        let mut node = Protocol::<2, 1, 4>::builder(id(2))
+            .max_queued_messages(2)
+            .build()
+            .unwrap();
+        let mut replayer = Replayer::new(&recording, ReplaySpeed::Instant);
+        replayer.replay_into(0, &mut node).unwrap();
+
+        assert_eq!(node.received.len(), 1);
+        assert_eq!(node.received[0].to_vec(), received_live);
+    }
+
+    #[test]
+    fn replayed_session_gives_the_same_messages() {
+        let (raw, received_live, received_frames) = record_session();
+        assert_eq!(&received_live[..PAYLOAD.len()], &PAYLOAD);
+
+        let recording = Recording::parse(&raw).unwrap();
+        assert_eq!(recording.nodes().count(), 2);
+
+        let mut node = Protocol::new(id(2)).unwrap();
+        let mut replayer = Replayer::new(&recording, ReplaySpeed::Instant);
+        let fed = replayer.replay_node_into(0, id(2), &mut node).unwrap();
+
+        // Only the frames node 2 received, not its own ACKs nor the copies of node 1
+        assert_eq!(fed, received_frames);
+        assert!(replayer.is_finished());
+        assert_eq!(node.received.len(), 1);
+        assert_eq!(node.received[0].to_vec(), received_live);
+        // the replayed node ACKs the frames just like the live one did
+        assert!(!node.acks_to_send.is_empty());
+    }
+
+    #[test]
+    fn accelerated_replay_respects_the_recorded_pace() {
+        let (raw, _, received_frames) = record_session();
+        let recording = Recording::parse(&raw).unwrap();
+        // Both nodes recorded every frame of the bus
+        let total = recording.frame_count() / 2;
+
+        let mut node = Protocol::new(id(2)).unwrap();
+        let mut replayer = Replayer::new(&recording, ReplaySpeed::Accelerated(4));
+        let mut now = 0;
+        let mut fed = replayer.replay_node_into(now, id(2), &mut node).unwrap();
+        assert_eq!(fed, 1);
+        while !replayer.is_finished() {
+            now += 250;
+            fed += replayer.replay_node_into(now, id(2), &mut node).unwrap();
+        }
+        assert_eq!(fed, received_frames);
+        assert_eq!(node.received.len(), 1);
+        // frames are 1ms apart on the bus so a 4 times faster replay lasts a quarter of it
+        assert_eq!(now, (total as u32 - 1) * 250);
+    }
+}
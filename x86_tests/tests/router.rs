@@ -0,0 +1,250 @@
+#[cfg(test)]
+mod router_tests {
+    use network_protocol::protocol::errors::RoutingError;
+    use network_protocol::protocol::extended::CanFrame;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::packet::Packet;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::router::{InterfaceId, Router};
+    use network_protocol::protocol::{CanId, MessageId, BROADCAST_ID, CAN_PACKET_SIZE};
+    use std::collections::VecDeque;
+
+    type Frame = [u8; CAN_PACKET_SIZE];
+
+    /// Time to send a frame, in microseconds
+    const STEP_TIME: u32 = 1_000;
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    /// The ACK timeout is one step, shorter than a round trip through the gateway, so the
+    /// packets are sent again before their ACK comes back
+    fn node(v: usize) -> Protocol {
+        Protocol::builder(id(v))
+            .ack_timeout(STEP_TIME)
+            .build()
+            .unwrap()
+    }
+
+    /// A gateway node joining buses, its interface `i` is on the bus `buses[i]`
+    struct Gateway {
+        node: Protocol,
+        router: Router,
+        buses: Vec<usize>,
+        outboxes: Vec<VecDeque<Frame>>,
+        dropped: Vec<RoutingError>,
+    }
+
+    impl Gateway {
+        fn new(host_id: usize, buses: &[usize], routes: &[(usize, InterfaceId)]) -> Self {
+            let mut router = Router::new(id(host_id), buses.len() as u8).unwrap();
+            for (node, interface) in routes {
+                router.add_route(id(*node), *interface).unwrap();
+            }
+            Gateway {
+                node: node(host_id),
+                router,
+                buses: buses.to_vec(),
+                outboxes: vec![VecDeque::new(); buses.len()],
+                dropped: Vec::new(),
+            }
+        }
+    }
+
+    /// Virtual buses joined by gateways, a frame is seen by everyone on its bus
+    struct Network {
+        buses: Vec<Vec<Protocol>>,
+        gateways: Vec<Gateway>,
+        /// Frames sent on each bus
+        traffic: Vec<usize>,
+        time: u32,
+    }
+
+    impl Network {
+        fn new(buses: Vec<Vec<Protocol>>, gateways: Vec<Gateway>) -> Self {
+            let traffic = vec![0; buses.len()];
+            Network {
+                buses,
+                gateways,
+                traffic,
+                time: 0,
+            }
+        }
+
+        fn send(
+            &mut self,
+            bus: usize,
+            sender: Option<usize>,
+            gateway: Option<usize>,
+            frame: Frame,
+        ) {
+            self.traffic[bus] += 1;
+            for (i, node) in self.buses[bus].iter_mut().enumerate() {
+                if Some(i) != sender {
+                    node.process_raw_packet(frame).unwrap();
+                }
+            }
+            for (g, gw) in self.gateways.iter_mut().enumerate() {
+                let Some(interface) = gw.buses.iter().position(|b| *b == bus) else {
+                    continue;
+                };
+                if Some(g) == gateway {
+                    continue;
+                }
+                gw.node.process_raw_packet(frame).unwrap();
+                match gw.router.route(&CanFrame::Standard(frame), interface as u8) {
+                    Ok(interfaces) => {
+                        for out in interfaces.iter() {
+                            gw.outboxes[out as usize].push_back(frame);
+                        }
+                    }
+                    Err(error) => gw.dropped.push(error),
+                }
+            }
+        }
+
+        /// Every node and every gateway interface sends at most one frame
+        fn step(&mut self) -> usize {
+            self.time += STEP_TIME;
+            let time = self.time;
+            self.buses
+                .iter_mut()
+                .flatten()
+                .for_each(|node| node.poll(time));
+            self.gateways.iter_mut().for_each(|gw| gw.node.poll(time));
+            let mut sent = 0;
+            for bus in 0..self.buses.len() {
+                for sender in 0..self.buses[bus].len() {
+                    if let Some(frame) = self.buses[bus][sender].get_next_packet_to_send().unwrap()
+                    {
+                        sent += 1;
+                        self.send(bus, Some(sender), None, frame);
+                    }
+                }
+            }
+            for g in 0..self.gateways.len() {
+                let gw = &mut self.gateways[g];
+                if let Some(frame) = gw.node.get_next_packet_to_send().unwrap() {
+                    let dest = Packet::try_from(&frame).unwrap().header.id_dest;
+                    for out in gw.router.interfaces_for(dest).unwrap().iter() {
+                        gw.outboxes[out as usize].push_back(frame);
+                    }
+                }
+                for interface in 0..self.gateways[g].buses.len() {
+                    if let Some(frame) = self.gateways[g].outboxes[interface].pop_front() {
+                        sent += 1;
+                        let bus = self.gateways[g].buses[interface];
+                        self.send(bus, None, Some(g), frame);
+                    }
+                }
+            }
+            sent
+        }
+
+        fn run_until_idle(&mut self, max_steps: usize) {
+            for _ in 0..max_steps {
+                if self.step() == 0 {
+                    return;
+                }
+            }
+            panic!("the network is still busy after {} steps", max_steps);
+        }
+    }
+
+    fn message(id_message: usize, dest: usize, src: usize, data: &[u8]) -> Message {
+        Message::new(MessageId::new(id_message).unwrap(), id(dest), id(src), data).unwrap()
+    }
+
+    /// Nodes 1 and 2 on the bus 0, nodes 3 and 4 on the bus 1, gateway 5 between them
+    fn two_buses() -> Network {
+        Network::new(
+            vec![vec![node(1), node(2)], vec![node(3), node(4)]],
+            vec![Gateway::new(5, &[0, 1], &[(1, 0), (2, 0), (3, 1), (4, 1)])],
+        )
+    }
+
+    #[test]
+    fn message_crosses_the_gateway_and_is_acknowledged() {
+        let mut network = two_buses();
+        let data: Vec<u8> = (0..40).collect();
+        network.buses[0][0]
+            .add_message_to_send_buff(message(0, 3, 1, &data))
+            .unwrap();
+        network.run_until_idle(100);
+
+        // The packets are sent again while their ACK is crossing the gateway, the last one
+        // included, the message is still delivered once
+        let received = &network.buses[1][0].received;
+        assert_eq!(received.len(), 1);
+        assert_eq!(&received.front_message().unwrap().data[..40], &data[..]);
+        // The ACKs came back through the gateway, nothing is left to send
+        assert!(network.buses[0][0].send_buff.is_empty());
+        assert!(network.buses[0][0].send_errors.is_empty());
+        assert!(network.gateways[0].dropped.is_empty());
+        assert!(network.buses[1][1].received.is_empty());
+    }
+
+    #[test]
+    fn local_traffic_stays_on_its_bus() {
+        let mut network = two_buses();
+        network.buses[1][0]
+            .add_message_to_send_buff(message(0, 4, 3, &[7; 10]))
+            .unwrap();
+        network.run_until_idle(100);
+
+        assert_eq!(network.buses[1][1].received.len(), 1);
+        assert_eq!(network.traffic[0], 0);
+    }
+
+    #[test]
+    fn gateway_node_reaches_both_buses() {
+        let mut network = two_buses();
+        network.gateways[0]
+            .node
+            .add_message_to_send_buff(Message::datagram(id(BROADCAST_ID), id(5), &[1, 2]).unwrap())
+            .unwrap();
+        network.buses[0][1]
+            .add_message_to_send_buff(message(1, 5, 2, &[3]))
+            .unwrap();
+        network.run_until_idle(100);
+
+        for bus in &network.buses {
+            for node in bus {
+                assert_eq!(node.received.len(), 1);
+            }
+        }
+        assert_eq!(network.gateways[0].node.received.len(), 1);
+        assert!(network.buses[0][1].send_buff.is_empty());
+        assert_eq!(network.traffic[1], 1);
+    }
+
+    #[test]
+    fn redundant_gateways_cant_make_frames_loop() {
+        let routes = [(1, 0), (3, 1)];
+        let mut network = Network::new(
+            vec![vec![node(1)], vec![node(3)]],
+            vec![
+                Gateway::new(5, &[0, 1], &routes),
+                Gateway::new(6, &[0, 1], &routes),
+            ],
+        );
+        network.buses[0][0]
+            .add_message_to_send_buff(Message::datagram(id(BROADCAST_ID), id(1), &[9]).unwrap())
+            .unwrap();
+        network.run_until_idle(100);
+
+        // Each gateway forwarded it once, then dropped the copy forwarded by the other one
+        assert_eq!(network.buses[1][0].received.len(), 2);
+        assert_eq!(network.traffic, [1, 2]);
+        for gateway in &network.gateways {
+            assert_eq!(
+                gateway.dropped,
+                [RoutingError::WrongInterface {
+                    src: id(1),
+                    interface: 1
+                }]
+            );
+        }
+    }
+}
@@ -0,0 +1,101 @@
+mod common;
+
+#[cfg(test)]
+mod schema_tests {
+    use crate::common::VirtualBus;
+    use network_protocol::protocol::control::NackReason;
+    use network_protocol::protocol::dispatcher::{Dispatcher, Reply};
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{CanId, BROADCAST_ID};
+    use network_protocol::schema::{nodes, Heartbeat, SchemaError, SchemaMessage, SetSpeed};
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    /// What the brain knows about the other nodes
+    #[derive(Default)]
+    struct Brain {
+        mismatches: Vec<(CanId, SchemaError)>,
+    }
+
+    fn heartbeat(
+        brain: &mut Brain,
+        src: CanId,
+        payload: &[u8],
+        _: &mut Reply,
+    ) -> Result<(), NackReason> {
+        let heartbeat = Heartbeat::decode(payload).map_err(|_| NackReason::MalformedPayload)?;
+        if let Err(error) = heartbeat.check() {
+            brain.mismatches.push((src, error));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn outdated_firmware_is_caught_by_its_heartbeat() {
+        let mut nodes = [
+            Protocol::new(id(nodes::BRAIN)).unwrap(),
+            Protocol::new(id(nodes::BASE_ROULANTE)).unwrap(),
+            Protocol::new(id(nodes::HERKULEX)).unwrap(),
+        ];
+        let mut bus = VirtualBus::new();
+        let mut dispatcher: Dispatcher<Brain, 2> = Dispatcher::new();
+        dispatcher.register(Heartbeat::COMMAND, heartbeat).unwrap();
+        let mut brain = Brain::default();
+
+        nodes[1]
+            .add_message_to_send_buff(Heartbeat::datagram(id(nodes::BASE_ROULANTE)).unwrap())
+            .unwrap();
+        // built before the last change of the schema
+        let outdated = Heartbeat { schema_hash: 42 }.encode();
+        nodes[2]
+            .add_message_to_send_buff(
+                Message::datagram(id(BROADCAST_ID), id(nodes::HERKULEX), &outdated).unwrap(),
+            )
+            .unwrap();
+        bus.run_until_idle(&mut nodes, 10);
+        assert_eq!(dispatcher.dispatch(&mut nodes[0], &mut brain), Ok(2));
+
+        assert_eq!(brain.mismatches.len(), 1);
+        let (node, error) = brain.mismatches[0];
+        assert_eq!(node, id(nodes::HERKULEX));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "schema 0x0000002a doesn't match ours {:#010x}",
+                network_protocol::schema::SCHEMA_HASH
+            )
+        );
+    }
+
+    #[test]
+    fn host_tools_decode_what_the_firmware_sends() {
+        let mut nodes = [
+            Protocol::new(id(nodes::BRAIN)).unwrap(),
+            Protocol::new(id(nodes::BASE_ROULANTE)).unwrap(),
+        ];
+        let mut bus = VirtualBus::new();
+        let command = SchemaMessage::SetSpeed(SetSpeed {
+            left: 300,
+            right: -300,
+        });
+        nodes[0]
+            .add_message_to_send_buff(
+                Message::new(
+                    network_protocol::protocol::MessageId::new(0).unwrap(),
+                    id(nodes::BASE_ROULANTE),
+                    id(nodes::BRAIN),
+                    &command.to_vec(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        bus.run_until_idle(&mut nodes, 10);
+
+        let received = SchemaMessage::decode(&nodes[1].received[0]).unwrap();
+        assert_eq!(received, command);
+        assert_eq!(received.name(), "set_speed");
+    }
+}
@@ -0,0 +1,100 @@
+mod common;
+
+#[cfg(test)]
+mod session_epochs_tests {
+    use crate::common::VirtualBus;
+    use network_protocol::protocol::message::Message;
+    use network_protocol::protocol::protocol::Protocol;
+    use network_protocol::protocol::{CanId, MessageId};
+
+    const LONG: [u8; 30] = [0xAA; 30];
+    const SHORT: [u8; 4] = [1, 2, 3, 4];
+
+    fn id(v: usize) -> CanId {
+        CanId::new(v).unwrap()
+    }
+
+    fn message(id_message: usize, dest: usize, src: usize, data: &[u8]) -> Message {
+        Message::new(MessageId::new(id_message).unwrap(), id(dest), id(src), data).unwrap()
+    }
+
+    fn boot(host: usize, epoch: u16, peers: &[CanId]) -> Protocol {
+        let mut protocol = Protocol::new(id(host)).unwrap();
+        protocol.announce_epoch(epoch, peers).unwrap();
+        protocol
+    }
+
+    fn nodes() -> [Protocol; 2] {
+        let mut nodes = [boot(1, 1, &[id(2)]), boot(2, 1, &[id(1)])];
+        VirtualBus::new().run_until_idle(&mut nodes, 10);
+        nodes
+    }
+
+    #[test]
+    fn sender_reboots_in_the_middle_of_a_transfer() {
+        let mut nodes = nodes();
+        let mut bus = VirtualBus::new();
+        nodes[0]
+            .add_message_to_send_buff(message(0, 2, 1, &LONG))
+            .unwrap();
+        for _ in 0..3 {
+            bus.step(&mut nodes);
+        }
+        assert_eq!(nodes[1].received.in_progress().count(), 1);
+
+        // the message id counter of the sender restarts at 0
+        nodes[0] = boot(1, 2, &[id(2)]);
+        nodes[0]
+            .add_message_to_send_buff(message(0, 2, 1, &SHORT))
+            .unwrap();
+        bus.run_until_idle(&mut nodes, 100);
+
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_eq!(&nodes[1].received[0][..SHORT.len()], &SHORT);
+        assert_eq!(nodes[1].received.in_progress().count(), 0);
+    }
+
+    #[test]
+    fn receiver_reboots_in_the_middle_of_a_transfer() {
+        let mut nodes = nodes();
+        let mut bus = VirtualBus::new();
+        nodes[0]
+            .add_message_to_send_buff(message(0, 2, 1, &LONG))
+            .unwrap();
+        for _ in 0..3 {
+            bus.step(&mut nodes);
+        }
+
+        nodes[1] = boot(2, 2, &[id(1)]);
+        bus.run_until_idle(&mut nodes, 100);
+
+        // the whole message was sent again instead of only its end
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_eq!(&nodes[1].received[0][..LONG.len()], &LONG);
+        assert!(nodes[0].send_buff.is_empty());
+    }
+
+    #[test]
+    fn lost_announce_is_sent_again() {
+        let mut nodes = nodes();
+        let mut bus = VirtualBus::new();
+        nodes[0]
+            .add_message_to_send_buff(message(0, 2, 1, &LONG))
+            .unwrap();
+        bus.step(&mut nodes);
+
+        nodes[1] = boot(2, 2, &[id(1)]);
+        // the announce never reaches node 1
+        bus.step_with(&mut nodes, |sender, _| sender != 1);
+        for _ in 0..5 {
+            bus.step(&mut nodes);
+        }
+        assert!(nodes[1].received.is_empty());
+        assert_eq!(nodes[1].pending_epoch_acks, [id(1)]);
+
+        nodes[1].resend_epoch_announces().unwrap();
+        bus.run_until_idle(&mut nodes, 100);
+        assert_eq!(nodes[1].received.len(), 1);
+        assert_eq!(&nodes[1].received[0][..LONG.len()], &LONG);
+    }
+}